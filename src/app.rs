@@ -1,13 +1,31 @@
-use std::collections::HashMap;
-use std::sync::mpsc::{self, Receiver};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread;
+use std::time::Instant;
 
 use eframe::egui;
 
-use crate::commands::command_exists;
-use crate::ddc::{VCP_BRIGHTNESS, VCP_CONTRAST, read_vcp};
+use crate::backlight;
+use crate::brightness::{BrightnessControl, Extended, ExtendedRange};
+use crate::commands::{CommandRunner, SystemRunner};
+use crate::config::{self, Config, PercentRange};
+use crate::ddc::{
+    COLOR_CODES, Ddc, PowerMode, VCP_AUDIO_MUTE, VCP_AUDIO_VOLUME, VCP_BRIGHTNESS, VCP_CONTRAST,
+    VCP_INPUT_SOURCE, VCP_POWER_MODE, VcpValue, WriteOutcome, input_name, is_muted, mccs,
+    mute_value,
+};
 use crate::display::{Display, enumerate_displays};
-use crate::gamma::{self, GammaBackend, map_drm_to_xrandr};
+use crate::error::Error;
+use crate::gamma::{self, GammaBackend, OutputGeometry, SessionType};
+use crate::hotplug::{self, Connected};
+use crate::ipc::{self, Client, Reading, Route};
+use crate::tray::{Tray, TrayDisplay, TrayEvent};
+use crate::worker::{Action, Completion, DisplayWorker, route_writer};
 
 pub enum InitState {
     Pending, // Not started yet (no ctx available)
@@ -22,45 +40,262 @@ pub struct InitResult {
     pub displays: Vec<Display>,
     pub gamma_backend: Option<Box<dyn GammaBackend>>,
     pub gamma_output_map: HashMap<String, String>,
-    pub brightness: u8,
-    pub contrast: u8,
+    pub positions: HashMap<String, OutputGeometry>,
+    pub brightness: VcpValue,
+    pub contrast: VcpValue,
+    pub feature_values: HashMap<u8, VcpValue>,
+    pub power_modes: HashMap<String, PowerMode>,
+    pub display_values: HashMap<(String, u8), VcpValue>,
+    /// Index of the display to start with, the one picked last time.
+    pub selected: usize,
+    /// Displays and their values came from a running daemon.
+    pub daemon: bool,
+    /// Connectors as they were before detection, for the hotplug watcher
+    /// to compare against.
+    pub connected: Connected,
     pub error: Option<String>,
 }
 
+/// What a background read is for, and so what its values update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadPurpose {
+    /// The selected display, after switching to it or pressing Refresh.
+    Refresh,
+    /// Brightness and contrast of every display for the dashboard.
+    Dashboard,
+    /// Native ranges of linked displays for "All displays" mode.
+    Linked,
+    /// Every readable feature the selected display advertises.
+    Features,
+    /// The selected display's color preset, gains and black levels.
+    Color,
+}
+
+/// One display's values, read off the UI thread.
+pub struct DisplayRead {
+    /// The display's key, which unlike its id survives a rescan.
+    pub display_key: String,
+    pub power: Option<PowerMode>,
+    pub values: Vec<(u8, Result<VcpValue, Error>)>,
+    /// Input, volume and mute on a refresh, or the features asked for.
+    pub controls: Option<HashMap<u8, VcpValue>>,
+}
+
+impl DisplayRead {
+    /// Read what `purpose` needs; `asleep` is the power state last seen.
+    fn read(route: &Route, display: &Display, asleep: bool, purpose: ReadPurpose) -> Self {
+        let mut read = Self {
+            display_key: display.key().to_string(),
+            power: None,
+            values: Vec::new(),
+            controls: None,
+        };
+
+        // The monitor may have been woken by its own button since we last looked
+        if purpose == ReadPurpose::Refresh && display.supports(VCP_POWER_MODE) {
+            read.power = read_power_mode(route, display);
+        }
+        // Sleeping monitors usually stop answering DDC, which is expected
+        // rather than an error worth reporting
+        if read.power.map_or(asleep, |m| !m.is_on()) {
+            return read;
+        }
+        match purpose {
+            ReadPurpose::Features => {
+                let codes: Vec<u8> = display
+                    .capabilities
+                    .iter()
+                    .flat_map(|caps| caps.features.keys().copied())
+                    .collect();
+                read.controls = Some(read_features(route, display, &codes));
+                return read;
+            }
+            ReadPurpose::Color => {
+                read.controls = Some(read_features(route, display, &COLOR_CODES));
+                return read;
+            }
+            _ => {}
+        }
+
+        for code in [VCP_BRIGHTNESS, VCP_CONTRAST] {
+            read.values.push((code, route.read_vcp(display, code)));
+        }
+        if purpose == ReadPurpose::Refresh {
+            read.controls = Some(read_control_values(route, display));
+        }
+        read
+    }
+}
+
 pub struct App {
     pub displays: Vec<Display>,
     pub selected_display: usize,
 
-    pub brightness: u8,
-    pub contrast: u8,
-    pub gamma_dimming: u8,
+    pub brightness: VcpValue,
+    pub contrast: VcpValue,
+    /// Software dimming per display id; displays missing here are at 100%.
+    pub gamma_levels: HashMap<String, u8>,
+    /// Extended-range slider position while it's being dragged.
+    pub extended_drag: Option<u8>,
+    /// Software dimming slider positions being dragged, per display id.
+    pub dimming_drag: HashMap<String, u8>,
+    /// Drive brightness and contrast of every linked display together.
+    pub all_displays: bool,
+    /// Shared slider positions in "All displays" mode, on a 0-100 scale.
+    pub linked_brightness: VcpValue,
+    pub linked_contrast: VcpValue,
+    /// Last known values of the selected display's advanced features.
+    pub feature_values: HashMap<u8, VcpValue>,
+    /// Destructive write-only feature waiting for its Run to be confirmed.
+    pub confirm_feature: Option<u8>,
+    /// Power state per display id; displays missing here are assumed on.
+    pub power_modes: HashMap<String, PowerMode>,
 
+    pub config: Config,
+    pub config_path: Option<PathBuf>,
+    /// Remembered values not yet written to the config file.
+    config_dirty: bool,
+
+    pub runner: Arc<dyn CommandRunner>,
+    /// Runs the workers' writes, apart from `runner` so abandoning reads
+    /// never kills a write still on its way to the monitor.
+    write_runner: Arc<dyn CommandRunner>,
+    /// Runs reads asked for on the selected display, so switching away
+    /// abandons those without cutting the dashboard's reads short.
+    refresh_runner: Arc<dyn CommandRunner>,
+    pub ddc: Ddc,
     pub ddcutil_available: bool,
     pub gamma_backend: Option<Box<dyn GammaBackend>>,
     pub gamma_output_map: HashMap<String, String>,
+    /// Desktop position of each display id, for laying out the dashboard.
+    pub positions: HashMap<String, OutputGeometry>,
 
     pub status: Option<String>,
     pub error: Option<String>,
 
     pub init_state: InitState,
     pub init_receiver: Option<Receiver<InitResult>>,
+
+    /// Connector changes seen by the hotplug watcher.
+    hotplug: Option<hotplug::Watcher>,
+    /// Connectors changed; detect again once no writes are in flight.
+    rescan_pending: bool,
+    rescan_receiver: Option<Receiver<InitResult>>,
+
+    /// Where to look for the daemon; cleared after init if none was running.
+    pub daemon_socket: Option<PathBuf>,
+    /// Changes other frontends made through the daemon.
+    daemon_changes: Option<Receiver<Reading>>,
+
+    /// Background writers keyed by display id, started on first use.
+    workers: HashMap<String, DisplayWorker>,
+    completion_sender: Sender<Completion>,
+    completions: Receiver<Completion>,
+    writes_in_flight: Arc<AtomicUsize>,
+    /// Lets workers wake the UI when a write finishes.
+    repaint: Option<egui::Context>,
+    /// When each display last got a live slider update, for throttling.
+    last_preview: HashMap<String, Instant>,
+    /// Last known brightness and contrast of every display, keyed by
+    /// display id and VCP code.
+    display_values: HashMap<(String, u8), VcpValue>,
+    /// How the most recent write to each display went.
+    last_outcomes: HashMap<String, WriteOutcome>,
+    read_sender: Sender<(ReadPurpose, Vec<DisplayRead>)>,
+    reads: Receiver<(ReadPurpose, Vec<DisplayRead>)>,
+    reads_in_flight: usize,
+
+    tray: Option<Tray>,
+    /// The desktop has no tray to put an icon in, so rescans don't ask again.
+    tray_unavailable: bool,
+    /// Quit was picked in the tray, so closing really closes.
+    pub quitting: bool,
+    tray_sender: Sender<TrayEvent>,
+    tray_events: Receiver<TrayEvent>,
+    /// What the tray menu currently shows, to rebuild it only on change.
+    tray_displays: Vec<TrayDisplay>,
 }
 
 impl App {
     pub fn new() -> Self {
+        let config_path = config::default_path();
+        let (config, error) = match config_path.as_deref().map(Config::load) {
+            Some(Ok(config)) => (config, None),
+            Some(Err(e)) => (
+                Config::default(),
+                Some(format!("Failed to load config: {}", e)),
+            ),
+            None => (Config::default(), None),
+        };
+
+        let runner = Arc::new(SystemRunner::new(config.commands.timeout()));
+        let mut app = Self::with_runner(runner, true);
+        app.write_runner = Arc::new(SystemRunner::new(config.commands.timeout()));
+        app.refresh_runner = Arc::new(SystemRunner::new(config.commands.timeout()));
+        app.ddc = app.ddc.with_policy(config.writes.policy());
+        app.config = config;
+        app.config_path = config_path;
+        app.daemon_socket = ipc::socket_path();
+        app.error = error;
+        app
+    }
+
+    pub fn with_runner(runner: Arc<dyn CommandRunner>, native_i2c: bool) -> Self {
+        let (completion_sender, completions) = mpsc::channel();
+        let (read_sender, reads) = mpsc::channel();
+        let (tray_sender, tray_events) = mpsc::channel();
+
         Self {
             displays: Vec::new(),
             selected_display: 0,
-            brightness: 50,
-            contrast: 50,
-            gamma_dimming: 100,
+            brightness: VcpValue::new(50, 100),
+            contrast: VcpValue::new(50, 100),
+            gamma_levels: HashMap::new(),
+            extended_drag: None,
+            dimming_drag: HashMap::new(),
+            all_displays: false,
+            linked_brightness: VcpValue::new(50, 100),
+            linked_contrast: VcpValue::new(50, 100),
+            feature_values: HashMap::new(),
+            confirm_feature: None,
+            power_modes: HashMap::new(),
+            config: Config::default(),
+            config_path: None,
+            config_dirty: false,
+            ddc: Ddc::new(runner.clone(), native_i2c),
+            write_runner: runner.clone(),
+            refresh_runner: runner.clone(),
+            runner,
             ddcutil_available: false,
             gamma_backend: None,
             gamma_output_map: HashMap::new(),
+            positions: HashMap::new(),
             status: None,
             error: None,
             init_state: InitState::Pending,
             init_receiver: None,
+            hotplug: None,
+            rescan_pending: false,
+            rescan_receiver: None,
+            daemon_socket: None,
+            daemon_changes: None,
+            workers: HashMap::new(),
+            completion_sender,
+            completions,
+            writes_in_flight: Arc::default(),
+            repaint: None,
+            last_preview: HashMap::new(),
+            display_values: HashMap::new(),
+            last_outcomes: HashMap::new(),
+            read_sender,
+            reads,
+            reads_in_flight: 0,
+            tray: None,
+            tray_unavailable: false,
+            quitting: false,
+            tray_sender,
+            tray_events,
+            tray_displays: Vec::new(),
         }
     }
 
@@ -68,37 +303,116 @@ impl App {
         let (tx, rx) = mpsc::channel();
         self.init_receiver = Some(rx);
         self.init_state = InitState::Loading;
+        self.repaint = Some(ctx.clone());
+
+        let runner = self.runner.clone();
+        let ddc = self.ddc.clone();
+        let session = gamma::detect_session();
+        let socket = self.daemon_socket.clone();
+        let selected = self.config.ui.selected.clone();
 
         thread::spawn(move || {
-            let result = Self::init_blocking();
+            let result = Self::init_blocking(
+                runner,
+                &ddc,
+                session,
+                socket.as_deref(),
+                selected.as_deref(),
+            );
             let _ = tx.send(result);
             ctx.request_repaint(); // Wake UI exactly once
         });
     }
 
-    fn init_blocking() -> InitResult {
-        let mut result = InitResult::default();
+    fn init_blocking(
+        runner: Arc<dyn CommandRunner>,
+        ddc: &Ddc,
+        session: SessionType,
+        daemon_socket: Option<&Path>,
+        selected: Option<&str>,
+    ) -> InitResult {
+        let mut result = InitResult {
+            connected: hotplug::connected(&runner.sysfs()),
+            ..InitResult::default()
+        };
 
-        if !command_exists("ddcutil", &["--version"]) {
-            result.error = Some("ddcutil not found. Install with: sudo apt install ddcutil".into());
+        result.ddcutil_available = runner.command_exists("ddcutil", &["--version"]);
+        // A laptop's own panel is driven without ddcutil
+        let has_panel = !backlight::enumerate(&runner.sysfs()).is_empty();
+        if !result.ddcutil_available && !has_panel {
+            result.error = Some(Error::ToolMissing("ddcutil".into()).explain());
             return result;
         }
 
-        result.ddcutil_available = true;
-        result.gamma_backend = gamma::create_backend();
-        result.displays = enumerate_displays();
+        // A running daemon has already done the slow detection
+        let mut daemon = daemon_socket.and_then(|path| Client::connect(path).ok());
+        let detected = match daemon.as_mut() {
+            Some(client) => client
+                .list()
+                .map(|displays| displays.into_iter().map(Display::from).collect()),
+            None => enumerate_displays(runner.as_ref()),
+        };
+        result.displays = match detected {
+            Ok(displays) => displays,
+            Err(e) => {
+                result.error = Some(format!("Could not detect displays: {}", e.explain()));
+                return result;
+            }
+        };
+        result.daemon = daemon.is_some();
+        // The daemon's list carries capabilities it has already read
+        let route = Route::new(
+            ddc.clone(),
+            daemon_socket
+                .filter(|_| result.daemon)
+                .map(Path::to_path_buf),
+        );
+        if !result.daemon {
+            for display in &mut result.displays {
+                display.capabilities = ddc.capabilities(display).ok();
+            }
+        }
+        result.gamma_backend = gamma::create_backend(session, runner);
 
         // Build gamma output map
-        if let Some(ref backend) = result.gamma_backend {
-            if let Ok(xrandr_outputs) = backend.enumerate_outputs() {
-                for display in &result.displays {
-                    if let Some(xrandr_name) =
-                        map_drm_to_xrandr(&display.drm_connector, &xrandr_outputs)
-                    {
-                        result
-                            .gamma_output_map
-                            .insert(display.id.clone(), xrandr_name);
-                    }
+        if let Some(ref backend) = result.gamma_backend
+            && let Ok(outputs) = gamma::map_outputs(backend.as_ref(), &result.displays)
+        {
+            result.gamma_output_map = outputs;
+        }
+
+        if let Some(ref backend) = result.gamma_backend
+            && let Ok(geometry) = backend.output_geometry()
+        {
+            for (id, output) in &result.gamma_output_map {
+                if let Some(g) = geometry.get(output) {
+                    result.positions.insert(id.clone(), *g);
+                }
+            }
+        }
+
+        for display in result
+            .displays
+            .iter()
+            .filter(|d| d.supports(VCP_POWER_MODE))
+        {
+            if let Some(mode) = read_power_mode(&route, display) {
+                result.power_modes.insert(display.id.clone(), mode);
+            }
+        }
+
+        // Every awake display's basics, for the dashboard
+        for display in &result.displays {
+            if result
+                .power_modes
+                .get(&display.id)
+                .is_some_and(|m| !m.is_on())
+            {
+                continue;
+            }
+            for code in [VCP_BRIGHTNESS, VCP_CONTRAST] {
+                if let Ok(v) = route.read_vcp(display, code) {
+                    result.display_values.insert((display.id.clone(), code), v);
                 }
             }
         }
@@ -106,41 +420,2329 @@ impl App {
         if result.displays.is_empty() {
             result.error = Some("No displays found. Try running: sudo ddcutil detect".into());
         } else {
-            // Read initial values from first display
-            let display_id = &result.displays[0].id;
-            if let Some(v) = read_vcp(display_id, VCP_BRIGHTNESS) {
+            // Read initial values from the display picked last time
+            result.selected = result
+                .displays
+                .iter()
+                .position(|d| selected.is_some_and(|s| d.matches(s)))
+                .unwrap_or(0);
+            let display = &result.displays[result.selected];
+            if let Some(&v) = result
+                .display_values
+                .get(&(display.id.clone(), VCP_BRIGHTNESS))
+            {
                 result.brightness = v;
             }
-            if let Some(v) = read_vcp(display_id, VCP_CONTRAST) {
+            if let Some(&v) = result
+                .display_values
+                .get(&(display.id.clone(), VCP_CONTRAST))
+            {
                 result.contrast = v;
             }
+            result.feature_values = read_control_values(&route, display);
         }
 
         result
     }
 
+    pub fn finish_init(&mut self, mut result: InitResult) {
+        self.take_displays(&mut result);
+        self.watch_hotplug(mem::take(&mut result.connected));
+
+        if result.error.is_none() {
+            self.restore_settings();
+        }
+
+        if let Some(err) = result.error {
+            self.init_state = InitState::Failed(err);
+        } else if let Some(socket) = self.daemon_socket.clone() {
+            self.watch_daemon(socket);
+            self.init_state = InitState::Ready;
+            self.status = Some("Ready (connected to daemon)".into());
+        } else {
+            self.init_state = InitState::Ready;
+            self.status = Some("Ready".into());
+        }
+    }
+
+    /// Take over the displays detection found and the values read from
+    /// them, leaving the rest of `result` to the caller.
+    fn take_displays(&mut self, result: &mut InitResult) {
+        self.ddcutil_available = result.ddcutil_available;
+        if !result.daemon {
+            self.daemon_socket = None;
+        }
+        self.displays = mem::take(&mut result.displays);
+        self.gamma_backend = result.gamma_backend.take();
+        self.gamma_output_map = mem::take(&mut result.gamma_output_map);
+        self.positions = mem::take(&mut result.positions);
+        self.display_values = mem::take(&mut result.display_values);
+        self.brightness = result.brightness;
+        self.contrast = result.contrast;
+        self.feature_values = mem::take(&mut result.feature_values);
+        self.power_modes = mem::take(&mut result.power_modes);
+        self.selected_display = result.selected;
+
+        if self.config.adopt_legacy_keys(&self.displays) {
+            self.save_config();
+        }
+    }
+
+    /// Detect again whenever a monitor is plugged in or unplugged. With a
+    /// daemon attached that means listing its displays again, which it
+    /// detects afresh once it sees the connectors changed too.
+    fn watch_hotplug(&mut self, since: Connected) {
+        if !self.config.features.hotplug {
+            return;
+        }
+        let sysfs = self.runner.sysfs();
+        self.hotplug = Some(hotplug::watch(
+            sysfs,
+            hotplug::POLL_INTERVAL,
+            since,
+            self.repaint.clone(),
+        ));
+    }
+
+    /// Start detecting again after connectors changed, and take over what
+    /// it found once it's done.
+    pub fn poll_hotplug(&mut self) {
+        if let Some(watcher) = &self.hotplug
+            && watcher.changes.try_iter().count() > 0
+        {
+            self.rescan_pending = true;
+        }
+
+        if let Some(rx) = &self.rescan_receiver {
+            match rx.try_recv() {
+                Ok(result) => {
+                    self.rescan_receiver = None;
+                    self.finish_rescan(result);
+                    if self.tray.is_none() {
+                        self.start_tray();
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => self.rescan_receiver = None,
+            }
+        }
+
+        // Workers hold on to displays by id, which detection may renumber
+        if self.rescan_pending && !self.writes_pending() {
+            self.rescan_pending = false;
+            self.start_rescan();
+        }
+    }
+
+    fn start_rescan(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        self.rescan_receiver = Some(rx);
+        self.status = Some("Displays changed, detecting...".into());
+
+        let runner = self.runner.clone();
+        let ddc = self.ddc.clone();
+        let repaint = self.repaint.clone();
+        let socket = self.daemon_socket.clone();
+        let selected = match self.displays.get(self.selected_display) {
+            Some(display) => Some(display.key().to_string()),
+            None => self.config.ui.selected.clone(),
+        };
+
+        thread::spawn(move || {
+            let result = Self::init_blocking(
+                runner,
+                &ddc,
+                gamma::detect_session(),
+                socket.as_deref(),
+                selected.as_deref(),
+            );
+            let _ = tx.send(result);
+            if let Some(ctx) = &repaint {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Swap in the displays found after a hotplug, keeping the selection
+    /// and setting newly connected monitors back to how they were left.
+    pub fn finish_rescan(&mut self, mut result: InitResult) {
+        let known: HashSet<String> = self.displays.iter().map(|d| d.key().to_string()).collect();
+
+        // Everything keyed by display id belongs to the old numbering
+        self.workers.clear();
+        self.last_preview.clear();
+        self.last_outcomes.clear();
+        self.gamma_levels.clear();
+        self.extended_drag = None;
+        self.dimming_drag.clear();
+        self.all_displays = false;
+        self.take_displays(&mut result);
+
+        if let Some(err) = result.error {
+            self.init_state = InitState::Failed(err);
+            return;
+        }
+        self.init_state = InitState::Ready;
+
+        // Gamma is reset on a newly connected output, and reapplying the
+        // same level elsewhere is harmless
+        self.restore_settings();
+        let arrived: Vec<usize> = (0..self.displays.len())
+            .filter(|&i| !known.contains(self.displays[i].key()))
+            .collect();
+        for &index in &arrived {
+            self.restore_values(index);
+        }
+
+        self.status = Some(match arrived.as_slice() {
+            [] => format!("Displays changed, {} connected", self.displays.len()),
+            [index] => format!("Connected {}", self.displays[*index].name),
+            _ => format!("Connected {} displays", arrived.len()),
+        });
+    }
+
+    /// Put a monitor's brightness and contrast back to what was last set
+    /// here, e.g. after it spent the day plugged into another machine.
+    fn restore_values(&mut self, index: usize) {
+        let Some(monitor) = self.config.monitor(self.displays[index].key()).cloned() else {
+            return;
+        };
+        let id = self.displays[index].id.clone();
+        for (code, label, saved) in [
+            (VCP_BRIGHTNESS, "Brightness", monitor.brightness),
+            (VCP_CONTRAST, "Contrast", monitor.contrast),
+        ] {
+            // Asleep or unreadable monitors have no value to scale against
+            if let Some(percent) = saved
+                && let Some(&value) = self.display_values.get(&(id.clone(), code))
+                && value.percent() != percent
+            {
+                self.apply_vcp_to(index, label, code, value.with_percent(percent));
+            }
+        }
+    }
+
+    /// Bring back what the monitors don't remember themselves: software
+    /// dimming and "All displays" mode.
+    fn restore_settings(&mut self) {
+        for index in 0..self.displays.len() {
+            let saved = self
+                .config
+                .monitor(self.displays[index].key())
+                .and_then(|m| m.dimming);
+            if let Some(level) = saved.filter(|&l| l < 100)
+                && self.gamma_available(index)
+                && let (Some(backend), Some(output)) =
+                    (&self.gamma_backend, self.gamma_output(index))
+                && backend.apply_dimming(output, level).is_ok()
+            {
+                self.gamma_levels
+                    .insert(self.displays[index].id.clone(), level);
+            }
+        }
+
+        if self.config.ui.all_displays && self.displays.len() > 1 {
+            self.set_all_displays(true);
+        }
+    }
+
+    /// Follow changes made through the daemon by other frontends.
+    fn watch_daemon(&mut self, socket: PathBuf) {
+        let (sender, changes) = mpsc::channel();
+        self.daemon_changes = Some(changes);
+        let repaint = self.repaint.clone();
+
+        thread::spawn(move || {
+            let Ok(mut events) = Client::connect(&socket).and_then(Client::subscribe) else {
+                return;
+            };
+            while let Ok(reading) = events.next_change() {
+                if sender.send(reading).is_err() {
+                    return;
+                }
+                if let Some(ctx) = &repaint {
+                    ctx.request_repaint();
+                }
+            }
+        });
+    }
+
+    fn apply_daemon_change(&mut self, reading: Reading) {
+        let Some(index) = self.displays.iter().position(|d| d.key == reading.display) else {
+            return;
+        };
+        let (Ok(code), Some(value)) = (ipc::feature_code(&reading.feature), reading.vcp_value())
+        else {
+            return;
+        };
+
+        if matches!(code, VCP_BRIGHTNESS | VCP_CONTRAST) {
+            self.display_values
+                .insert((self.displays[index].id.clone(), code), value);
+        }
+        if code == VCP_POWER_MODE {
+            if let Some(mode) = PowerMode::from_vcp(value.low_byte()) {
+                self.power_modes
+                    .insert(self.displays[index].id.clone(), mode);
+            }
+            return;
+        }
+        if index == self.selected_display {
+            self.store_value(code, value);
+        }
+    }
+
+    /// Abandon reads still in flight after the user switched to another
+    /// display. Queued writes carry on, so the last slider value still lands.
+    pub fn cancel_reads(&self) {
+        self.refresh_runner.cancel_pending();
+    }
+
+    /// Abandon every DDC command still in flight, writes included, once
+    /// the window closes.
+    pub fn cancel_pending(&self) {
+        self.runner.cancel_pending();
+        self.write_runner.cancel_pending();
+        self.refresh_runner.cancel_pending();
+    }
+
+    /// Reads go through the daemon when attached to one, like writes.
+    fn route(&self) -> Route {
+        Route::new(self.ddc.clone(), self.daemon_socket.clone())
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.displays[self.selected_display]
+    }
+
     pub fn display_id(&self) -> &str {
-        &self.displays[self.selected_display].id
+        &self.display().id
     }
 
-    pub fn gamma_output(&self) -> Option<&str> {
+    pub fn gamma_output(&self, index: usize) -> Option<&str> {
         self.gamma_output_map
-            .get(self.display_id())
+            .get(&self.displays[index].id)
             .map(|s| s.as_str())
     }
 
-    pub fn gamma_available(&self) -> bool {
-        self.gamma_backend.is_some() && self.gamma_output().is_some()
+    pub fn gamma_available(&self, index: usize) -> bool {
+        self.config.features.dimming
+            && self.gamma_backend.is_some()
+            && self.gamma_output(index).is_some()
+    }
+
+    pub fn gamma_level(&self, index: usize) -> u8 {
+        self.gamma_levels
+            .get(&self.displays[index].id)
+            .copied()
+            .unwrap_or(100)
+    }
+
+    /// Last known value of a display's brightness or contrast.
+    pub fn display_value(&self, index: usize, code: u8) -> Option<VcpValue> {
+        self.display_values
+            .get(&(self.displays[index].id.clone(), code))
+            .copied()
+    }
+
+    pub fn set_display_value(&mut self, index: usize, code: u8, value: VcpValue) {
+        self.display_values
+            .insert((self.displays[index].id.clone(), code), value);
+        if index == self.selected_display {
+            self.store_value(code, value);
+        }
+    }
+
+    /// How the most recent write to a display went, if there was one.
+    pub fn last_outcome(&self, index: usize) -> Option<&WriteOutcome> {
+        self.last_outcomes.get(&self.displays[index].id)
+    }
+
+    pub fn power_mode_of(&self, index: usize) -> PowerMode {
+        self.power_modes
+            .get(&self.displays[index].id)
+            .copied()
+            .unwrap_or(PowerMode::On)
+    }
+
+    /// Displays grouped into rows the way they sit on the desktop: top to
+    /// bottom, then left to right. Ones without a known position go last.
+    pub fn dashboard_rows(&self) -> Vec<Vec<usize>> {
+        let mut placed: Vec<(usize, OutputGeometry)> = (0..self.displays.len())
+            .filter_map(|i| self.positions.get(&self.displays[i].id).map(|g| (i, *g)))
+            .collect();
+        placed.sort_by_key(|(_, g)| (g.y, g.x));
+
+        let mut rows: Vec<Vec<(usize, OutputGeometry)>> = Vec::new();
+        for (index, geometry) in placed {
+            // A display belongs to a row when its top edge is above the
+            // middle of the row's topmost display
+            match rows.last_mut() {
+                Some(row) if geometry.y < row[0].1.y + row[0].1.height as i32 / 2 => {
+                    row.push((index, geometry));
+                }
+                _ => rows.push(vec![(index, geometry)]),
+            }
+        }
+
+        let mut rows: Vec<Vec<usize>> = rows
+            .into_iter()
+            .map(|mut row| {
+                row.sort_by_key(|(_, g)| g.x);
+                row.into_iter().map(|(i, _)| i).collect()
+            })
+            .collect();
+
+        let unplaced: Vec<usize> = (0..self.displays.len())
+            .filter(|&i| !self.positions.contains_key(&self.displays[i].id))
+            .collect();
+        if !unplaced.is_empty() {
+            rows.push(unplaced);
+        }
+        rows
+    }
+
+    pub fn power_mode(&self) -> PowerMode {
+        self.power_mode_of(self.selected_display)
     }
 
+    /// Re-read the selected display, starting from what was last seen of
+    /// it so the sliders don't show the previous display meanwhile.
     pub fn refresh_values(&mut self) {
-        if let Some(v) = read_vcp(self.display_id(), VCP_BRIGHTNESS) {
-            self.brightness = v;
+        for code in [VCP_BRIGHTNESS, VCP_CONTRAST] {
+            if let Some(v) = self.display_value(self.selected_display, code) {
+                self.store_value(code, v);
+            }
+        }
+        self.read_in_background(vec![self.selected_display], ReadPurpose::Refresh);
+    }
+
+    /// Re-read brightness and contrast of every awake display.
+    pub fn refresh_dashboard(&mut self) {
+        let all = (0..self.displays.len()).collect();
+        self.read_in_background(all, ReadPurpose::Dashboard);
+    }
+
+    /// Read displays on a background thread, like detection at startup,
+    /// so a slow monitor can't freeze the window. [`App::poll_reads`]
+    /// takes the values over.
+    fn read_in_background(&mut self, indices: Vec<usize>, purpose: ReadPurpose) {
+        let displays: Vec<(Display, bool)> = indices
+            .into_iter()
+            .map(|i| (self.displays[i].clone(), !self.power_mode_of(i).is_on()))
+            .collect();
+        let route = match purpose {
+            ReadPurpose::Dashboard | ReadPurpose::Linked => self.route(),
+            ReadPurpose::Refresh | ReadPurpose::Features | ReadPurpose::Color => Route::new(
+                self.ddc.with_runner(self.refresh_runner.clone()),
+                self.daemon_socket.clone(),
+            ),
+        };
+        let sender = self.read_sender.clone();
+        let repaint = self.repaint.clone();
+        self.reads_in_flight += 1;
+
+        thread::spawn(move || {
+            let reads = displays
+                .iter()
+                .map(|(display, asleep)| DisplayRead::read(&route, display, *asleep, purpose))
+                .collect();
+            let _ = sender.send((purpose, reads));
+            if let Some(ctx) = &repaint {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Take over values read in the background since the last frame.
+    pub fn poll_reads(&mut self) {
+        while let Ok((purpose, reads)) = self.reads.try_recv() {
+            self.finish_read(purpose, reads);
+        }
+    }
+
+    /// Block until every background read has been taken over.
+    #[cfg(test)]
+    pub fn flush_reads(&mut self) {
+        while self.reads_in_flight > 0 {
+            let Ok((purpose, reads)) = self.reads.recv() else {
+                return;
+            };
+            self.finish_read(purpose, reads);
+        }
+    }
+
+    fn finish_read(&mut self, purpose: ReadPurpose, reads: Vec<DisplayRead>) {
+        self.reads_in_flight -= 1;
+        let count = reads.len();
+        let mut failure = None;
+        let mut features_read = None;
+
+        for read in reads {
+            let Some(index) = self
+                .displays
+                .iter()
+                .position(|d| d.key() == read.display_key)
+            else {
+                continue;
+            };
+            let id = self.displays[index].id.clone();
+            if let Some(mode) = read.power {
+                self.power_modes.insert(id.clone(), mode);
+            }
+            // Only reads the user asked for may move the sliders mid-drag
+            let selected = index == self.selected_display
+                && (!matches!(purpose, ReadPurpose::Dashboard | ReadPurpose::Linked)
+                    || !self.writes_pending());
+
+            for (code, read) in read.values {
+                match read {
+                    Ok(v) => {
+                        self.display_values.insert((id.clone(), code), v);
+                        if selected {
+                            self.store_value(code, v);
+                        }
+                    }
+                    // Not every monitor has both, which is no reason to complain
+                    Err(Error::UnsupportedFeature(_)) => {}
+                    Err(e) => failure = failure.or(Some(e)),
+                }
+            }
+            if let Some(controls) = read.controls
+                && selected
+            {
+                if purpose == ReadPurpose::Refresh {
+                    self.feature_values = controls;
+                } else {
+                    features_read = Some(controls.len());
+                    self.feature_values.extend(controls);
+                }
+            }
+        }
+
+        match purpose {
+            ReadPurpose::Refresh => self.report_refresh(failure),
+            ReadPurpose::Dashboard => {
+                self.status = Some(format!("Values refreshed from {} displays", count));
+                self.error = None;
+            }
+            ReadPurpose::Linked => {}
+            ReadPurpose::Features => {
+                if let Some(read) = features_read {
+                    let advertised = self
+                        .display()
+                        .capabilities
+                        .as_ref()
+                        .map_or(0, |caps| caps.features.len());
+                    self.status = Some(format!("Read {} of {} features", read, advertised));
+                }
+            }
+            ReadPurpose::Color => {
+                if let Some(read) = features_read {
+                    self.status = Some(format!("Read {} color settings", read));
+                }
+            }
+        }
+    }
+
+    fn report_refresh(&mut self, failure: Option<Error>) {
+        let Some(display) = self.displays.get(self.selected_display) else {
+            return;
+        };
+        let mode = self.power_mode();
+        if !mode.is_on() {
+            self.status = Some(format!(
+                "{} is {}; wake it to read its settings",
+                display.name,
+                mode.describe()
+            ));
+            return;
+        }
+
+        match failure {
+            Some(e) => {
+                self.error = Some(format!("Could not read {}: {}", display.name, e.explain()));
+            }
+            None => {
+                self.status = Some("Values refreshed from monitor".into());
+                self.error = None;
+            }
+        }
+    }
+
+    /// Put an icon in the system tray, unless the config turns it off or
+    /// the desktop has no tray.
+    pub fn start_tray(&mut self) {
+        if !self.config.features.tray || self.tray_unavailable || self.displays.is_empty() {
+            return;
+        }
+
+        self.tray_displays = self.tray_menu();
+        let displays = self.tray_displays.clone();
+        match Tray::start_session(displays, self.tray_sender.clone(), self.repaint.clone()) {
+            Ok(tray) => self.tray = Some(tray),
+            Err(e) => {
+                self.tray_unavailable = true;
+                self.status = Some(format!(
+                    "No system tray, so closing the window quits: {}",
+                    e.explain()
+                ));
+            }
+        }
+    }
+
+    /// Closing the window only hides it while the tray icon is up.
+    pub fn has_tray(&self) -> bool {
+        self.tray.is_some()
+    }
+
+    /// Apply brightness changes picked in the tray and keep its menu
+    /// current. Returns the events that are about the window itself.
+    pub fn poll_tray(&mut self) -> Vec<TrayEvent> {
+        let mut window_events = Vec::new();
+
+        while let Ok(event) = self.tray_events.try_recv() {
+            match event {
+                TrayEvent::Preset { display, percent } => {
+                    if let Some(index) = self.displays.iter().position(|d| d.id == display) {
+                        self.tray_brightness(index, percent);
+                    }
+                }
+                TrayEvent::Scroll(direction) => self.scroll_brightness(direction),
+                TrayEvent::Open | TrayEvent::Quit => window_events.push(event),
+            }
+        }
+
+        let menu = self.tray_menu();
+        if let Some(tray) = &self.tray
+            && menu != self.tray_displays
+            && tray.update(menu.clone()).is_ok()
+        {
+            self.tray_displays = menu;
+        }
+        window_events
+    }
+
+    fn tray_menu(&self) -> Vec<TrayDisplay> {
+        (0..self.displays.len())
+            .map(|index| TrayDisplay {
+                id: self.displays[index].id.clone(),
+                name: self.displays[index].name.clone(),
+                brightness: self
+                    .display_value(index, VCP_BRIGHTNESS)
+                    .map(|v| v.percent()),
+            })
+            .collect()
+    }
+
+    fn tray_brightness(&mut self, index: usize, percent: u8) {
+        let current = self
+            .display_value(index, VCP_BRIGHTNESS)
+            .unwrap_or_default();
+        let value = current.with_percent(self.config.ranges.brightness.clamp(percent));
+        self.set_display_value(index, VCP_BRIGHTNESS, value);
+        self.apply_vcp_to(index, "brightness", VCP_BRIGHTNESS, value);
+    }
+
+    /// Scrolling over the tray icon moves whatever the brightness slider
+    /// currently drives: every linked display, or the selected one.
+    fn scroll_brightness(&mut self, direction: i32) {
+        let step = self.config.steps.scroll as i32 * direction.signum();
+
+        if self.all_displays {
+            let percent = (self.linked_brightness.percent() as i32 + step).clamp(0, 100) as u8;
+            let percent = self.config.ranges.brightness.clamp(percent);
+            self.linked_brightness = self.linked_brightness.with_percent(percent);
+            self.apply_linked("brightness", VCP_BRIGHTNESS, self.linked_brightness);
+        } else if !self.displays.is_empty() {
+            let index = self.selected_display;
+            let current = self
+                .display_value(index, VCP_BRIGHTNESS)
+                .unwrap_or(self.brightness);
+            let percent = (current.percent() as i32 + step).clamp(0, 100) as u8;
+            self.tray_brightness(index, percent);
+        }
+    }
+
+    /// Read every readable feature the selected display advertises, which
+    /// can take a while with ddcutil.
+    pub fn refresh_features(&mut self) {
+        if self.display().capabilities.is_none() {
+            return;
+        }
+        self.status = Some("Reading features...".into());
+        self.read_in_background(vec![self.selected_display], ReadPurpose::Features);
+    }
+
+    /// Read the color preset, gains and black levels of the selected display.
+    pub fn refresh_color(&mut self) {
+        self.status = Some("Reading color settings...".into());
+        self.read_in_background(vec![self.selected_display], ReadPurpose::Color);
+    }
+
+    /// Queue a write to the selected display; its outcome is reported once
+    /// the display's worker gets to it.
+    fn queue_write(&mut self, code: u8, value: u16, action: Action) {
+        self.queue_write_to(self.selected_display, code, value, action);
+    }
+
+    fn queue_write_to(&mut self, index: usize, code: u8, value: u16, action: Action) {
+        // Its completion would come back under an id detection may hand to
+        // another display
+        if self.rescan_receiver.is_some() {
+            self.status = Some("Displays changed, detecting...".into());
+            return;
+        }
+        let display = self.displays[index].clone();
+        let worker = self.workers.entry(display.id.clone()).or_insert_with(|| {
+            let ddc = self.ddc.with_runner(self.write_runner.clone());
+            DisplayWorker::spawn(
+                route_writer(Route::new(ddc, self.daemon_socket.clone())),
+                display,
+                self.completion_sender.clone(),
+                self.writes_in_flight.clone(),
+                self.repaint.clone(),
+            )
+        });
+
+        worker.submit(code, value, action);
+    }
+
+    pub fn writes_pending(&self) -> bool {
+        self.writes_in_flight.load(Ordering::SeqCst) > 0
+    }
+
+    /// Report writes the workers have finished since the last frame.
+    pub fn poll_writes(&mut self) {
+        while let Ok(completion) = self.completions.try_recv() {
+            self.finish_write(completion);
+        }
+        if self.config_dirty && !self.writes_pending() {
+            self.config_dirty = false;
+            self.save_config();
+        }
+
+        // Our own writes echo back too; while some are still queued an echo
+        // could yank a slider back mid-drag
+        if self.writes_pending() {
+            return;
+        }
+        let changes: Vec<Reading> = self
+            .daemon_changes
+            .as_ref()
+            .map(|changes| changes.try_iter().collect())
+            .unwrap_or_default();
+        for reading in changes {
+            self.apply_daemon_change(reading);
         }
-        if let Some(v) = read_vcp(self.display_id(), VCP_CONTRAST) {
-            self.contrast = v;
+    }
+
+    /// Block until every queued write has been reported.
+    #[cfg(test)]
+    pub fn flush_writes(&mut self) {
+        while self.writes_pending() {
+            thread::yield_now();
+        }
+        self.poll_writes();
+    }
+
+    /// Write a VCP feature to the selected display and report the outcome.
+    pub fn apply_vcp(&mut self, label: &str, code: u8, value: VcpValue) {
+        self.apply_vcp_to(self.selected_display, label, code, value);
+    }
+
+    /// Write a VCP feature to any display, e.g. from its dashboard card.
+    pub fn apply_vcp_to(&mut self, index: usize, label: &str, code: u8, value: VcpValue) {
+        let action = Action::Slider {
+            label: label.to_string(),
+            value,
+        };
+        self.queue_write_to(index, code, value.current, action);
+    }
+
+    /// Send an intermediate slider value while the user is still dragging,
+    /// at most once per throttle interval per display. The release always
+    /// goes through [`App::apply_vcp`], so the final value lands regardless.
+    pub fn preview_vcp(&mut self, label: &str, code: u8, value: VcpValue) {
+        self.preview_vcp_to(self.selected_display, label, code, value);
+    }
+
+    pub fn preview_vcp_to(&mut self, index: usize, label: &str, code: u8, value: VcpValue) {
+        if !self.config.ui.live_updates {
+            return;
+        }
+
+        let id = self.displays[index].id.clone();
+        if self.preview_due(&id) {
+            self.apply_vcp_to(index, label, code, value);
+        }
+    }
+
+    /// Whether a display is due another live update, recording it as sent
+    /// if so.
+    fn preview_due(&mut self, display_id: &str) -> bool {
+        let now = Instant::now();
+        if let Some(last) = self.last_preview.get(display_id)
+            && now.duration_since(*last) < self.config.ui.live_interval()
+        {
+            return false;
         }
 
-        self.status = Some("Values refreshed from monitor".into());
+        self.last_preview.insert(display_id.to_string(), now);
+        true
+    }
+
+    pub fn is_linked(&self, index: usize) -> bool {
+        self.config.is_linked(self.displays[index].key())
+    }
+
+    /// Include or leave out a display when sliders drive all of them.
+    pub fn set_linked(&mut self, index: usize, linked: bool) {
+        let key = self.displays[index].key().to_string();
+        self.config.set_linked(&key, linked);
+        self.save_config();
+
+        if linked && self.all_displays {
+            self.read_in_background(vec![index], ReadPurpose::Linked);
+        }
+    }
+
+    pub fn offset(&self, index: usize, code: u8) -> i8 {
+        self.config.offset(self.displays[index].key(), code)
+    }
+
+    pub fn set_offset(&mut self, index: usize, code: u8, offset: i8) {
+        let key = self.displays[index].key().to_string();
+        self.config.set_offset(&key, code, offset);
+    }
+
+    /// Switch "All displays" mode. Turning it on reads every linked display
+    /// so shared values can be scaled to each monitor's range, and starts the
+    /// shared sliders where the selected display currently is.
+    pub fn set_all_displays(&mut self, on: bool) {
+        self.all_displays = on;
+        if self.config.ui.all_displays != on {
+            self.config.ui.all_displays = on;
+            self.save_config();
+        }
+        if !on {
+            return;
+        }
+
+        let linked: Vec<usize> = (0..self.displays.len())
+            .filter(|&i| self.is_linked(i))
+            .collect();
+        let count = linked.len();
+        self.read_in_background(linked, ReadPurpose::Linked);
+
+        let level = |value: VcpValue, offset: i8, range: &PercentRange| {
+            let percent = (value.percent() as i16 - offset as i16).clamp(0, 100) as u8;
+            VcpValue::new(range.clamp(percent).into(), 100)
+        };
+        self.linked_brightness = level(
+            self.brightness,
+            self.offset(self.selected_display, VCP_BRIGHTNESS),
+            self.range(VCP_BRIGHTNESS),
+        );
+        self.linked_contrast = level(
+            self.contrast,
+            self.offset(self.selected_display, VCP_CONTRAST),
+            self.range(VCP_CONTRAST),
+        );
+        self.status = Some(format!("Controlling {} linked displays", count));
+    }
+
+    /// Linked displays that are awake and have the feature.
+    fn linked_targets(&self, code: u8) -> Vec<usize> {
+        (0..self.displays.len())
+            .filter(|&i| self.is_linked(i))
+            .filter(|&i| {
+                let display = &self.displays[i];
+                self.power_modes.get(&display.id).is_none_or(|m| m.is_on())
+                    && display
+                        .capabilities
+                        .as_ref()
+                        .is_none_or(|c| c.supports(code))
+            })
+            .collect()
+    }
+
+    /// The slider range configured for brightness or contrast.
+    fn range(&self, code: u8) -> &PercentRange {
+        match code {
+            VCP_CONTRAST => &self.config.ranges.contrast,
+            _ => &self.config.ranges.brightness,
+        }
+    }
+
+    /// A shared slider level turned into one display's value: its offset
+    /// added and kept within the slider's range, then scaled to its native
+    /// range.
+    fn linked_value(&self, index: usize, code: u8, level: u8) -> VcpValue {
+        let display = &self.displays[index];
+        let shifted = (level as i16 + self.offset(index, code) as i16).clamp(0, 100) as u8;
+        let percent = self.range(code).clamp(shifted);
+        let max = self
+            .display_values
+            .get(&(display.id.clone(), code))
+            .map_or(100, |v| v.max);
+
+        VcpValue::new(0, max).with_percent(percent)
+    }
+
+    /// Write a shared slider level to every linked display.
+    pub fn apply_linked(&mut self, label: &str, code: u8, level: VcpValue) {
+        for index in self.linked_targets(code) {
+            self.queue_linked(index, label, code, level);
+        }
+    }
+
+    /// Like [`App::preview_vcp`], throttled per display.
+    pub fn preview_linked(&mut self, label: &str, code: u8, level: VcpValue) {
+        if !self.config.ui.live_updates {
+            return;
+        }
+
+        for index in self.linked_targets(code) {
+            let id = self.displays[index].id.clone();
+            if self.preview_due(&id) {
+                self.queue_linked(index, label, code, level);
+            }
+        }
+    }
+
+    fn queue_linked(&mut self, index: usize, label: &str, code: u8, level: VcpValue) {
+        let value = self.linked_value(index, code, level.percent());
+        let action = Action::Slider {
+            label: format!("{} {}", self.displays[index].name, label),
+            value,
+        };
+        self.queue_write_to(index, code, value.current, action);
+    }
+
+    /// Write one of the selected display's advanced features.
+    pub fn apply_feature(&mut self, code: u8, value: u16) {
+        let known = self.feature_values.get(&code).map(|v| v.max);
+        let max = known.unwrap_or(0);
+        let feature = self
+            .display()
+            .capabilities
+            .as_ref()
+            .and_then(|c| c.feature(code));
+        // Without a read there's no maximum to show next to the value
+        let shown = match feature {
+            Some(f) if known.is_some() || f.kind == mccs::FeatureKind::NonContinuous => {
+                f.format_value(VcpValue::new(value, max))
+            }
+            _ => value.to_string(),
+        };
+
+        self.queue_write(code, value, Action::Feature { shown, max });
+    }
+
+    pub fn switch_input(&mut self, code: u8) {
+        let label = self.input_label(code);
+        self.queue_write(VCP_INPUT_SOURCE, code.into(), Action::Input { code, label });
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.queue_write(
+            VCP_AUDIO_MUTE,
+            mute_value(muted).into(),
+            Action::Mute(muted),
+        );
+    }
+
+    pub fn set_power_mode(&mut self, mode: PowerMode) {
+        self.queue_write(VCP_POWER_MODE, mode.vcp_value().into(), Action::Power(mode));
+    }
+
+    fn finish_write(&mut self, completion: Completion) {
+        let Completion {
+            display_id,
+            code,
+            value,
+            action,
+            outcome,
+            ..
+        } = completion;
+        let Some(display) = self.displays.iter().find(|d| d.id == display_id) else {
+            return;
+        };
+        self.last_outcomes
+            .insert(display_id.clone(), outcome.clone());
+        let name = display.name.clone();
+        // Values shown in the window belong to the selected display only
+        let selected = display_id == self.display_id();
+
+        match action {
+            Action::Slider { label, value } => {
+                let done = if value.max == 100 {
+                    format!("Set {} to {}%", label, value.percent())
+                } else {
+                    format!(
+                        "Set {} to {}% ({}/{})",
+                        label,
+                        value.percent(),
+                        value.current,
+                        value.max
+                    )
+                };
+                self.report_write(done, &format!("Failed to set {}", label), &outcome);
+
+                // Show what the monitor actually kept rather than what was asked for
+                let kept = match outcome {
+                    WriteOutcome::Mismatch { actual, .. } => Some(VcpValue::new(actual, value.max)),
+                    ref outcome if outcome.is_applied() => Some(value),
+                    _ => None,
+                };
+                if let Some(kept) = kept {
+                    if matches!(code, VCP_BRIGHTNESS | VCP_CONTRAST) {
+                        self.display_values.insert((display_id.clone(), code), kept);
+                        self.remember_value(&display_id, code, kept.percent());
+                    }
+                    if selected {
+                        self.store_value(code, kept);
+                    }
+                }
+            }
+            Action::Feature { shown, max } => {
+                let feature = mccs::feature_name(code);
+                self.report_write(
+                    format!("Set {} to {}", feature, shown),
+                    &format!("Failed to set {}", feature),
+                    &outcome,
+                );
+
+                match outcome {
+                    WriteOutcome::Mismatch { actual, .. } if selected => {
+                        self.store_value(code, VcpValue::new(actual, max));
+                    }
+                    ref outcome if selected && outcome.is_applied() => {
+                        self.store_value(code, VcpValue::new(value, max));
+                    }
+                    _ => {}
+                }
+            }
+            Action::Input { code: input, label } => {
+                let switched = self.report_write(
+                    format!("Switched input to {}", label),
+                    &format!("Failed to switch input to {}", label),
+                    &outcome,
+                );
+                if switched && selected {
+                    self.store_value(VCP_INPUT_SOURCE, VcpValue::new(input.into(), 0));
+                }
+            }
+            Action::Mute(muted) => {
+                let (verb, done) = if muted {
+                    ("mute", "Muted")
+                } else {
+                    ("unmute", "Unmuted")
+                };
+                let applied = self.report_write(
+                    format!("{} {}", done, name),
+                    &format!("Failed to {} {}", verb, name),
+                    &outcome,
+                );
+                if applied && selected {
+                    self.store_value(VCP_AUDIO_MUTE, VcpValue::new(value, 0));
+                }
+            }
+            Action::Power(mode) => self.finish_power_mode(&display_id, &name, mode, &outcome),
+        }
+    }
+
+    fn store_value(&mut self, code: u8, value: VcpValue) {
+        match code {
+            VCP_BRIGHTNESS => self.brightness = value,
+            VCP_CONTRAST => self.contrast = value,
+            _ => {
+                self.feature_values.insert(code, value);
+            }
+        }
+    }
+
+    fn finish_power_mode(
+        &mut self,
+        display_id: &str,
+        name: &str,
+        mode: PowerMode,
+        outcome: &WriteOutcome,
+    ) {
+        match (outcome.is_applied(), mode.is_on()) {
+            (true, true) => {
+                self.power_modes.insert(display_id.to_string(), mode);
+                self.status = Some(format!("Woke {}", name));
+                self.error = None;
+            }
+            (true, false) => {
+                self.power_modes.insert(display_id.to_string(), mode);
+                self.status = Some(format!("{} is now {}", name, mode.describe()));
+                self.error = None;
+            }
+            // Monitors often drop off the bus while powering down, so a
+            // failed write doesn't mean the command was ignored
+            (false, false) => {
+                self.power_modes.insert(display_id.to_string(), mode);
+                self.status = Some(format!(
+                    "{} is now {} (monitor stopped responding)",
+                    name,
+                    mode.describe()
+                ));
+                self.error = None;
+            }
+            (false, true) => {
+                self.error = Some(format!(
+                    "Could not wake {}: {}. Some monitors only wake on input activity or their power button.",
+                    name, outcome
+                ));
+            }
+        }
+    }
+
+    /// Show a write's outcome as the status or error line, returning whether
+    /// the value took effect.
+    fn report_write(&mut self, done: String, failed: &str, outcome: &WriteOutcome) -> bool {
+        match outcome {
+            WriteOutcome::Applied => self.status = Some(done),
+            WriteOutcome::AppliedAfterRetry { attempts } => {
+                self.status = Some(format!("{} (after {} attempts)", done, attempts));
+            }
+            WriteOutcome::Mismatch { .. } | WriteOutcome::Unresponsive(_) => {
+                self.error = Some(match outcome {
+                    WriteOutcome::Unresponsive(e) => format!("{}: {}", failed, e.explain()),
+                    _ => format!("{}: {}", failed, outcome),
+                });
+                return false;
+            }
+        }
+
+        self.error = None;
+        true
+    }
+
+    /// Input sources the selected display advertises.
+    pub fn input_sources(&self) -> Vec<u8> {
+        self.display()
+            .capabilities
+            .as_ref()
+            .and_then(|c| c.feature(VCP_INPUT_SOURCE))
+            .map(|f| f.values.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn current_input(&self) -> Option<u8> {
+        self.feature_values
+            .get(&VCP_INPUT_SOURCE)
+            .map(VcpValue::low_byte)
+    }
+
+    /// The user's label for an input on the selected display, or its
+    /// friendly name when none was assigned.
+    pub fn input_label(&self, code: u8) -> String {
+        self.config
+            .input_label(self.display().key(), code)
+            .map(str::to_string)
+            .unwrap_or_else(|| input_name(code))
+    }
+
+    pub fn set_input_label(&mut self, code: u8, label: &str) {
+        let key = self.display().key().to_string();
+        self.config.set_input_label(&key, code, label);
+    }
+
+    pub fn volume(&self) -> Option<VcpValue> {
+        self.feature_values.get(&VCP_AUDIO_VOLUME).copied()
+    }
+
+    pub fn muted(&self) -> Option<bool> {
+        self.feature_values
+            .get(&VCP_AUDIO_MUTE)
+            .map(|v| is_muted(v.low_byte()))
+    }
+
+    /// Remember the display the user switched to for the next start.
+    pub fn remember_selection(&mut self) {
+        self.config.ui.selected = Some(self.display().key().to_string());
+        self.save_config();
+    }
+
+    /// Record a brightness or contrast the monitor took. [`App::poll_writes`]
+    /// saves it once a burst of slider writes has settled.
+    fn remember_value(&mut self, display_id: &str, code: u8, percent: u8) {
+        let Some(display) = self.displays.iter().find(|d| d.id == display_id) else {
+            return;
+        };
+        let key = display.key().to_string();
+        let monitor = self.config.monitor_mut(&key);
+        match code {
+            VCP_BRIGHTNESS => monitor.brightness = Some(percent),
+            VCP_CONTRAST => monitor.contrast = Some(percent),
+            _ => return,
+        }
+        self.config_dirty = true;
+    }
+
+    pub fn save_config(&mut self) {
+        let Some(path) = &self.config_path else {
+            return;
+        };
+
+        if let Err(e) = self.config.save(path) {
+            self.error = Some(format!("Failed to save config: {}", e));
+        }
+    }
+
+    pub fn apply_dimming(&mut self, index: usize, value: u8) {
+        // Released either way; a failed write leaves the slider where it was
+        self.dimming_drag.remove(&self.displays[index].id);
+        let (Some(backend), Some(output)) = (&self.gamma_backend, self.gamma_output(index)) else {
+            return;
+        };
+
+        match backend.apply_dimming(output, value) {
+            Ok(_) => {
+                self.gamma_levels
+                    .insert(self.displays[index].id.clone(), value);
+                let key = self.displays[index].key().to_string();
+                self.config.monitor_mut(&key).dimming = Some(value).filter(|&v| v < 100);
+                self.save_config();
+                if value < 100 {
+                    self.status = Some(format!("Set software dimming to {}%", value));
+                } else {
+                    self.status = Some("Software dimming removed".into());
+                }
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(format!("Gamma error ({}): {}", backend.name(), e.explain()));
+            }
+        }
+    }
+
+    pub fn reset_gamma(&mut self, index: usize) {
+        let (Some(backend), Some(output)) = (&self.gamma_backend, self.gamma_output(index)) else {
+            return;
+        };
+
+        match backend.reset(output) {
+            Ok(_) => {
+                self.gamma_levels.remove(&self.displays[index].id);
+                let key = self.displays[index].key().to_string();
+                self.config.monitor_mut(&key).dimming = None;
+                self.save_config();
+                self.status = Some("Reset gamma to normal".into());
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(format!("Gamma error ({}): {}", backend.name(), e.explain()));
+            }
+        }
+    }
+
+    /// Where the selected display sits on the extended-range slider.
+    pub fn extended_level(&self) -> u8 {
+        ExtendedRange::new(&self.config.ranges).combine(
+            self.brightness.percent(),
+            self.gamma_level(self.selected_display),
+        )
+    }
+
+    /// Keep the extended slider where it's being dragged, moving the display
+    /// along with it when live updates are on.
+    pub fn preview_extended(&mut self, percent: u8) {
+        self.extended_drag = Some(percent);
+        if !self.config.ui.live_updates {
+            return;
+        }
+
+        let id = self.display_id().to_string();
+        if self.preview_due(&id) {
+            self.set_extended(percent);
+        }
+    }
+
+    /// Move the selected display along the extended range: its brightness,
+    /// then software dimming once that is at its minimum.
+    pub fn apply_extended(&mut self, percent: u8) {
+        self.extended_drag = None;
+        self.set_extended(percent);
+    }
+
+    fn set_extended(&mut self, percent: u8) {
+        let range = ExtendedRange::new(&self.config.ranges);
+        let app = RefCell::new(self);
+        let mut extended = Extended {
+            range,
+            hardware: Box::new(SelectedBrightness(&app)),
+            software: Box::new(SelectedDimming(&app)),
+        };
+        // Each part reports its own outcome in the status line
+        let _ = extended.set(percent);
+    }
+}
+
+/// The selected display's brightness, queued on its worker like the
+/// brightness slider's writes.
+struct SelectedBrightness<'a, 'b>(&'a RefCell<&'b mut App>);
+
+impl BrightnessControl for SelectedBrightness<'_, '_> {
+    fn get(&mut self) -> Result<u8, Error> {
+        Ok(self.0.borrow().brightness.percent())
+    }
+
+    fn set(&mut self, percent: u8) -> Result<(), Error> {
+        let mut app = self.0.borrow_mut();
+        if percent != app.brightness.percent() {
+            let value = app.brightness.with_percent(percent);
+            let index = app.selected_display;
+            app.set_display_value(index, VCP_BRIGHTNESS, value);
+            app.apply_vcp("brightness", VCP_BRIGHTNESS, value);
+        }
+        Ok(())
+    }
+}
+
+/// The selected display's software dimming.
+struct SelectedDimming<'a, 'b>(&'a RefCell<&'b mut App>);
+
+impl BrightnessControl for SelectedDimming<'_, '_> {
+    fn get(&mut self) -> Result<u8, Error> {
+        let app = self.0.borrow();
+        Ok(app.gamma_level(app.selected_display))
+    }
+
+    fn set(&mut self, percent: u8) -> Result<(), Error> {
+        let mut app = self.0.borrow_mut();
+        let index = app.selected_display;
+        if percent != app.gamma_level(index) {
+            app.apply_dimming(index, percent);
+        }
+        Ok(())
+    }
+}
+
+/// Read the non-slider features that have their own controls in the main
+/// view, for whichever of them the display advertises.
+fn read_control_values(route: &Route, display: &Display) -> HashMap<u8, VcpValue> {
+    [VCP_INPUT_SOURCE, VCP_AUDIO_VOLUME, VCP_AUDIO_MUTE]
+        .into_iter()
+        .filter(|&code| display.supports(code))
+        .filter_map(|code| route.read_vcp(display, code).ok().map(|v| (code, v)))
+        .collect()
+}
+
+/// The listed features the display advertises as readable, as far as they
+/// answer.
+fn read_features(route: &Route, display: &Display, codes: &[u8]) -> HashMap<u8, VcpValue> {
+    let Some(caps) = &display.capabilities else {
+        return HashMap::new();
+    };
+    codes
+        .iter()
+        .copied()
+        .filter(|&code| caps.feature(code).is_some_and(|f| f.readable()))
+        .filter_map(|code| route.read_vcp(display, code).ok().map(|v| (code, v)))
+        .collect()
+}
+
+fn read_power_mode(route: &Route, display: &Display) -> Option<PowerMode> {
+    route
+        .read_vcp(display, VCP_POWER_MODE)
+        .ok()
+        .and_then(|v| PowerMode::from_vcp(v.low_byte()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::backlight::tests::laptop_sysfs;
+    use crate::commands::ScriptedRunner;
+    use crate::ddc::WritePolicy;
+
+    pub const DDCUTIL_DETECT: &str = "Display 1
+   I2C bus:  /dev/i2c-7
+   DRM connector:           card1-DP-1
+   EDID synopsis:
+      Mfg id:               DEL - Dell Inc.
+      Model:                DELL U2715H
+      Product code:         16614  (0x40E6)
+      Serial number:        ABC123
+   VCP version:         2.1
+
+Display 2
+   I2C bus:  /dev/i2c-8
+   DRM connector:           card1-HDMI-A-1
+   EDID synopsis:
+      Mfg id:               SAM - Samsung
+      Model:                Samsung 27\"
+      Product code:         1234
+      Serial number:        XYZ789
+   VCP version:         2.2
+";
+
+    pub const DELL_CAPABILITIES: &str = "Unparsed capabilities string: (prot(monitor)type(lcd)model(U2715H)cmds(01 02 03 07 0C E3 F3)vcp(02 04 05 08 10 12 14(05 08 0B 0C) 16 18 1A 52 60(01 0F 11) AA(01 02) AC AE B2 B6 C6 C8 C9 D6(01 04 05) DC(00 02 03 05) DF)mccs_ver(2.1))";
+
+    pub const XRANDR_QUERY: &str =
+        "Screen 0: minimum 8 x 8, current 5120 x 1440, maximum 32767 x 32767
+DP-0 connected primary 2560x1440+0+0 (normal left inverted right x axis y axis) 597mm x 336mm
+HDMI-A-0 connected 2560x1440+2560+0 (normal left inverted right x axis y axis) 597mm x 336mm
+";
+
+    /// Two monitors behind ddcutil and an X11 session with xrandr.
+    pub fn scripted_desk() -> ScriptedRunner {
+        ScriptedRunner::new()
+            .on("ddcutil detect", DDCUTIL_DETECT)
+            .on("ddcutil getvcp 10 --bus 7 --terse", "VCP 10 C 70 100")
+            .on("ddcutil getvcp 12 --bus 7 --terse", "VCP 12 C 40 100")
+            .on("ddcutil getvcp 10 --bus 8 --terse", "VCP 10 C 20 100")
+            .on("ddcutil getvcp 12 --bus 8 --terse", "VCP 12 C 60 100")
+            .on("ddcutil capabilities --bus 7 --verbose", DELL_CAPABILITIES)
+            .on("xrandr --query", XRANDR_QUERY)
+    }
+
+    /// An `App` that has completed init against the scripted runner.
+    pub fn ready_app(runner: Arc<ScriptedRunner>) -> App {
+        let mut app = App::with_runner(runner.clone(), false);
+        app.ddc = app.ddc.with_policy(WritePolicy {
+            verify: false,
+            ..WritePolicy::immediate()
+        });
+        let result = App::init_blocking(
+            runner,
+            &app.ddc,
+            SessionType::X11,
+            None,
+            app.config.ui.selected.as_deref(),
+        );
+        app.finish_init(result);
+        app
+    }
+
+    #[test]
+    fn test_init_reads_displays_and_values() {
+        let app = ready_app(Arc::new(scripted_desk()));
+
+        assert!(matches!(app.init_state, InitState::Ready));
+        assert_eq!(app.displays.len(), 2);
+        assert_eq!(app.brightness, VcpValue::new(70, 100));
+        assert_eq!(app.contrast, VcpValue::new(40, 100));
+        assert_eq!(
+            app.gamma_output_map.get("1").map(String::as_str),
+            Some("DP-0")
+        );
+        assert_eq!(
+            app.gamma_output_map.get("2").map(String::as_str),
+            Some("HDMI-A-0")
+        );
+
+        // Only the Dell answered the capabilities query
+        assert!(
+            app.displays[0]
+                .capabilities
+                .as_ref()
+                .is_some_and(|c| c.supports(0x60))
+        );
+        assert!(app.displays[1].capabilities.is_none());
+    }
+
+    #[test]
+    fn test_refresh_features() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil getvcp 60 --bus 7 --terse", "VCP 60 SNC x0f")
+                .on("ddcutil getvcp 14 --bus 7 --terse", "VCP 14 SNC x05"),
+        );
+        let mut app = ready_app(runner.clone());
+
+        app.refresh_features();
+        assert_eq!(app.status.as_deref(), Some("Reading features..."));
+        app.flush_reads();
+        assert_eq!(app.status.as_deref(), Some("Read 4 of 23 features"));
+        assert_eq!(app.feature_values.get(&0x10), Some(&VcpValue::new(70, 100)));
+        assert_eq!(
+            app.feature_values.get(&0x60).map(VcpValue::low_byte),
+            Some(0x0f)
+        );
+        assert_eq!(
+            app.feature_values.get(&0x14).map(VcpValue::low_byte),
+            Some(0x05)
+        );
+
+        // Write-only features are never queried
+        assert!(
+            !runner
+                .calls()
+                .iter()
+                .any(|c| c.starts_with("ddcutil getvcp 04 "))
+        );
+    }
+
+    #[test]
+    fn test_apply_feature_uses_value_names() {
+        let runner = Arc::new(scripted_desk().on("ddcutil setvcp dc 3 --bus 7", ""));
+        let mut app = ready_app(runner);
+
+        app.apply_feature(0xDC, 0x03);
+
+        app.flush_writes();
+        assert_eq!(app.status.as_deref(), Some("Set Display Mode to Movie"));
+        assert_eq!(
+            app.feature_values.get(&0xDC).map(VcpValue::low_byte),
+            Some(0x03)
+        );
+    }
+
+    #[test]
+    fn test_cancelling_reads_leaves_writes_alone() {
+        let reads = Arc::new(scripted_desk());
+        let writes = Arc::new(ScriptedRunner::new().on("ddcutil setvcp 10 30 --bus 7", ""));
+        let mut app = ready_app(reads.clone());
+        app.write_runner = writes.clone();
+
+        app.apply_vcp(
+            "brightness",
+            VCP_BRIGHTNESS,
+            app.brightness.with_percent(30),
+        );
+        app.cancel_reads();
+
+        app.flush_writes();
+        assert_eq!(writes.calls(), ["ddcutil setvcp 10 30 --bus 7"]);
+        assert!(!reads.calls().iter().any(|c| c.contains("setvcp")));
+    }
+
+    #[test]
+    fn test_cancelling_reads_leaves_the_dashboard_alone() {
+        let dashboard = Arc::new(scripted_desk());
+        let selected = Arc::new(scripted_desk());
+        let mut app = ready_app(dashboard.clone());
+        app.refresh_runner = selected.clone();
+        let before = dashboard.calls().len();
+
+        // Only reads of the selected display go through the runner that
+        // switching displays cancels
+        app.refresh_values();
+        app.flush_reads();
+        assert_eq!(dashboard.calls().len(), before);
+        assert!(
+            selected
+                .calls()
+                .iter()
+                .any(|c| c.starts_with("ddcutil getvcp 10"))
+        );
+
+        app.refresh_dashboard();
+        app.flush_reads();
+        assert!(dashboard.calls().len() > before);
+    }
+
+    #[test]
+    fn test_apply_feature_without_known_max() {
+        let runner = Arc::new(scripted_desk().on("ddcutil setvcp 16 40 --bus 7", ""));
+        let mut app = ready_app(runner);
+
+        app.apply_feature(0x16, 40);
+
+        app.flush_writes();
+        let expected = format!("Set {} to 40", mccs::feature_name(0x16));
+        assert_eq!(app.status.as_deref(), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn test_switch_input_with_label() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil getvcp 60 --bus 7 --terse", "VCP 60 SNC x0f")
+                .on("ddcutil setvcp 60 17 --bus 7", ""),
+        );
+        let mut app = ready_app(runner.clone());
+
+        assert_eq!(app.input_sources(), vec![0x01, 0x0F, 0x11]);
+        assert_eq!(app.current_input(), Some(0x0F));
+
+        app.set_input_label(0x11, "Desktop");
+        assert_eq!(app.input_label(0x0F), "DP-1");
+        assert_eq!(app.input_label(0x11), "Desktop");
+
+        app.switch_input(0x11);
+
+        app.flush_writes();
+        assert_eq!(app.status.as_deref(), Some("Switched input to Desktop"));
+        assert_eq!(app.current_input(), Some(0x11));
+        assert_eq!(
+            runner.calls().last().map(String::as_str),
+            Some("ddcutil setvcp 60 17 --bus 7")
+        );
+    }
+
+    #[test]
+    fn test_standby_and_wake() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil getvcp d6 --bus 7 --terse", "VCP d6 SNC x01")
+                .fail("ddcutil setvcp d6 4 --bus 7", "DDC communication failed")
+                .on("ddcutil setvcp d6 1 --bus 7", ""),
+        );
+        let mut app = ready_app(runner.clone());
+        assert_eq!(app.power_mode(), PowerMode::On);
+
+        // The monitor drops off the bus as it turns off
+        app.set_power_mode(PowerMode::Off);
+        app.flush_writes();
+        assert_eq!(app.power_mode(), PowerMode::Off);
+        assert!(app.error.is_none());
+
+        // While off, reads fail quietly instead of raising errors
+        runner.set(
+            "ddcutil getvcp d6 --bus 7 --terse",
+            Err(Error::Failed("DDC communication failed".into())),
+        );
+        let calls_before = runner.calls().len();
+        app.refresh_values();
+        app.flush_reads();
+        assert!(app.error.is_none());
+        assert_eq!(
+            app.status.as_deref(),
+            Some("DELL U2715H is off; wake it to read its settings")
+        );
+        assert_eq!(runner.calls().len(), calls_before + 1);
+
+        app.set_power_mode(PowerMode::On);
+
+        app.flush_writes();
+        assert_eq!(app.power_mode(), PowerMode::On);
+        assert_eq!(app.status.as_deref(), Some("Woke DELL U2715H"));
+    }
+
+    #[test]
+    fn test_wake_failure_is_reported() {
+        let runner =
+            Arc::new(scripted_desk().fail("ddcutil setvcp d6 1 --bus 7", "No monitor detected"));
+        let mut app = ready_app(runner);
+
+        app.power_modes.insert("1".into(), PowerMode::Standby);
+        app.set_power_mode(PowerMode::On);
+        app.flush_writes();
+        assert_eq!(app.power_mode(), PowerMode::Standby);
+        assert!(
+            app.error
+                .as_deref()
+                .is_some_and(|e| e.starts_with("Could not wake DELL U2715H"))
+        );
+    }
+
+    #[test]
+    fn test_color_controls() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil getvcp 14 --bus 7 --terse", "VCP 14 SNC x0b")
+                .on("ddcutil getvcp 16 --bus 7 --terse", "VCP 16 C 100 100")
+                .on("ddcutil getvcp 18 --bus 7 --terse", "VCP 18 C 95 100")
+                .on("ddcutil getvcp 1a --bus 7 --terse", "VCP 1a C 90 100")
+                .on("ddcutil setvcp 16 97 --bus 7", "")
+                .on("ddcutil setvcp 14 5 --bus 7", ""),
+        );
+        let mut app = ready_app(runner.clone());
+
+        // The Dell has no black level controls, so only four are queried
+        app.refresh_color();
+        app.flush_reads();
+        assert_eq!(app.status.as_deref(), Some("Read 4 color settings"));
+        assert_eq!(app.feature_values.get(&0x18), Some(&VcpValue::new(95, 100)));
+        assert!(
+            !runner
+                .calls()
+                .iter()
+                .any(|c| c.starts_with("ddcutil getvcp 6c "))
+        );
+
+        app.apply_feature(0x16, 97);
+
+        app.flush_writes();
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Set Video Gain: Red to 97 / 100")
+        );
+
+        app.apply_feature(0x14, 0x05);
+
+        app.flush_writes();
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Set Select Color Preset to 6500 K")
+        );
+    }
+
+    #[test]
+    fn test_audio_controls() {
+        let audio_caps =
+            DELL_CAPABILITIES.replace("DC(00 02 03 05)", "DC(00 02 03 05) 62 8D(01 02)");
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil capabilities --bus 7 --verbose", &audio_caps)
+                .on("ddcutil getvcp 62 --bus 7 --terse", "VCP 62 C 30 100")
+                .on("ddcutil getvcp 8d --bus 7 --terse", "VCP 8d SNC x02")
+                .on("ddcutil setvcp 62 45 --bus 7", "")
+                .on("ddcutil setvcp 8d 1 --bus 7", ""),
+        );
+        let mut app = ready_app(runner.clone());
+
+        assert_eq!(app.volume(), Some(VcpValue::new(30, 100)));
+        assert_eq!(app.muted(), Some(false));
+
+        app.apply_vcp("volume", VCP_AUDIO_VOLUME, VcpValue::new(45, 100));
+
+        app.flush_writes();
+        assert_eq!(app.status.as_deref(), Some("Set volume to 45%"));
+        assert_eq!(app.volume(), Some(VcpValue::new(45, 100)));
+
+        app.set_muted(true);
+
+        app.flush_writes();
+        assert_eq!(app.status.as_deref(), Some("Muted DELL U2715H"));
+        assert_eq!(app.muted(), Some(true));
+
+        // Displays that don't advertise audio are never asked about it
+        app.selected_display = 1;
+        app.refresh_values();
+        app.flush_reads();
+        assert_eq!(app.volume(), None);
+        assert!(
+            !runner
+                .calls()
+                .iter()
+                .any(|c| c.starts_with("ddcutil getvcp 62 --bus 8"))
+        );
+    }
+
+    #[test]
+    fn test_dropped_write_reported_as_mismatch() {
+        // The monitor acknowledges the write but keeps reporting 70
+        let runner = Arc::new(scripted_desk().on("ddcutil setvcp 10 40 --bus 7", ""));
+        let mut app = ready_app(runner.clone());
+        app.ddc = app.ddc.clone().with_policy(WritePolicy::immediate());
+
+        app.apply_vcp(
+            "brightness",
+            VCP_BRIGHTNESS,
+            app.brightness.with_percent(40),
+        );
+
+        app.flush_writes();
+        assert_eq!(
+            app.error.as_deref(),
+            Some("Failed to set brightness: monitor reports 70 instead of 40")
+        );
+        assert_eq!(app.brightness, VcpValue::new(70, 100));
+
+        let writes = runner
+            .calls()
+            .iter()
+            .filter(|c| c.starts_with("ddcutil setvcp"))
+            .count();
+        assert_eq!(writes, 3);
+    }
+
+    #[test]
+    fn test_brightness_scaled_to_monitor_range() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil getvcp 10 --bus 7 --terse", "VCP 10 C 25 50")
+                .on("ddcutil setvcp 10 20 --bus 7", ""),
+        );
+        let mut app = ready_app(runner.clone());
+
+        assert_eq!(app.brightness.percent(), 50);
+
+        app.apply_vcp(
+            "brightness",
+            VCP_BRIGHTNESS,
+            app.brightness.with_percent(40),
+        );
+
+        app.flush_writes();
+        assert_eq!(app.status.as_deref(), Some("Set brightness to 40% (20/50)"));
+        assert_eq!(
+            runner.calls().last().map(String::as_str),
+            Some("ddcutil setvcp 10 20 --bus 7")
+        );
+    }
+
+    #[test]
+    fn test_init_without_ddcutil() {
+        let runner = Arc::new(ScriptedRunner::new().missing("ddcutil"));
+        let app = ready_app(runner);
+
+        assert!(matches!(app.init_state, InitState::Failed(_)));
+        assert!(!app.ddcutil_available);
+    }
+
+    #[test]
+    fn test_laptop_panel_without_ddcutil() {
+        let root = tempfile::tempdir().unwrap();
+        laptop_sysfs(root.path());
+        let runner = Arc::new(
+            ScriptedRunner::new()
+                .missing("ddcutil")
+                .with_sysfs(root.path()),
+        );
+        let mut app = ready_app(runner);
+
+        assert!(matches!(app.init_state, InitState::Ready));
+        assert_eq!(app.displays.len(), 1);
+        assert_eq!(app.display().name, "Built-in display");
+        assert_eq!(app.display().key(), "card0-eDP-1");
+        assert!(!app.ddcutil_available);
+        assert!(!app.display().supports(VCP_CONTRAST));
+        assert_eq!(app.brightness.percent(), 50);
+
+        app.apply_vcp(
+            "brightness",
+            VCP_BRIGHTNESS,
+            app.brightness.with_percent(30),
+        );
+
+        app.flush_writes();
+        assert!(app.error.is_none());
+        let written = std::fs::read_to_string(
+            root.path()
+                .join("class/backlight/intel_backlight/brightness"),
+        )
+        .unwrap();
+        assert_eq!(written, "5818");
+    }
+
+    #[test]
+    fn test_hotplug_keeps_selection_and_restores_monitors() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 50 --bus 7", "")
+                .on("xrandr --output DP-0 --brightness 0.6", ""),
+        );
+        let mut app = ready_app(runner.clone());
+        app.selected_display = 1;
+        let rescan = |app: &mut App| {
+            let selected = app.display().key().to_string();
+            let result = App::init_blocking(
+                runner.clone(),
+                &app.ddc,
+                SessionType::X11,
+                None,
+                Some(&selected),
+            );
+            app.finish_rescan(result);
+        };
+
+        // Unplugging the Dell moves the Samsung up to ddcutil's display 1
+        let samsung_only = DDCUTIL_DETECT
+            .split("\n\n")
+            .nth(1)
+            .unwrap()
+            .replace("Display 2", "Display 1");
+        runner.set("ddcutil detect", Ok(samsung_only));
+        rescan(&mut app);
+        assert_eq!(app.displays.len(), 1);
+        assert_eq!(app.display().key(), "SAM-04D2-XYZ789");
+        assert_eq!(app.brightness, VcpValue::new(20, 100));
+
+        // While it was away the Dell was last left at 50% and dimmed
+        let dell = app.config.monitor_mut("DEL-40E6-ABC123");
+        dell.brightness = Some(50);
+        dell.dimming = Some(60);
+        runner.set("ddcutil detect", Ok(DDCUTIL_DETECT.into()));
+        rescan(&mut app);
+        app.flush_writes();
+
+        assert_eq!(app.displays.len(), 2);
+        assert_eq!(app.selected_display, 1);
+        assert_eq!(app.display().key(), "SAM-04D2-XYZ789");
+        assert_eq!(app.gamma_output(0), Some("DP-0"));
+        assert_eq!(app.gamma_level(0), 60);
+        assert_eq!(
+            app.display_values.get(&("1".into(), VCP_BRIGHTNESS)),
+            Some(&VcpValue::new(50, 100))
+        );
+        let calls = runner.calls();
+        assert!(calls.iter().any(|c| c == "ddcutil setvcp 10 50 --bus 7"));
+        assert!(
+            calls
+                .iter()
+                .any(|c| c == "xrandr --output DP-0 --brightness 0.6")
+        );
+        // The Samsung never left, so it's left alone
+        assert!(
+            !calls
+                .iter()
+                .any(|c| c.starts_with("ddcutil setvcp") && c.ends_with("--bus 8"))
+        );
+    }
+
+    #[test]
+    fn test_rescan_holds_writes_and_drops_stale_reads() {
+        let runner = Arc::new(scripted_desk());
+        let mut app = ready_app(runner.clone());
+        app.refresh_values();
+
+        // Nothing may be written under the old numbering while detecting
+        let (_tx, rx) = mpsc::channel();
+        app.rescan_receiver = Some(rx);
+        app.apply_vcp(
+            "brightness",
+            VCP_BRIGHTNESS,
+            app.brightness.with_percent(35),
+        );
+        assert!(!app.writes_pending());
+        assert!(
+            !runner
+                .calls()
+                .iter()
+                .any(|c| c.starts_with("ddcutil setvcp"))
+        );
+
+        // The Samsung is display 1 once the Dell is gone, so what was read
+        // from the Dell must not end up on it
+        let samsung_only = DDCUTIL_DETECT
+            .split("\n\n")
+            .nth(1)
+            .unwrap()
+            .replace("Display 2", "Display 1");
+        runner.set("ddcutil detect", Ok(samsung_only));
+        let result = App::init_blocking(runner.clone(), &app.ddc, SessionType::X11, None, None);
+        app.rescan_receiver = None;
+        app.finish_rescan(result);
+        app.flush_reads();
+
+        assert_eq!(
+            app.display_values.get(&("1".into(), VCP_BRIGHTNESS)),
+            Some(&VcpValue::new(20, 100))
+        );
+        assert_eq!(app.brightness.percent(), 20);
+    }
+
+    #[test]
+    fn test_init_without_displays() {
+        let runner = Arc::new(ScriptedRunner::new().on("ddcutil detect", "No displays found.\n"));
+        let app = ready_app(runner);
+
+        assert!(matches!(app.init_state, InitState::Failed(_)));
+        assert!(app.displays.is_empty());
+    }
+
+    #[test]
+    fn test_select_display_and_set_brightness() {
+        let runner = Arc::new(scripted_desk().on("ddcutil setvcp 10 35 --bus 8", ""));
+        let mut app = ready_app(runner.clone());
+
+        app.selected_display = 1;
+        app.refresh_values();
+        app.flush_reads();
+        assert_eq!(app.brightness.percent(), 20);
+        assert_eq!(app.contrast.percent(), 60);
+
+        app.apply_vcp(
+            "brightness",
+            VCP_BRIGHTNESS,
+            app.brightness.with_percent(35),
+        );
+
+        app.flush_writes();
+        assert_eq!(app.status.as_deref(), Some("Set brightness to 35%"));
+        assert!(app.error.is_none());
+        assert_eq!(
+            runner.calls().last().map(String::as_str),
+            Some("ddcutil setvcp 10 35 --bus 8")
+        );
+    }
+
+    #[test]
+    fn test_live_updates_are_throttled() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 60 --bus 7", "")
+                .on("ddcutil setvcp 10 50 --bus 7", "")
+                .on("ddcutil setvcp 10 40 --bus 7", ""),
+        );
+        let mut app = ready_app(runner.clone());
+        app.config.ui.live_interval_ms = 60_000;
+
+        app.preview_vcp("brightness", VCP_BRIGHTNESS, VcpValue::new(60, 100));
+        app.preview_vcp("brightness", VCP_BRIGHTNESS, VcpValue::new(50, 100));
+        app.apply_vcp("brightness", VCP_BRIGHTNESS, VcpValue::new(40, 100));
+        app.flush_writes();
+
+        let writes: Vec<String> = runner
+            .calls()
+            .into_iter()
+            .filter(|c| c.starts_with("ddcutil setvcp"))
+            .collect();
+        assert!(!writes.contains(&"ddcutil setvcp 10 50 --bus 7".to_string()));
+        assert_eq!(
+            writes.last().map(String::as_str),
+            Some("ddcutil setvcp 10 40 --bus 7")
+        );
+        assert_eq!(app.status.as_deref(), Some("Set brightness to 40%"));
+
+        // With live updates off, nothing is sent until release
+        app.config.ui.live_updates = false;
+        let before = runner.calls().len();
+        app.preview_vcp("brightness", VCP_BRIGHTNESS, VcpValue::new(60, 100));
+        app.flush_writes();
+        assert_eq!(runner.calls().len(), before);
+    }
+
+    #[test]
+    fn test_all_displays_with_offsets() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 40 --bus 7", "")
+                .on("ddcutil setvcp 10 60 --bus 8", "")
+                .on("ddcutil setvcp 10 100 --bus 8", ""),
+        );
+        let mut app = ready_app(runner.clone());
+        app.set_offset(1, VCP_BRIGHTNESS, 20);
+
+        app.set_all_displays(true);
+        app.flush_reads();
+        assert_eq!(app.linked_brightness, VcpValue::new(70, 100));
+
+        app.apply_linked("brightness", VCP_BRIGHTNESS, VcpValue::new(40, 100));
+        app.flush_writes();
+        let calls = runner.calls();
+        assert!(calls.contains(&"ddcutil setvcp 10 40 --bus 7".to_string()));
+        assert!(calls.contains(&"ddcutil setvcp 10 60 --bus 8".to_string()));
+        assert_eq!(app.brightness, VcpValue::new(40, 100));
+        assert_eq!(app.error, None);
+
+        // Offsets never push a display past its range
+        app.set_linked(0, false);
+        app.apply_linked("brightness", VCP_BRIGHTNESS, VcpValue::new(95, 100));
+        app.flush_writes();
+        assert!(
+            !runner
+                .calls()
+                .iter()
+                .any(|c| c.contains(" --display 1") && c.contains("setvcp 10 95"))
+        );
+        assert_eq!(
+            runner.calls().last().map(String::as_str),
+            Some("ddcutil setvcp 10 100 --bus 8")
+        );
+        assert_eq!(app.error, None);
+
+        // Nor below the bottom of the brightness slider
+        app.set_linked(0, true);
+        app.set_offset(1, VCP_BRIGHTNESS, -20);
+        assert_eq!(
+            app.linked_value(1, VCP_BRIGHTNESS, 10),
+            VcpValue::new(1, 100)
+        );
+    }
+
+    #[test]
+    fn test_dashboard_follows_desktop_layout() {
+        let mut app = ready_app(Arc::new(scripted_desk()));
+
+        // Side by side, as xrandr reports them
+        assert_eq!(app.dashboard_rows(), vec![vec![0, 1]]);
+        assert_eq!(
+            app.display_value(1, VCP_BRIGHTNESS),
+            Some(VcpValue::new(20, 100))
+        );
+        assert_eq!(
+            app.display_value(1, VCP_CONTRAST),
+            Some(VcpValue::new(60, 100))
+        );
+
+        // The Samsung stacked above the Dell
+        let geometry = |x, y| OutputGeometry {
+            x,
+            y,
+            width: 2560,
+            height: 1440,
+        };
+        app.positions.insert("1".into(), geometry(0, 1440));
+        app.positions.insert("2".into(), geometry(400, 0));
+        assert_eq!(app.dashboard_rows(), vec![vec![1], vec![0]]);
+
+        app.positions.remove("2");
+        assert_eq!(app.dashboard_rows(), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn test_settings_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = Arc::new(
+            scripted_desk()
+                .on("xrandr --output DP-0 --brightness 0.6", "")
+                .on("ddcutil setvcp 10 35 --bus 8", ""),
+        );
+        let mut app = App::with_runner(runner.clone(), false);
+        app.ddc = app.ddc.with_policy(WritePolicy {
+            verify: false,
+            ..WritePolicy::immediate()
+        });
+        app.config_path = Some(dir.path().join("config.toml"));
+        // Saved by a version that keyed monitors by model name
+        app.config.ui.selected = Some("Samsung 27\"".into());
+        app.config.monitor_mut("DELL U2715H").dimming = Some(60);
+
+        let result = App::init_blocking(
+            runner.clone(),
+            &app.ddc,
+            SessionType::X11,
+            None,
+            Some("Samsung 27\""),
+        );
+        app.finish_init(result);
+        assert_eq!(app.selected_display, 1);
+        assert_eq!(app.brightness, VcpValue::new(20, 100));
+        assert_eq!(app.gamma_level(0), 60);
+        assert!(
+            runner
+                .calls()
+                .contains(&"xrandr --output DP-0 --brightness 0.6".to_string())
+        );
+
+        app.apply_vcp("brightness", VCP_BRIGHTNESS, VcpValue::new(35, 100));
+        app.set_all_displays(true);
+        app.flush_reads();
+        app.flush_writes();
+        let saved = Config::load(app.config_path.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            saved.monitor("SAM-04D2-XYZ789").and_then(|m| m.brightness),
+            Some(35)
+        );
+        assert_eq!(
+            saved.monitor("DEL-40E6-ABC123").and_then(|m| m.dimming),
+            Some(60)
+        );
+        assert_eq!(saved.monitor("DELL U2715H"), None);
+        assert_eq!(saved.ui.selected.as_deref(), Some("SAM-04D2-XYZ789"));
+        assert!(saved.ui.all_displays);
+        assert_eq!(app.error, None);
+    }
+
+    #[test]
+    fn test_tray_presets_and_scroll() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 75 --bus 8", "")
+                .on("ddcutil setvcp 10 75 --bus 7", "")
+                .on("ddcutil setvcp 10 70 --bus 7", "")
+                .on("ddcutil setvcp 10 70 --bus 8", ""),
+        );
+        let mut app = ready_app(runner.clone());
+        assert_eq!(app.tray_menu()[1].brightness, Some(20));
+
+        let preset = TrayEvent::Preset {
+            display: "2".into(),
+            percent: 75,
+        };
+        for event in [preset, TrayEvent::Scroll(1), TrayEvent::Open] {
+            app.tray_sender.send(event).unwrap();
+        }
+        assert_eq!(app.poll_tray(), vec![TrayEvent::Open]);
+        app.flush_writes();
+        let calls = runner.calls();
+        assert!(calls.contains(&"ddcutil setvcp 10 75 --bus 8".to_string()));
+        assert!(calls.contains(&"ddcutil setvcp 10 75 --bus 7".to_string()));
+        assert_eq!(app.brightness, VcpValue::new(75, 100));
+        assert_eq!(app.tray_menu()[1].brightness, Some(75));
+
+        // In "All displays" mode the wheel moves the shared slider
+        app.set_all_displays(true);
+        app.flush_reads();
+        app.tray_sender.send(TrayEvent::Scroll(-1)).unwrap();
+        app.poll_tray();
+        app.flush_writes();
+        assert_eq!(app.linked_brightness, VcpValue::new(70, 100));
+        assert!(
+            runner
+                .calls()
+                .contains(&"ddcutil setvcp 10 70 --bus 8".to_string())
+        );
+        assert_eq!(app.error, None);
+    }
+
+    #[test]
+    fn test_gui_uses_running_daemon() {
+        let daemon_runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil getvcp d6 --bus 7 --terse", "VCP D6 SNC x01")
+                .on("ddcutil getvcp 60 --bus 7 --terse", "VCP 60 SNC x0f")
+                .on("ddcutil setvcp 10 45 --bus 8", "")
+                .on("ddcutil setvcp 60 17 --bus 7", "")
+                .on("ddcutil setvcp 12 80 --bus 7", ""),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let socket = crate::daemon::tests::spawn_daemon(daemon_runner.clone(), dir.path());
+
+        let runner = Arc::new(scripted_desk());
+        let mut app = App::with_runner(runner.clone(), false);
+        app.daemon_socket = Some(socket.clone());
+        let result = App::init_blocking(
+            runner.clone(),
+            &app.ddc,
+            SessionType::X11,
+            Some(&socket),
+            None,
+        );
+        app.finish_init(result);
+
+        assert_eq!(app.status.as_deref(), Some("Ready (connected to daemon)"));
+        assert_eq!(app.displays.len(), 2);
+        assert_eq!(
+            app.display_value(1, VCP_BRIGHTNESS),
+            Some(VcpValue::new(20, 100))
+        );
+        // Capabilities, power and other features come from the daemon too
+        assert!(app.displays[0].supports(VCP_INPUT_SOURCE));
+        assert_eq!(app.power_mode_of(0), PowerMode::On);
+        assert_eq!(
+            app.feature_values.get(&VCP_INPUT_SOURCE),
+            Some(&VcpValue::new(0x0f, 0))
+        );
+        assert!(!runner.calls().iter().any(|c| c.starts_with("ddcutil")));
+
+        // Brightness goes through the daemon rather than straight to the bus
+        app.apply_vcp_to(1, "brightness", VCP_BRIGHTNESS, VcpValue::new(45, 100));
+        app.flush_writes();
+        assert_eq!(app.error, None);
+        assert!(
+            daemon_runner
+                .calls()
+                .contains(&"ddcutil setvcp 10 45 --bus 8".to_string())
+        );
+        app.switch_input(0x11);
+        app.flush_writes();
+        assert_eq!(app.error, None);
+        assert!(
+            daemon_runner
+                .calls()
+                .contains(&"ddcutil setvcp 60 17 --bus 7".to_string())
+        );
+        assert!(!runner.calls().iter().any(|c| c.starts_with("ddcutil")));
+
+        // And changes from other frontends show up
+        Client::connect(&socket)
+            .unwrap()
+            .set("1", "contrast", ipc::Level::Absolute(80))
+            .unwrap();
+        for _ in 0..200 {
+            app.poll_writes();
+            if app.contrast == VcpValue::new(80, 100) {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(app.contrast, VcpValue::new(80, 100));
+    }
+
+    #[test]
+    fn test_write_finishing_after_switching_display() {
+        let runner = Arc::new(scripted_desk().on("ddcutil setvcp 10 35 --bus 7", ""));
+        let mut app = ready_app(runner);
+
+        app.apply_vcp(
+            "brightness",
+            VCP_BRIGHTNESS,
+            app.brightness.with_percent(35),
+        );
+        app.selected_display = 1;
+        app.refresh_values();
+        app.flush_reads();
+        app.flush_writes();
+
+        // The Dell's write is reported, but the Samsung's slider is left alone
+        assert_eq!(app.status.as_deref(), Some("Set brightness to 35%"));
+        assert_eq!(app.brightness, VcpValue::new(20, 100));
+    }
+
+    #[test]
+    fn test_set_failure_reports_error() {
+        let runner = Arc::new(
+            scripted_desk().fail("ddcutil setvcp 12 80 --bus 7", "DDC communication failed"),
+        );
+        let mut app = ready_app(runner);
+
+        app.apply_vcp("contrast", VCP_CONTRAST, app.contrast.with_percent(80));
+
+        app.flush_writes();
+        assert_eq!(
+            app.error.as_deref(),
+            Some(
+                "Failed to set contrast: monitor not responding: DDC communication failed. \
+                 Check that DDC/CI is enabled in the monitor's on-screen menu"
+            )
+        );
+    }
+
+    #[test]
+    fn test_software_dimming() {
+        let runner = Arc::new(scripted_desk().on("xrandr --output DP-0 --brightness 0.6", ""));
+        let mut app = ready_app(runner);
+
+        assert!(app.gamma_available(0));
+        app.apply_dimming(0, 60);
+        assert_eq!(app.gamma_level(0), 60);
+        assert_eq!(app.gamma_level(1), 100);
+        assert_eq!(app.status.as_deref(), Some("Set software dimming to 60%"));
+
+        // A level the backend refused is not shown as applied
+        let id = app.displays[0].id.clone();
+        app.dimming_drag.insert(id.clone(), 40);
+        app.apply_dimming(0, 40);
+        assert!(app.error.is_some());
+        assert_eq!(app.gamma_level(0), 60);
+        assert!(!app.dimming_drag.contains_key(&id));
+
+        // So is a reset that didn't happen
+        app.error = None;
+        app.reset_gamma(0);
+        assert!(
+            app.error
+                .as_deref()
+                .is_some_and(|e| e.starts_with("Gamma error"))
+        );
+        assert_eq!(app.gamma_level(0), 60);
+    }
+
+    #[test]
+    fn test_extended_range_slider() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 1 --bus 7", "")
+                .on("xrandr --output DP-0 --brightness 0.73", "")
+                .on("ddcutil setvcp 10 51 --bus 7", "")
+                .on("xrandr --output DP-0 --brightness 1", ""),
+        );
+        let mut app = ready_app(runner.clone());
+        assert_eq!(app.extended_level(), 83);
+
+        app.apply_extended(30);
+        app.flush_writes();
+        assert_eq!(app.brightness.percent(), 1);
+        assert_eq!(app.gamma_level(0), 73);
+        assert_eq!(app.extended_level(), 30);
+
+        // Back above the knee, dimming is lifted first thing
+        app.apply_extended(73);
+        app.flush_writes();
+        assert_eq!(app.brightness.percent(), 51);
+        assert_eq!(app.gamma_level(0), 100);
+        assert!(app.error.is_none());
+
+        // Dragging moves the display too, unless live updates are off
+        app.config.ui.live_updates = false;
+        app.preview_extended(30);
+        assert_eq!(app.extended_drag, Some(30));
+        assert_eq!(app.gamma_level(0), 100);
+        app.config.ui.live_updates = true;
+        app.preview_extended(30);
+        app.flush_writes();
+        assert_eq!(app.gamma_level(0), 73);
+        assert_eq!(app.extended_drag, Some(30));
     }
 }
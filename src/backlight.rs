@@ -0,0 +1,321 @@
+//! Laptop panels, which have no DDC but a backlight in
+//! `/sys/class/backlight`. Each panel is presented as one more display whose
+//! only feature is brightness.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use zbus::blocking::Connection;
+
+use crate::ddc::{DdcTransport, VCP_BRIGHTNESS, VcpValue};
+use crate::edid::DRM_CLASS;
+use crate::error::Error;
+
+pub const BACKLIGHT_CLASS: &str = "class/backlight";
+
+/// What a panel reports in place of an MCCS capabilities string.
+const CAPABILITIES: &str = "(prot(backlight)type(lcd)cmds(01 03)vcp(10)mccs_ver(2.1))";
+
+/// Connector types that are built into the machine rather than plugged in.
+const INTERNAL_CONNECTORS: [&str; 3] = ["eDP", "LVDS", "DSI"];
+
+/// A backlight device, e.g. `intel_backlight`, and the connector of the
+/// panel it lights.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlight {
+    pub device: String,
+    pub connector: String,
+}
+
+/// Every panel backlight under `sysfs`, one per connector.
+pub fn enumerate(sysfs: &Path) -> Vec<Backlight> {
+    let Ok(entries) = fs::read_dir(sysfs.join(BACKLIGHT_CLASS)) else {
+        return Vec::new();
+    };
+    let mut devices: Vec<(u8, Backlight)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let device = entry.file_name().into_string().ok()?;
+            let connector = connector_for(sysfs, &entry.path())?;
+            let kind = fs::read_to_string(entry.path().join("type")).unwrap_or_default();
+            Some((priority(kind.trim()), Backlight { device, connector }))
+        })
+        .collect();
+
+    // A panel can have several interfaces to one backlight; keep the best
+    devices.sort_by(|a, b| {
+        (&a.1.connector, a.0, &a.1.device).cmp(&(&b.1.connector, b.0, &b.1.device))
+    });
+    devices.dedup_by(|later, first| later.1.connector == first.1.connector);
+    devices
+        .into_iter()
+        .map(|(_, backlight)| backlight)
+        .collect()
+}
+
+/// The kernel's advice: firmware interfaces know the panel best, raw
+/// register access least.
+fn priority(kind: &str) -> u8 {
+    match kind {
+        "firmware" => 0,
+        "platform" => 1,
+        _ => 2,
+    }
+}
+
+/// Raw backlights hang off their connector; firmware and platform ones off
+/// the GPU or ACPI, which leaves the machine's one internal connector.
+fn connector_for(sysfs: &Path, device: &Path) -> Option<String> {
+    let parent = fs::canonicalize(device.join("device")).ok();
+    if let Some(name) = parent
+        .as_deref()
+        .and_then(Path::file_name)
+        .and_then(|n| n.to_str())
+        && is_connector(name)
+    {
+        return Some(name.to_string());
+    }
+
+    let mut internal: Vec<String> = fs::read_dir(sysfs.join(DRM_CLASS))
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            is_connector(name)
+                && INTERNAL_CONNECTORS
+                    .iter()
+                    .any(|t| name.contains(&format!("-{}-", t)))
+        })
+        .filter(|name| {
+            // DSI panels may not report a status at all
+            match fs::read_to_string(sysfs.join(DRM_CLASS).join(name).join("status")) {
+                Ok(status) => status.trim() == "connected",
+                Err(e) => e.kind() == io::ErrorKind::NotFound,
+            }
+        })
+        .collect();
+    internal.sort();
+    internal.into_iter().next()
+}
+
+fn is_connector(name: &str) -> bool {
+    name.starts_with("card") && name.contains('-')
+}
+
+/// Brightness through a backlight device, shaped like a DDC transport so
+/// the rest of the app doesn't need to know the difference.
+pub struct BacklightTransport {
+    dir: PathBuf,
+    device: String,
+}
+
+impl BacklightTransport {
+    pub fn new(sysfs: &Path, device: &str) -> Self {
+        Self {
+            dir: sysfs.join(BACKLIGHT_CLASS).join(device),
+            device: device.to_string(),
+        }
+    }
+
+    fn read(&self, file: &str) -> Result<u32, Error> {
+        let path = self.dir.join(file);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| Error::from_io(&path.display().to_string(), e))?;
+        text.trim()
+            .parse()
+            .map_err(|_| Error::Parse(format!("{}: {:?}", path.display(), text.trim())))
+    }
+
+    /// Backlights can count past what a VCP value holds; scale them down
+    /// by a whole factor so every VCP step is one or more raw steps.
+    fn scale(max: u32) -> u32 {
+        max.div_ceil(u32::from(u16::MAX)).max(1)
+    }
+
+    /// Write through sysfs when we may, otherwise ask logind, which lets
+    /// the active session's user set the backlight without udev rules.
+    fn write(&self, raw: u32) -> Result<(), Error> {
+        match fs::write(self.dir.join("brightness"), raw.to_string()) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => self.write_logind(raw),
+            result => result.map_err(|e| Error::from_io(&self.device, e)),
+        }
+    }
+
+    fn write_logind(&self, raw: u32) -> Result<(), Error> {
+        let denied = |e: zbus::Error| {
+            Error::PermissionDenied(format!("{} (logind: {})", self.dir.display(), e))
+        };
+        Connection::system()
+            .and_then(|bus| {
+                bus.call_method(
+                    Some("org.freedesktop.login1"),
+                    "/org/freedesktop/login1/session/auto",
+                    Some("org.freedesktop.login1.Session"),
+                    "SetBrightness",
+                    &("backlight", self.device.as_str(), raw),
+                )
+            })
+            .map(|_| ())
+            .map_err(denied)
+    }
+}
+
+impl DdcTransport for BacklightTransport {
+    fn get_vcp(&mut self, code: u8) -> Result<VcpValue, Error> {
+        if code != VCP_BRIGHTNESS {
+            return Err(Error::UnsupportedFeature(code));
+        }
+        let max = self.read("max_brightness")?;
+        let current = self
+            .read("actual_brightness")
+            .or_else(|_| self.read("brightness"))?;
+        let scale = Self::scale(max);
+        Ok(VcpValue::new(
+            (current.min(max) / scale) as u16,
+            (max / scale) as u16,
+        ))
+    }
+
+    fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), Error> {
+        if code != VCP_BRIGHTNESS {
+            return Err(Error::UnsupportedFeature(code));
+        }
+        let max = self.read("max_brightness")?;
+        self.write((u32::from(value) * Self::scale(max)).min(max))
+    }
+
+    fn capabilities(&mut self) -> Result<String, Error> {
+        Ok(CAPABILITIES.to_string())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    use crate::ddc::Capabilities;
+
+    /// A laptop's sysfs: an eDP panel with `intel_backlight` hanging off its
+    /// connector, and an external DP port with nothing plugged in.
+    pub fn laptop_sysfs(root: &Path) {
+        let drm = root.join(DRM_CLASS);
+        for (connector, status) in [("card0-eDP-1", "connected"), ("card0-DP-1", "disconnected")] {
+            fs::create_dir_all(drm.join(connector)).unwrap();
+            fs::write(drm.join(connector).join("status"), status).unwrap();
+        }
+        add_backlight(root, "intel_backlight", "raw", 19393, Some("card0-eDP-1"));
+    }
+
+    pub fn add_backlight(root: &Path, device: &str, kind: &str, max: u32, connector: Option<&str>) {
+        let dir = root.join(BACKLIGHT_CLASS).join(device);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+        fs::write(dir.join("max_brightness"), format!("{}\n", max)).unwrap();
+        fs::write(dir.join("brightness"), format!("{}\n", max / 2)).unwrap();
+        fs::write(dir.join("actual_brightness"), format!("{}\n", max / 2)).unwrap();
+
+        let parent = match connector {
+            Some(connector) => root.join(DRM_CLASS).join(connector),
+            None => root.join("devices/pci0000:00/0000:00:02.0"),
+        };
+        fs::create_dir_all(&parent).unwrap();
+        symlink(parent, dir.join("device")).unwrap();
+    }
+
+    #[test]
+    fn test_enumerate_backlights() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(enumerate(root.path()).is_empty());
+
+        laptop_sysfs(root.path());
+        assert_eq!(
+            enumerate(root.path()),
+            [Backlight {
+                device: "intel_backlight".into(),
+                connector: "card0-eDP-1".into()
+            }]
+        );
+
+        // ACPI's interface to the same panel is preferred
+        add_backlight(root.path(), "acpi_video0", "firmware", 100, None);
+        assert_eq!(
+            enumerate(root.path()),
+            [Backlight {
+                device: "acpi_video0".into(),
+                connector: "card0-eDP-1".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_desktop_without_panel() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join(DRM_CLASS).join("card0-DP-1")).unwrap();
+        // Some desktop boards register a backlight with nothing behind it
+        add_backlight(root.path(), "acpi_video0", "firmware", 100, None);
+
+        assert!(enumerate(root.path()).is_empty());
+    }
+
+    #[test]
+    fn test_read_and_write_brightness() {
+        let root = tempfile::tempdir().unwrap();
+        laptop_sysfs(root.path());
+        let mut panel = BacklightTransport::new(root.path(), "intel_backlight");
+
+        let value = panel.get_vcp(VCP_BRIGHTNESS).unwrap();
+        assert_eq!(value, VcpValue::new(9696, 19393));
+        assert_eq!(value.percent(), 50);
+
+        panel
+            .set_vcp(VCP_BRIGHTNESS, value.with_percent(30).current)
+            .unwrap();
+        let written = fs::read_to_string(
+            root.path()
+                .join(BACKLIGHT_CLASS)
+                .join("intel_backlight/brightness"),
+        )
+        .unwrap();
+        assert_eq!(written, "5818");
+
+        assert_eq!(panel.get_vcp(0x12), Err(Error::UnsupportedFeature(0x12)));
+        let caps = Capabilities::parse(&panel.capabilities().unwrap()).unwrap();
+        assert!(caps.supports(VCP_BRIGHTNESS));
+        assert!(!caps.supports(0x12));
+    }
+
+    #[test]
+    fn test_scale_large_ranges() {
+        let root = tempfile::tempdir().unwrap();
+        add_backlight(
+            root.path(),
+            "nvidia_wmi_ec_backlight",
+            "firmware",
+            120000,
+            Some("card1-eDP-1"),
+        );
+        let mut panel = BacklightTransport::new(root.path(), "nvidia_wmi_ec_backlight");
+
+        let value = panel.get_vcp(VCP_BRIGHTNESS).unwrap();
+        assert_eq!(value, VcpValue::new(30000, 60000));
+
+        panel.set_vcp(VCP_BRIGHTNESS, value.max).unwrap();
+        let written = fs::read_to_string(
+            root.path()
+                .join(BACKLIGHT_CLASS)
+                .join("nvidia_wmi_ec_backlight/brightness"),
+        )
+        .unwrap();
+        assert_eq!(written, "120000");
+
+        // A range that just fits is kept whole
+        add_backlight(root.path(), "acpi_video0", "firmware", 65535, None);
+        let mut panel = BacklightTransport::new(root.path(), "acpi_video0");
+        assert_eq!(
+            panel.get_vcp(VCP_BRIGHTNESS).unwrap(),
+            VcpValue::new(32767, 65535)
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! One way to ask for "brighter" or "darker" whatever does the work: a
+//! monitor over DDC, a laptop backlight or the session's gamma ramps. The
+//! extended range chains two of them so a single slider keeps going once
+//! the panel's own brightness bottoms out.
+
+use crate::backlight::BacklightTransport;
+use crate::commands::CommandRunner;
+use crate::config::RangeConfig;
+use crate::ddc::{Ddc, DdcTransport, VCP_BRIGHTNESS};
+use crate::display::Display;
+use crate::error::Error;
+use crate::gamma::GammaBackend;
+
+/// Hardware brightness continuing into software dimming, served alongside
+/// the VCP features and `dimming`.
+pub const EXTENDED: &str = "extended";
+
+/// Something that makes a display brighter or darker, on a 0-100 scale.
+pub trait BrightnessControl {
+    fn get(&mut self) -> Result<u8, Error>;
+    fn set(&mut self, percent: u8) -> Result<(), Error>;
+}
+
+/// The display's own brightness: its backlight for a laptop panel, DDC for
+/// everything else.
+pub fn hardware<'a>(
+    runner: &dyn CommandRunner,
+    ddc: &'a Ddc,
+    display: &'a Display,
+) -> Box<dyn BrightnessControl + 'a> {
+    match &display.backlight {
+        Some(device) => Box::new(BacklightTransport::new(&runner.sysfs(), device)),
+        None => Box::new(DdcBrightness { ddc, display }),
+    }
+}
+
+/// VCP brightness under the write policy.
+pub struct DdcBrightness<'a> {
+    pub ddc: &'a Ddc,
+    pub display: &'a Display,
+}
+
+impl BrightnessControl for DdcBrightness<'_> {
+    fn get(&mut self) -> Result<u8, Error> {
+        self.ddc
+            .read_vcp(self.display, VCP_BRIGHTNESS)
+            .map(|v| v.percent())
+    }
+
+    fn set(&mut self, percent: u8) -> Result<(), Error> {
+        // Read first for the monitor's own range
+        let value = self
+            .ddc
+            .read_vcp(self.display, VCP_BRIGHTNESS)?
+            .with_percent(percent);
+        self.ddc
+            .set_vcp(self.display, VCP_BRIGHTNESS, value.current)
+            .check("brightness")
+    }
+}
+
+impl BrightnessControl for BacklightTransport {
+    fn get(&mut self) -> Result<u8, Error> {
+        self.get_vcp(VCP_BRIGHTNESS).map(|v| v.percent())
+    }
+
+    fn set(&mut self, percent: u8) -> Result<(), Error> {
+        let value = self.get_vcp(VCP_BRIGHTNESS)?.with_percent(percent);
+        self.set_vcp(VCP_BRIGHTNESS, value.current)
+    }
+}
+
+/// Software dimming of one output.
+pub struct GammaBrightness<'a> {
+    pub backend: &'a dyn GammaBackend,
+    pub output: String,
+}
+
+impl BrightnessControl for GammaBrightness<'_> {
+    fn get(&mut self) -> Result<u8, Error> {
+        self.backend.current_dimming(&self.output)
+    }
+
+    fn set(&mut self, percent: u8) -> Result<(), Error> {
+        self.backend.apply_dimming(&self.output, percent)
+    }
+}
+
+/// Where an extended-range slider hands over from hardware brightness to
+/// software dimming. Above the knee the hardware moves between its minimum
+/// and full; below it the hardware stays at its minimum and dimming takes
+/// over, down to its own minimum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedRange {
+    pub hardware_min: u8,
+    pub software_min: u8,
+}
+
+impl ExtendedRange {
+    pub fn new(ranges: &RangeConfig) -> Self {
+        Self {
+            hardware_min: *ranges.brightness.range().start(),
+            software_min: *ranges.dimming.range().start(),
+        }
+    }
+
+    /// Slider position where dimming ends, so each part of the slider
+    /// moves its control at the same rate.
+    fn knee(&self) -> u8 {
+        let hardware = 100 - u32::from(self.hardware_min);
+        let software = 100 - u32::from(self.software_min);
+        rescale(software, hardware + software, 100) as u8
+    }
+
+    /// Hardware brightness and software dimming for a slider position.
+    pub fn split(&self, percent: u8) -> (u8, u8) {
+        let (percent, knee) = (percent.min(100), self.knee());
+        if percent >= knee {
+            let hardware = rescale(
+                u32::from(percent - knee),
+                u32::from(100 - knee),
+                u32::from(100 - self.hardware_min),
+            );
+            (self.hardware_min + hardware as u8, 100)
+        } else {
+            let software = rescale(
+                u32::from(percent),
+                u32::from(knee),
+                u32::from(100 - self.software_min),
+            );
+            (self.hardware_min, self.software_min + software as u8)
+        }
+    }
+
+    /// The slider position for a brightness and dimming, the inverse of
+    /// [`ExtendedRange::split`]. Any dimming puts it below the knee.
+    pub fn combine(&self, hardware: u8, software: u8) -> u8 {
+        let knee = self.knee();
+        if software >= 100 {
+            let above = hardware.clamp(self.hardware_min, 100) - self.hardware_min;
+            knee + rescale(
+                u32::from(above),
+                u32::from(100 - self.hardware_min),
+                u32::from(100 - knee),
+            ) as u8
+        } else {
+            let above = software.max(self.software_min) - self.software_min;
+            rescale(
+                u32::from(above),
+                u32::from(100 - self.software_min),
+                u32::from(knee),
+            ) as u8
+        }
+    }
+}
+
+/// `value` out of `from`, as a rounded share of `to`.
+fn rescale(value: u32, from: u32, to: u32) -> u32 {
+    if from == 0 {
+        return to;
+    }
+    (value * to + from / 2) / from
+}
+
+/// One control across both: hardware brightness down to its minimum, then
+/// software dimming for very dark rooms.
+pub struct Extended<'a> {
+    pub range: ExtendedRange,
+    pub hardware: Box<dyn BrightnessControl + 'a>,
+    pub software: Box<dyn BrightnessControl + 'a>,
+}
+
+impl BrightnessControl for Extended<'_> {
+    fn get(&mut self) -> Result<u8, Error> {
+        Ok(self
+            .range
+            .combine(self.hardware.get()?, self.software.get()?))
+    }
+
+    fn set(&mut self, percent: u8) -> Result<(), Error> {
+        let (hardware, software) = self.range.split(percent);
+        self.hardware.set(hardware)?;
+        self.software.set(software)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    use crate::app::tests::scripted_desk;
+    use crate::ddc::WritePolicy;
+    use crate::display::enumerate_displays;
+    use crate::gamma::{self, SessionType};
+
+    /// A control that remembers what it was set to, for watching the
+    /// extended range drive its parts.
+    struct Recorded(Rc<RefCell<u8>>);
+
+    impl BrightnessControl for Recorded {
+        fn get(&mut self) -> Result<u8, Error> {
+            Ok(*self.0.borrow())
+        }
+
+        fn set(&mut self, percent: u8) -> Result<(), Error> {
+            *self.0.borrow_mut() = percent;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_split_and_combine() {
+        let range = ExtendedRange::new(&RangeConfig::default());
+        assert_eq!((range.hardware_min, range.software_min), (1, 20));
+
+        assert_eq!(range.split(100), (100, 100));
+        assert_eq!(range.split(45), (1, 100));
+        assert_eq!(range.split(44), (1, 98));
+        assert_eq!(range.split(0), (1, 20));
+
+        for percent in 0..=100 {
+            let (hardware, software) = range.split(percent);
+            assert_eq!(range.combine(hardware, software), percent, "at {}", percent);
+        }
+        // Dimming beyond the range still lands at the bottom of the slider
+        assert_eq!(range.combine(60, 10), 0);
+    }
+
+    #[test]
+    fn test_degenerate_ranges() {
+        let no_dimming = ExtendedRange {
+            hardware_min: 0,
+            software_min: 100,
+        };
+        assert_eq!(no_dimming.split(0), (0, 100));
+        assert_eq!(no_dimming.split(40), (40, 100));
+
+        let fixed_hardware = ExtendedRange {
+            hardware_min: 100,
+            software_min: 0,
+        };
+        assert_eq!(fixed_hardware.split(100), (100, 100));
+        assert_eq!(fixed_hardware.split(30), (100, 30));
+        assert_eq!(fixed_hardware.combine(100, 30), 30);
+    }
+
+    #[test]
+    fn test_extended_drives_both_controls() {
+        let (hardware, software) = (Rc::new(RefCell::new(70)), Rc::new(RefCell::new(100)));
+        let mut extended = Extended {
+            range: ExtendedRange {
+                hardware_min: 0,
+                software_min: 0,
+            },
+            hardware: Box::new(Recorded(hardware.clone())),
+            software: Box::new(Recorded(software.clone())),
+        };
+
+        assert_eq!(extended.get().unwrap(), 85);
+        extended.set(25).unwrap();
+        assert_eq!((*hardware.borrow(), *software.borrow()), (0, 50));
+        extended.set(75).unwrap();
+        assert_eq!((*hardware.borrow(), *software.borrow()), (50, 100));
+    }
+
+    #[test]
+    fn test_ddc_and_gamma_controls() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 40 --bus 7", "")
+                .on("xrandr --output DP-0 --brightness 0.5", ""),
+        );
+        let ddc = Ddc::new(runner.clone(), false).with_policy(WritePolicy {
+            verify: false,
+            ..WritePolicy::immediate()
+        });
+        let displays = enumerate_displays(runner.as_ref()).unwrap();
+        let backend = gamma::create_backend(SessionType::X11, runner.clone()).unwrap();
+
+        let mut ddc_control = hardware(runner.as_ref(), &ddc, &displays[0]);
+        assert_eq!(ddc_control.get().unwrap(), 70);
+        ddc_control.set(40).unwrap();
+
+        let mut gamma_control = GammaBrightness {
+            backend: backend.as_ref(),
+            output: "DP-0".into(),
+        };
+        gamma_control.set(50).unwrap();
+        assert_eq!(
+            runner.calls().iter().rev().take(2).collect::<Vec<_>>(),
+            [
+                "xrandr --output DP-0 --brightness 0.5",
+                "ddcutil setvcp 10 40 --bus 7"
+            ]
+        );
+    }
+}
@@ -0,0 +1,795 @@
+//! Command-line entry points, for scripting without opening the window.
+
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use crate::brightness::{
+    self, BrightnessControl, EXTENDED, Extended, ExtendedRange, GammaBrightness,
+};
+use crate::commands::{CommandRunner, SystemRunner};
+use crate::config::{self, Config};
+use crate::daemon;
+use crate::ddc::{Ddc, VCP_INPUT_SOURCE, VcpValue, WriteOutcome, input_name, parse_input};
+use crate::display::{Display, enumerate_displays};
+use crate::error::Error;
+use crate::gamma::{self, SessionType};
+use crate::ipc::{self, Client, DisplayInfo, Level, Reading};
+
+const USAGE: &str = "Usage:
+  dim-and-dimmer                                Launch the GUI
+  dim-and-dimmer list                           List detected displays
+  dim-and-dimmer get FEATURE                    Read brightness, contrast, volume or extended
+  dim-and-dimmer set FEATURE VALUE              Set a feature to VALUE% or change it by +N/-N
+  dim-and-dimmer dim LEVEL                      Software dimming to LEVEL% (100 removes it)
+  dim-and-dimmer input                          List input sources
+  dim-and-dimmer input SOURCE                   Switch input (DP-1, HDMI-2, USB-C, a label or 0xNN)
+  dim-and-dimmer daemon                         Serve the other commands and the GUI from one process
+
+The extended feature is brightness that carries on into software dimming
+once the monitor's own brightness is at its minimum.
+
+Options:
+  --display ID    Pick a display by ddcutil number or model name (default: the first)
+  --all           Apply get, set and dim to every display
+  --json          Print results as JSON
+";
+
+/// Where `list`, `get` and `set` get their answers: the daemon when it's
+/// running, otherwise the monitors themselves.
+trait Monitors {
+    fn list(&mut self) -> Result<Vec<Display>, Error>;
+    fn get(&mut self, display: &Display, feature: &str) -> Result<Reading, Error>;
+    fn set(&mut self, display: &Display, feature: &str, level: Level) -> Result<Reading, Error>;
+}
+
+struct Local<'a> {
+    runner: &'a Arc<dyn CommandRunner>,
+    ddc: &'a Ddc,
+    config: &'a Config,
+    session: SessionType,
+}
+
+impl Local<'_> {
+    /// Run `op` on the display's brightness chained with the software
+    /// dimming of its output.
+    fn extended<T>(
+        &self,
+        display: &Display,
+        op: impl FnOnce(&mut Extended) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let backend = gamma::create_backend(self.session, self.runner.clone())
+            .ok_or_else(|| Error::ToolMissing("xrandr".into()))?;
+        let output = gamma::map_outputs(backend.as_ref(), std::slice::from_ref(display))?
+            .remove(&display.id)
+            .ok_or_else(|| {
+                Error::Failed(format!(
+                    "No {} output found for {}",
+                    backend.name(),
+                    display.name
+                ))
+            })?;
+
+        op(&mut Extended {
+            range: ExtendedRange::new(&self.config.ranges),
+            hardware: brightness::hardware(self.runner.as_ref(), self.ddc, display),
+            software: Box::new(GammaBrightness {
+                backend: backend.as_ref(),
+                output,
+            }),
+        })
+    }
+}
+
+impl Monitors for Local<'_> {
+    fn list(&mut self) -> Result<Vec<Display>, Error> {
+        enumerate_displays(self.runner.as_ref())
+    }
+
+    fn get(&mut self, display: &Display, feature: &str) -> Result<Reading, Error> {
+        if feature == EXTENDED {
+            return self
+                .extended(display, |control| control.get())
+                .map(|p| Reading::percent(display, feature, p));
+        }
+        let code = ipc::feature_code(feature)?;
+        self.ddc
+            .read_vcp(display, code)
+            .map(|v| Reading::vcp(display, feature, v))
+    }
+
+    fn set(&mut self, display: &Display, feature: &str, level: Level) -> Result<Reading, Error> {
+        if feature == EXTENDED {
+            return self.extended(display, |control| {
+                // Only a relative change needs to know where the display is
+                let current = match level {
+                    Level::Relative(_) => control.get()?,
+                    _ => 0,
+                };
+                let percent = level.apply(VcpValue::new(current.into(), 100)).current as u8;
+                control.set(percent)?;
+                Ok(Reading::percent(display, feature, percent))
+            });
+        }
+        let code = ipc::feature_code(feature)?;
+        // Read first, both for relative changes and for the monitor's own range
+        let value = level.apply(self.ddc.read_vcp(display, code)?);
+        let value = self.config.ranges.clamp(code, value);
+
+        self.ddc
+            .set_vcp(display, code, value.current)
+            .check(feature)?;
+        Ok(Reading::vcp(display, feature, value))
+    }
+}
+
+impl Monitors for Client {
+    fn list(&mut self) -> Result<Vec<Display>, Error> {
+        Ok(Client::list(self)?.into_iter().map(Display::from).collect())
+    }
+
+    fn get(&mut self, display: &Display, feature: &str) -> Result<Reading, Error> {
+        Client::get(self, &display.key, feature)
+    }
+
+    fn set(&mut self, display: &Display, feature: &str, level: Level) -> Result<Reading, Error> {
+        Client::set(self, &display.key, feature, level)
+    }
+}
+
+pub fn run(args: &[String]) -> ExitCode {
+    let config = config::default_path()
+        .and_then(|path| Config::load(&path).ok())
+        .unwrap_or_default();
+    let runner: Arc<dyn CommandRunner> = Arc::new(SystemRunner::new(config.commands.timeout()));
+    let ddc = Ddc::new(runner.clone(), true).with_policy(config.writes.policy());
+    let session = gamma::detect_session();
+    let daemon = match args.first().map(String::as_str) {
+        Some("daemon") => None,
+        _ => Client::connect_default(),
+    };
+
+    match execute(
+        args,
+        &runner,
+        &ddc,
+        &config,
+        session,
+        daemon,
+        &mut io::stdout(),
+    ) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            match &e {
+                // Not a failure as such, just a reminder of how to call us
+                Error::Usage(usage) => eprintln!("{}", usage),
+                _ => eprintln!("Error ({}): {}", e.kind(), e.explain()),
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn execute(
+    args: &[String],
+    runner: &Arc<dyn CommandRunner>,
+    ddc: &Ddc,
+    config: &Config,
+    session: SessionType,
+    daemon: Option<Client>,
+    out: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut args = args.to_vec();
+    let display_arg = take_option(&mut args, "--display")?;
+    let all = take_flag(&mut args, "--all");
+    let json = take_flag(&mut args, "--json");
+
+    if all && display_arg.is_some() {
+        return Err(Error::Usage(format!(
+            "--display and --all can't be used together\n\n{}",
+            USAGE
+        )));
+    }
+
+    let Some((command, rest)) = args.split_first() else {
+        return Err(Error::Usage(USAGE.into()));
+    };
+    let targets = |displays| select_displays(displays, display_arg.as_deref(), all);
+
+    let mut local = Local {
+        runner,
+        ddc,
+        config,
+        session,
+    };
+    let via_daemon = daemon.is_some();
+    let mut daemon = daemon;
+    let monitors: &mut dyn Monitors = match daemon.as_mut() {
+        Some(client) => client,
+        None => &mut local,
+    };
+
+    match (command.as_str(), rest) {
+        ("list", []) => {
+            let displays = monitors.list()?;
+            list(&displays, json, out)
+        }
+        ("get", [feature]) => {
+            known_feature(feature)?;
+            let displays = monitors.list()?;
+            for_each(targets(&displays)?, json, out, |display| {
+                monitors.get(display, feature)
+            })
+        }
+        ("set", [feature, value]) => {
+            known_feature(feature)?;
+            let level = Level::parse(value)?;
+            let displays = monitors.list()?;
+            for_each(targets(&displays)?, json, out, |display| {
+                monitors.set(display, feature, level)
+            })
+        }
+        ("dim", [value]) => {
+            // The GUI's slider doesn't go below the floor either
+            let level = match Level::parse(value)? {
+                Level::Absolute(percent) => config.ranges.dimming.clamp(percent),
+                Level::Relative(_) | Level::Raw(_) => {
+                    return Err(Error::Failed(
+                        "Software dimming can't be read back, so give an absolute level".into(),
+                    ));
+                }
+            };
+            let displays = monitors.list()?;
+            // Keeps the daemon's own idea of the level, and its D-Bus
+            // property, in step
+            if via_daemon {
+                return for_each(targets(&displays)?, json, out, |display| {
+                    monitors.set(display, daemon::DIMMING, Level::Absolute(level))
+                });
+            }
+            let backend = gamma::create_backend(session, runner.clone())
+                .ok_or_else(|| Error::ToolMissing("xrandr".into()))?;
+            let outputs = gamma::map_outputs(backend.as_ref(), &displays)?;
+
+            for_each(targets(&displays)?, json, out, |display| {
+                let output = outputs.get(&display.id).cloned().ok_or_else(|| {
+                    Error::Failed(format!(
+                        "No {} output found for {}",
+                        backend.name(),
+                        display.name
+                    ))
+                })?;
+                backend.apply_dimming(&output, level)?;
+                Ok(Reading {
+                    display: display.key.clone(),
+                    name: display.name.clone(),
+                    feature: "dimming".into(),
+                    percent: level,
+                    value: None,
+                    max: None,
+                })
+            })
+        }
+        ("input", [] | [_]) => {
+            let displays = monitors.list()?;
+            let display = select_display(&displays, display_arg.as_deref())?;
+            let daemon = via_daemon.then_some(monitors);
+            input(
+                ddc,
+                daemon,
+                config,
+                display,
+                rest.first().map(String::as_str),
+                out,
+            )
+        }
+        ("daemon", []) => daemon::run(runner.clone(), ddc.clone(), config),
+        ("help" | "--help" | "-h", _) => write!(out, "{}", USAGE).map_err(output_error),
+        ("list" | "get" | "set" | "dim" | "input" | "daemon", _) => Err(Error::Usage(format!(
+            "Wrong arguments for '{}'\n\n{}",
+            command, USAGE
+        ))),
+        _ => Err(Error::Usage(format!(
+            "Unknown command '{}'\n\n{}",
+            command, USAGE
+        ))),
+    }
+}
+
+fn known_feature(feature: &str) -> Result<(), Error> {
+    if feature == EXTENDED {
+        return Ok(());
+    }
+    ipc::feature_code(feature).map(|_| ())
+}
+
+fn output_error(e: io::Error) -> Error {
+    Error::Failed(format!("could not write output: {}", e))
+}
+
+/// Remove a bare `--name` switch from the argument list, returning whether
+/// it was there.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != name);
+    args.len() != before
+}
+
+/// Remove `--name VALUE` from the argument list, wherever it appears.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, Error> {
+    let Some(pos) = args.iter().position(|a| a == name) else {
+        return Ok(None);
+    };
+    if pos + 1 >= args.len() {
+        return Err(Error::Failed(format!("{} needs a value", name)));
+    }
+
+    let value = args.remove(pos + 1);
+    args.remove(pos);
+    Ok(Some(value))
+}
+
+/// Pick a display by ddcutil number or model name; the first one by default.
+fn select_display<'a>(displays: &'a [Display], wanted: Option<&str>) -> Result<&'a Display, Error> {
+    let display = match wanted {
+        Some(wanted) => displays.iter().find(|d| d.matches(wanted)),
+        None => displays.first(),
+    };
+
+    display.ok_or_else(|| match wanted {
+        Some(wanted) => Error::Failed(format!("No display matching '{}'", wanted)),
+        None => Error::Failed("No displays found. Try running: sudo ddcutil detect".into()),
+    })
+}
+
+/// The displays a command applies to: all of them, or the one picked.
+fn select_displays<'a>(
+    displays: &'a [Display],
+    wanted: Option<&str>,
+    all: bool,
+) -> Result<Vec<&'a Display>, Error> {
+    if all && !displays.is_empty() {
+        return Ok(displays.iter().collect());
+    }
+    select_display(displays, wanted).map(|d| vec![d])
+}
+
+/// Run a command against each display, printing every result that worked.
+/// A failing display doesn't stop the others; the first error is returned
+/// once they've all been tried.
+fn for_each(
+    displays: Vec<&Display>,
+    json: bool,
+    out: &mut dyn Write,
+    mut f: impl FnMut(&Display) -> Result<Reading, Error>,
+) -> Result<(), Error> {
+    let mut readings = Vec::new();
+    let mut failure = None;
+    let several = displays.len() > 1;
+
+    for display in displays {
+        match f(display) {
+            Ok(reading) => readings.push(reading),
+            Err(e) => {
+                // Say which one failed; a lone display's error speaks for itself
+                if several {
+                    eprintln!("{}: {}", display.name, e);
+                }
+                failure = failure.or(Some(e));
+            }
+        }
+    }
+
+    if json {
+        let text = serde_json::to_string(&readings).map_err(|e| Error::Failed(e.to_string()))?;
+        writeln!(out, "{}", text).map_err(output_error)?;
+    } else {
+        for r in &readings {
+            writeln!(out, "{}: {} {}%", r.name, r.feature, r.percent).map_err(output_error)?;
+        }
+    }
+
+    failure.map_or(Ok(()), Err)
+}
+
+fn list(displays: &[Display], json: bool, out: &mut dyn Write) -> Result<(), Error> {
+    if json {
+        let listings: Vec<DisplayInfo> = displays.iter().map(DisplayInfo::from).collect();
+        let text = serde_json::to_string(&listings).map_err(|e| Error::Failed(e.to_string()))?;
+        return writeln!(out, "{}", text).map_err(output_error);
+    }
+
+    for d in displays {
+        let mut line = format!("{}  {}  {}  ({})", d.id, d.key, d.name, d.drm_connector);
+        let summary = d.edid.summary();
+        if !summary.is_empty() {
+            line = format!("{}  {}", line, summary);
+        }
+        writeln!(out, "{}", line).map_err(output_error)?;
+    }
+    Ok(())
+}
+
+/// List a display's inputs, or switch it to `source`. Switching goes through
+/// the daemon when there is one, queued behind its other writes.
+fn input(
+    ddc: &Ddc,
+    daemon: Option<&mut dyn Monitors>,
+    config: &Config,
+    display: &Display,
+    source: Option<&str>,
+    out: &mut dyn Write,
+) -> Result<(), Error> {
+    // Labels saved before monitors had stable keys; the GUI rewrites the file
+    let mut config = config.clone();
+    config.adopt_legacy_keys(std::slice::from_ref(display));
+
+    let label = |code: u8| {
+        config
+            .input_label(display.key(), code)
+            .map(str::to_string)
+            .unwrap_or_else(|| input_name(code))
+    };
+
+    if let Some(source) = source {
+        let code = config
+            .input_for_label(display.key(), source)
+            .or_else(|| parse_input(source))
+            .ok_or_else(|| Error::Failed(format!("Unknown input source '{}'", source)))?;
+
+        let feature = ipc::feature(VCP_INPUT_SOURCE);
+        match daemon {
+            Some(monitors) => monitors
+                .set(display, &feature, Level::Raw(code.into()))
+                .map(|_| ())?,
+            None => match ddc.set_vcp(display, VCP_INPUT_SOURCE, code.into()) {
+                WriteOutcome::Unresponsive(e) => return Err(e),
+                outcome if !outcome.is_applied() => {
+                    return Err(Error::Failed(format!(
+                        "Failed to switch {} to {}: {}",
+                        display.name,
+                        label(code),
+                        outcome
+                    )));
+                }
+                _ => {}
+            },
+        }
+        return writeln!(out, "{}: switched to {}", display.name, label(code))
+            .map_err(output_error);
+    }
+
+    let caps = ddc.capabilities(display)?;
+    let inputs = caps
+        .feature(VCP_INPUT_SOURCE)
+        .map(|f| f.values.clone())
+        .ok_or(Error::UnsupportedFeature(VCP_INPUT_SOURCE))?;
+    let current = ddc
+        .read_vcp(display, VCP_INPUT_SOURCE)
+        .ok()
+        .map(|v| v.low_byte());
+
+    for code in inputs {
+        let marker = if current == Some(code) { "*" } else { " " };
+        let name = input_name(code);
+        let line = match config.input_label(display.key(), code) {
+            Some(user) => format!("{} {:<12} {}", marker, name, user),
+            None => format!("{} {}", marker, name),
+        };
+        writeln!(out, "{}", line).map_err(output_error)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::{DELL_CAPABILITIES, scripted_desk};
+    use crate::commands::ScriptedRunner;
+    use crate::ddc::WritePolicy;
+
+    fn exec(runner: &Arc<ScriptedRunner>, config: &Config, args: &[&str]) -> Result<String, Error> {
+        exec_via(runner, config, None, args)
+    }
+
+    fn exec_via(
+        runner: &Arc<ScriptedRunner>,
+        config: &Config,
+        daemon: Option<Client>,
+        args: &[&str],
+    ) -> Result<String, Error> {
+        // Scripted monitors never change, so reading writes back would fail
+        let policy = WritePolicy {
+            verify: false,
+            ..WritePolicy::immediate()
+        };
+        let ddc = Ddc::new(runner.clone(), false).with_policy(policy);
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let runner: Arc<dyn CommandRunner> = runner.clone();
+        let mut out = Vec::new();
+        execute(
+            &args,
+            &runner,
+            &ddc,
+            config,
+            SessionType::X11,
+            daemon,
+            &mut out,
+        )?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_list() {
+        let runner = Arc::new(scripted_desk());
+
+        let out = exec(&runner, &Config::default(), &["list"]).unwrap();
+        assert_eq!(
+            out,
+            "1  DEL-40E6-ABC123  DELL U2715H  (card1-DP-1)\n2  SAM-04D2-XYZ789  Samsung 27\"  (card1-HDMI-A-1)\n"
+        );
+
+        let out = exec(&runner, &Config::default(), &["list", "--json"]).unwrap();
+        let listed: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(listed[1]["name"], "Samsung 27\"");
+        assert_eq!(listed[0]["i2c_bus"], "/dev/i2c-7");
+        assert_eq!(listed[0]["display"], "DEL-40E6-ABC123");
+        assert_eq!(listed[0]["product_code"], 0x40E6);
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 40 --bus 7", "")
+                .on("ddcutil setvcp 10 80 --bus 7", "")
+                .on("ddcutil setvcp 10 30 --bus 8", "")
+                .on("ddcutil setvcp 10 40 --bus 8", "")
+                .on("ddcutil setvcp 10 1 --bus 7", ""),
+        );
+        let config = Config::default();
+
+        let out = exec(&runner, &config, &["get", "brightness", "--display", "2"]).unwrap();
+        assert_eq!(out, "Samsung 27\": brightness 20%\n");
+
+        let out = exec(&runner, &config, &["set", "brightness", "40", "--all"]).unwrap();
+        assert_eq!(
+            out,
+            "DELL U2715H: brightness 40%\nSamsung 27\": brightness 40%\n"
+        );
+
+        // Relative changes start from what the monitor reports
+        let out = exec(&runner, &config, &["set", "brightness", "+10", "--all"]).unwrap();
+        assert_eq!(
+            out,
+            "DELL U2715H: brightness 80%\nSamsung 27\": brightness 30%\n"
+        );
+
+        // Not below the floor the GUI's slider keeps to
+        let out = exec(
+            &runner,
+            &config,
+            &["set", "brightness", "-90", "--display", "1"],
+        )
+        .unwrap();
+        assert_eq!(out, "DELL U2715H: brightness 1%\n");
+
+        let out = exec(&runner, &config, &["--json", "get", "contrast"]).unwrap();
+        let read: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(read[0]["percent"], 40);
+        assert_eq!(read[0]["max"], 100);
+    }
+
+    #[test]
+    fn test_extended_range() {
+        let verbose = "HDMI-A-0 connected 2560x1440+2560+0 (0x1c4) normal (normal left inverted right x axis y axis) 597mm x 336mm
+\tIdentifier: 0x1c0
+\tBrightness: 0.50
+";
+        let runner = Arc::new(
+            scripted_desk()
+                .on("xrandr --verbose", verbose)
+                .on("ddcutil setvcp 10 28 --bus 8", "")
+                .on("xrandr --output HDMI-A-0 --brightness 1", ""),
+        );
+        let config = Config::default();
+
+        // Brightness 20% with dimming at 50% sits low in the dimming part
+        let verbose_reads = || {
+            runner
+                .calls()
+                .iter()
+                .filter(|c| *c == "xrandr --verbose")
+                .count()
+        };
+        let out = exec(&runner, &config, &["get", "extended", "--display", "2"]).unwrap();
+        assert_eq!(out, "Samsung 27\": extended 17%\n");
+        let for_get = verbose_reads();
+
+        let out = exec(
+            &runner,
+            &config,
+            &["set", "extended", "60", "--display", "2"],
+        )
+        .unwrap();
+        assert_eq!(out, "Samsung 27\": extended 60%\n");
+        let calls = runner.calls();
+        assert_eq!(
+            &calls[calls.len() - 2..],
+            [
+                "ddcutil setvcp 10 28 --bus 8",
+                "xrandr --output HDMI-A-0 --brightness 1"
+            ]
+        );
+        // Unlike the get, an absolute level skips reading the dimming back,
+        // which not every session can do
+        assert_eq!(verbose_reads(), 2 * for_get - 1);
+    }
+
+    #[test]
+    fn test_commands_go_through_daemon() {
+        let daemon_runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 40 --bus 7", "")
+                .on("ddcutil setvcp 10 40 --bus 8", "")
+                .on("xrandr --output HDMI-A-0 --brightness 0.7", "")
+                .on("ddcutil setvcp 60 15 --bus 8", ""),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let socket = crate::daemon::tests::spawn_daemon(daemon_runner.clone(), dir.path());
+        let runner = Arc::new(ScriptedRunner::new());
+        let config = Config::default();
+
+        let daemon = Client::connect(&socket).ok();
+        let out = exec_via(
+            &runner,
+            &config,
+            daemon,
+            &["set", "brightness", "40", "--all"],
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            "DELL U2715H: brightness 40%\nSamsung 27\": brightness 40%\n"
+        );
+
+        let daemon = Client::connect(&socket).ok();
+        let out = exec_via(
+            &runner,
+            &config,
+            daemon,
+            &["get", "brightness", "--display", "2"],
+        )
+        .unwrap();
+        assert_eq!(out, "Samsung 27\": brightness 40%\n");
+
+        // Dimming and input switches are the daemon's to make too
+        let daemon = Client::connect(&socket).ok();
+        let out = exec_via(&runner, &config, daemon, &["dim", "70", "--display", "2"]).unwrap();
+        assert_eq!(out, "Samsung 27\": dimming 70%\n");
+        let daemon = Client::connect(&socket).ok();
+        let out = exec_via(
+            &runner,
+            &config,
+            daemon,
+            &["input", "dp1", "--display", "2"],
+        )
+        .unwrap();
+        assert_eq!(out, "Samsung 27\": switched to DP-1\n");
+        assert!(
+            daemon_runner
+                .calls()
+                .contains(&"ddcutil setvcp 60 15 --bus 8".to_string())
+        );
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn test_dim() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("xrandr --output DP-0 --brightness 0.7", "")
+                .on("xrandr --output HDMI-A-0 --brightness 0.7", "")
+                .on("xrandr --output DP-0 --brightness 0.2", ""),
+        );
+
+        let out = exec(&runner, &Config::default(), &["dim", "70", "--all"]).unwrap();
+        assert_eq!(out, "DELL U2715H: dimming 70%\nSamsung 27\": dimming 70%\n");
+        assert!(exec(&runner, &Config::default(), &["dim", "-10"]).is_err());
+
+        // Too dark to find the slider again, so held at the configured floor
+        let out = exec(&runner, &Config::default(), &["dim", "5", "--display", "1"]).unwrap();
+        assert_eq!(out, "DELL U2715H: dimming 20%\n");
+    }
+
+    #[test]
+    fn test_list_inputs() {
+        let runner =
+            Arc::new(scripted_desk().on("ddcutil getvcp 60 --bus 7 --terse", "VCP 60 SNC x0f"));
+        let mut config = Config::default();
+        config.set_input_label("DELL U2715H", 0x11, "Desktop");
+
+        let out = exec(&runner, &config, &["input"]).unwrap();
+        assert_eq!(out, "  VGA-1\n* DP-1\n  HDMI-1       Desktop\n");
+    }
+
+    #[test]
+    fn test_switch_input_by_name_and_label() {
+        let runner = Arc::new(scripted_desk().on("ddcutil setvcp 60 17 --bus 7", ""));
+        let mut config = Config::default();
+        config.set_input_label("DELL U2715H", 0x11, "Desktop");
+
+        let out = exec(&runner, &config, &["input", "desktop"]).unwrap();
+        assert_eq!(out, "DELL U2715H: switched to Desktop\n");
+
+        let out = exec(&runner, &config, &["input", "--display", "1", "HDMI-1"]).unwrap();
+        assert_eq!(out, "DELL U2715H: switched to Desktop\n");
+    }
+
+    #[test]
+    fn test_switch_on_second_display() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil capabilities --bus 8 --verbose", DELL_CAPABILITIES)
+                .on("ddcutil setvcp 60 15 --bus 8", ""),
+        );
+
+        let out = exec(
+            &runner,
+            &Config::default(),
+            &["--display", "2", "input", "dp1"],
+        )
+        .unwrap();
+        assert_eq!(out, "Samsung 27\": switched to DP-1\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        let runner = Arc::new(scripted_desk());
+        let config = Config::default();
+
+        assert!(exec(&runner, &config, &["input", "thunderbolt"]).is_err());
+        assert!(exec(&runner, &config, &["input", "--display", "9"]).is_err());
+        assert!(matches!(
+            exec(&runner, &config, &["bogus"]),
+            Err(Error::Usage(usage)) if usage.contains("Unknown command 'bogus'")
+        ));
+        assert!(exec(&runner, &config, &["input", "--display"]).is_err());
+        assert!(exec(&runner, &config, &["get", "sharpness"]).is_err());
+        assert!(exec(&runner, &config, &["set", "brightness"]).is_err());
+        assert!(matches!(
+            exec(
+                &runner,
+                &config,
+                &["get", "brightness", "--all", "--display", "1"]
+            ),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let runner = Arc::new(scripted_desk().fail(
+            "ddcutil setvcp 60 17 --bus 7",
+            "Open failed for /dev/i2c-7: Permission denied",
+        ));
+        let err = exec(&runner, &Config::default(), &["input", "HDMI-1"]).unwrap_err();
+        assert_eq!(err, Error::PermissionDenied("/dev/i2c-7".into()));
+        assert_eq!(err.exit_code(), 4);
+
+        // The Samsung has no capabilities, so it can't list inputs
+        let err = exec(&runner, &Config::default(), &["input", "--display", "2"]).unwrap_err();
+        assert!(matches!(err, Error::Failed(_)));
+
+        let runner = Arc::new(ScriptedRunner::new().missing("ddcutil"));
+        let err = exec(&runner, &Config::default(), &["input"]).unwrap_err();
+        assert_eq!(err, Error::ToolMissing("ddcutil".into()));
+    }
+
+    #[test]
+    fn test_no_displays() {
+        let runner = Arc::new(ScriptedRunner::new().on("ddcutil detect", ""));
+        assert!(exec(&runner, &Config::default(), &["input"]).is_err());
+    }
+}
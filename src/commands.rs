@@ -1,31 +1,284 @@
-use std::process::Command;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-pub fn command_exists(cmd: &str, args: &[&str]) -> bool {
-    Command::new(cmd).args(args).output().is_ok()
+use crate::error::Error;
+
+/// How often a running command is checked for exit, timeout or cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Abstraction over spawning external tools so callers can be exercised
+/// against recorded output instead of real hardware.
+pub trait CommandRunner: Send + Sync {
+    fn command_exists(&self, cmd: &str, args: &[&str]) -> bool;
+    fn run_capture(&self, cmd: &str, args: &[&str]) -> Result<String, Error>;
+
+    fn run(&self, cmd: &str, args: &[&str]) -> Result<(), Error> {
+        self.run_capture(cmd, args).map(|_| ())
+    }
+
+    /// Kill every command started so far that is still running. Commands
+    /// started afterwards are unaffected.
+    fn cancel_pending(&self) {}
+
+    /// Where sysfs is mounted, for the connectors and backlights read
+    /// alongside the tools' output.
+    fn sysfs(&self) -> PathBuf {
+        PathBuf::from("/sys")
+    }
+}
+
+/// Shared flag telling in-flight commands to give up.
+#[derive(Debug, Clone, Default)]
+struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Runs commands on the host via `std::process::Command`, killing any that
+/// outlive the timeout so a wedged I2C bus can't hang the caller.
+pub struct SystemRunner {
+    timeout: Duration,
+    cancel: Mutex<CancelToken>,
 }
 
-pub fn run(cmd: &str, args: &[&str]) -> Result<(), String> {
-    let output = Command::new(cmd)
-        .args(args)
-        .output()
-        .map_err(|e| e.to_string())?;
+impl SystemRunner {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            cancel: Mutex::new(CancelToken::default()),
+        }
+    }
+
+    fn output(&self, cmd: &str, args: &[&str]) -> Result<Output, Error> {
+        let cancel = self.cancel.lock().unwrap().clone();
+        let mut child = Command::new(cmd)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::from_io(cmd, e))?;
+
+        // Drain both pipes as we go so a chatty child can't block on a full one
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+        let deadline = Instant::now() + self.timeout;
+
+        let status = loop {
+            if let Some(status) = child.try_wait().map_err(|e| Error::from_io(cmd, e))? {
+                break status;
+            }
 
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).into())
+            let failure = if cancel.is_cancelled() {
+                Error::Cancelled
+            } else if Instant::now() >= deadline {
+                Error::Timeout(format!(
+                    "{} {} after {:?}",
+                    cmd,
+                    args.join(" "),
+                    self.timeout
+                ))
+            } else {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            };
+
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(failure);
+        };
+
+        Ok(Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
     }
 }
 
-pub fn run_capture(cmd: &str, args: &[&str]) -> Result<String, String> {
-    let output = Command::new(cmd)
-        .args(args)
-        .output()
-        .map_err(|e| e.to_string())?;
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+impl CommandRunner for SystemRunner {
+    fn command_exists(&self, cmd: &str, args: &[&str]) -> bool {
+        !matches!(self.output(cmd, args), Err(Error::ToolMissing(_)))
+    }
+
+    fn run_capture(&self, cmd: &str, args: &[&str]) -> Result<String, Error> {
+        let output = self.output(cmd, args)?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into())
+        } else {
+            Err(Error::Failed(
+                String::from_utf8_lossy(&output.stderr).trim().into(),
+            ))
+        }
+    }
+
+    fn cancel_pending(&self) {
+        let mut token = self.cancel.lock().unwrap();
+        token.cancel();
+        *token = CancelToken::default();
+    }
+}
+
+#[cfg(test)]
+pub use scripted::ScriptedRunner;
+
+#[cfg(test)]
+mod scripted {
+    use std::collections::{HashMap, HashSet};
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use super::CommandRunner;
+    use crate::error::Error;
+
+    /// Replays canned output keyed by the full command line and records
+    /// every invocation for later assertions.
+    #[derive(Default)]
+    pub struct ScriptedRunner {
+        responses: Mutex<HashMap<String, Result<String, Error>>>,
+        missing: HashSet<String>,
+        calls: Mutex<Vec<String>>,
+        sysfs: PathBuf,
+    }
+
+    impl ScriptedRunner {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn on(self, command_line: &str, output: &str) -> Self {
+            self.set(command_line, Ok(output.to_string()));
+            self
+        }
+
+        pub fn fail(self, command_line: &str, stderr: &str) -> Self {
+            self.set(command_line, Err(Error::Failed(stderr.to_string())));
+            self
+        }
+
+        /// Read sysfs from a fixture tree. Without one nothing is found there,
+        /// whatever the machine running the tests has plugged in.
+        pub fn with_sysfs(mut self, root: &Path) -> Self {
+            self.sysfs = root.to_path_buf();
+            self
+        }
+
+        pub fn missing(mut self, cmd: &str) -> Self {
+            self.missing.insert(cmd.to_string());
+            self
+        }
+
+        /// Replace the response for a command line after construction.
+        pub fn set(&self, command_line: &str, response: Result<String, Error>) {
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(command_line.to_string(), response);
+        }
+
+        pub fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl CommandRunner for ScriptedRunner {
+        fn command_exists(&self, cmd: &str, _args: &[&str]) -> bool {
+            !self.missing.contains(cmd)
+        }
+
+        fn run_capture(&self, cmd: &str, args: &[&str]) -> Result<String, Error> {
+            let command_line = std::iter::once(cmd)
+                .chain(args.iter().copied())
+                .collect::<Vec<_>>()
+                .join(" ");
+            self.calls.lock().unwrap().push(command_line.clone());
+
+            if self.missing.contains(cmd) {
+                return Err(Error::ToolMissing(cmd.to_string()));
+            }
+
+            self.responses
+                .lock()
+                .unwrap()
+                .get(&command_line)
+                .cloned()
+                .unwrap_or_else(|| {
+                    Err(Error::Failed(format!(
+                        "unscripted command: {}",
+                        command_line
+                    )))
+                })
+        }
+
+        fn sysfs(&self) -> PathBuf {
+            self.sysfs.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captures_output() {
+        let runner = SystemRunner::new(Duration::from_secs(10));
+
+        assert_eq!(
+            runner.run_capture("sh", &["-c", "echo hello"]),
+            Ok("hello\n".into())
+        );
+        assert_eq!(
+            runner.run_capture("sh", &["-c", "echo oops >&2; exit 1"]),
+            Err(Error::Failed("oops".into()))
+        );
+        assert!(!runner.command_exists("dim-and-dimmer-no-such-tool", &[]));
+    }
+
+    #[test]
+    fn test_hung_command_is_killed() {
+        let runner = SystemRunner::new(Duration::from_millis(100));
+
+        let started = Instant::now();
+        let result = runner.run_capture("sleep", &["5"]);
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_cancel_pending() {
+        let runner = Arc::new(SystemRunner::new(Duration::from_secs(10)));
+
+        let background = runner.clone();
+        let handle = thread::spawn(move || background.run_capture("sleep", &["5"]));
+        thread::sleep(Duration::from_millis(100));
+        runner.cancel_pending();
 
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).into())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).into())
+        assert_eq!(handle.join().unwrap(), Err(Error::Cancelled));
+        // Later commands get a fresh token
+        assert!(runner.run_capture("true", &[]).is_ok());
     }
 }
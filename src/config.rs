@@ -0,0 +1,561 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ddc::{VCP_AUDIO_VOLUME, VCP_BRIGHTNESS, VCP_CONTRAST, VcpValue, WritePolicy};
+use crate::display::Display;
+
+/// User settings persisted as TOML under `$XDG_CONFIG_HOME/dim-and-dimmer`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Per-monitor settings keyed by [`crate::display::Display::key`].
+    pub monitors: BTreeMap<String, MonitorConfig>,
+    /// How DDC writes are retried and verified.
+    pub writes: WriteConfig,
+    pub commands: CommandConfig,
+    pub ui: UiConfig,
+    pub steps: StepConfig,
+    /// Limits of the percentage sliders, also applied to tray presets.
+    pub ranges: RangeConfig,
+    /// Optional parts of the app, all on unless switched off here.
+    pub features: FeatureConfig,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    /// User names for input sources, keyed by the VCP 0x60 value in hex.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub input_labels: BTreeMap<String, String>,
+    /// Left alone when sliders drive all displays together.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unlinked: bool,
+    /// Percentage points added to the shared slider value in "All displays"
+    /// mode, keyed by VCP code in hex, so mismatched panels stay matched.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub offsets: BTreeMap<String, i8>,
+    /// Last brightness and contrast set from this app, in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contrast: Option<u8>,
+    /// Software dimming, re-applied at startup since gamma doesn't survive
+    /// a restart of the session.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimming: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WriteConfig {
+    pub attempts: u32,
+    pub backoff_ms: u64,
+    pub verify: bool,
+}
+
+impl Default for WriteConfig {
+    fn default() -> Self {
+        let policy = WritePolicy::default();
+        Self {
+            attempts: policy.attempts,
+            backoff_ms: policy.backoff.as_millis() as u64,
+            verify: policy.verify,
+        }
+    }
+}
+
+impl WriteConfig {
+    pub fn policy(&self) -> WritePolicy {
+        WritePolicy {
+            attempts: self.attempts.max(1),
+            backoff: Duration::from_millis(self.backoff_ms),
+            verify: self.verify,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandConfig {
+    /// How long an external tool may run before it is killed.
+    pub timeout_ms: u64,
+}
+
+impl Default for CommandConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 10_000 }
+    }
+}
+
+impl CommandConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.max(1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Send slider values to the monitor while dragging, not just on release.
+    pub live_updates: bool,
+    /// Minimum gap between live updates to one display.
+    pub live_interval_ms: u64,
+    /// Show every display as a card instead of one at a time.
+    pub dashboard: bool,
+    /// Key of the display picked last, selected again at startup.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<String>,
+    /// Start in "All displays" mode.
+    pub all_displays: bool,
+    /// One brightness slider that carries on into software dimming.
+    pub extended_range: bool,
+    /// Where `features.tray` used to live, still read from older configs.
+    #[serde(skip_serializing)]
+    pub tray: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            live_updates: true,
+            live_interval_ms: 150,
+            dashboard: false,
+            selected: None,
+            all_displays: false,
+            extended_range: false,
+            tray: true,
+        }
+    }
+}
+
+impl UiConfig {
+    pub fn live_interval(&self) -> Duration {
+        Duration::from_millis(self.live_interval_ms)
+    }
+}
+
+/// Percentage points per step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StepConfig {
+    /// Slider granularity, also used by the arrow keys.
+    pub slider: u8,
+    /// One notch of the mouse wheel over the tray icon.
+    pub scroll: u8,
+}
+
+impl Default for StepConfig {
+    fn default() -> Self {
+        Self {
+            slider: 1,
+            scroll: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PercentRange {
+    pub min: u8,
+    pub max: u8,
+}
+
+impl PercentRange {
+    const fn new(min: u8, max: u8) -> Self {
+        Self { min, max }
+    }
+
+    /// The range as a slider takes it, repaired if the file has it backwards
+    /// or past 100.
+    pub fn range(&self) -> RangeInclusive<u8> {
+        let max = self.max.min(100);
+        self.min.min(max)..=max
+    }
+
+    pub fn clamp(&self, percent: u8) -> u8 {
+        let range = self.range();
+        percent.clamp(*range.start(), *range.end())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RangeConfig {
+    pub brightness: PercentRange,
+    pub contrast: PercentRange,
+    pub volume: PercentRange,
+    /// Below 20% most panels are too dark to find the slider again.
+    pub dimming: PercentRange,
+}
+
+impl RangeConfig {
+    /// `value` kept within the range configured for its feature, if it has
+    /// one, so no frontend goes below the floor the GUI's sliders keep to.
+    pub fn clamp(&self, code: u8, value: VcpValue) -> VcpValue {
+        let range = match code {
+            VCP_BRIGHTNESS => self.brightness,
+            VCP_CONTRAST => self.contrast,
+            VCP_AUDIO_VOLUME => self.volume,
+            _ => return value,
+        };
+        let percent = range.clamp(value.percent());
+        if percent == value.percent() {
+            value
+        } else {
+            value.with_percent(percent)
+        }
+    }
+}
+
+impl Default for RangeConfig {
+    fn default() -> Self {
+        Self {
+            brightness: PercentRange::new(1, 100),
+            contrast: PercentRange::new(1, 100),
+            volume: PercentRange::new(0, 100),
+            dimming: PercentRange::new(20, 100),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureConfig {
+    /// Keep running in the system tray when the window is closed.
+    pub tray: bool,
+    /// Publish the daemon on the D-Bus session bus.
+    pub dbus: bool,
+    /// Pick up monitors plugged in or unplugged while the app is running.
+    pub hotplug: bool,
+    pub dimming: bool,
+    pub power: bool,
+    pub inputs: bool,
+    pub audio: bool,
+    pub color: bool,
+    pub advanced: bool,
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        Self {
+            tray: true,
+            dbus: true,
+            hotplug: true,
+            dimming: true,
+            power: true,
+            inputs: true,
+            audio: true,
+            color: true,
+            advanced: true,
+        }
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("dim-and-dimmer").join("config.toml"))
+}
+
+/// Hex key for per-code tables, as written in the config file.
+fn code_key(code: u8) -> String {
+    format!("{:02X}", code)
+}
+
+impl Config {
+    /// Load the config file, treating a missing file as empty.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text)
+                .map(Self::migrate)
+                .map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Carry settings from older configs over to where they live now.
+    fn migrate(mut self) -> Self {
+        if !self.ui.tray {
+            self.features.tray = false;
+            self.ui.tray = true;
+        }
+        self
+    }
+
+    /// Write the config by renaming a temporary file over the old one, so a
+    /// crash mid-write never leaves a truncated config behind.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, text).map_err(|e| format!("{}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn input_label(&self, monitor: &str, code: u8) -> Option<&str> {
+        self.monitors
+            .get(monitor)?
+            .input_labels
+            .get(&code_key(code))
+            .map(String::as_str)
+    }
+
+    /// Find the input a user label refers to, ignoring case.
+    pub fn input_for_label(&self, monitor: &str, label: &str) -> Option<u8> {
+        self.monitors
+            .get(monitor)?
+            .input_labels
+            .iter()
+            .find(|(_, l)| l.eq_ignore_ascii_case(label.trim()))
+            .and_then(|(code, _)| u8::from_str_radix(code, 16).ok())
+    }
+
+    /// Set or, with an empty label, clear the user's name for an input.
+    pub fn set_input_label(&mut self, monitor: &str, code: u8, label: &str) {
+        let monitor_config = self.monitors.entry(monitor.to_string()).or_default();
+
+        if label.trim().is_empty() {
+            monitor_config.input_labels.remove(&code_key(code));
+        } else {
+            monitor_config
+                .input_labels
+                .insert(code_key(code), label.to_string());
+        }
+    }
+
+    pub fn is_linked(&self, monitor: &str) -> bool {
+        self.monitors.get(monitor).is_none_or(|m| !m.unlinked)
+    }
+
+    pub fn set_linked(&mut self, monitor: &str, linked: bool) {
+        self.monitors
+            .entry(monitor.to_string())
+            .or_default()
+            .unlinked = !linked;
+    }
+
+    pub fn offset(&self, monitor: &str, code: u8) -> i8 {
+        self.monitors
+            .get(monitor)
+            .and_then(|m| m.offsets.get(&code_key(code)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Move settings saved under a model name, as older versions keyed
+    /// them, to the display's stable key. Returns whether anything moved.
+    pub fn adopt_legacy_keys(&mut self, displays: &[Display]) -> bool {
+        let mut moved = false;
+
+        for display in displays {
+            if display.name == display.key || self.monitors.contains_key(&display.key) {
+                continue;
+            }
+            if let Some(settings) = self.monitors.remove(&display.name) {
+                self.monitors.insert(display.key.clone(), settings);
+                moved = true;
+            }
+            if self.ui.selected.as_deref() == Some(display.name.as_str()) {
+                self.ui.selected = Some(display.key.clone());
+                moved = true;
+            }
+        }
+        moved
+    }
+
+    pub fn monitor(&self, monitor: &str) -> Option<&MonitorConfig> {
+        self.monitors.get(monitor)
+    }
+
+    pub fn monitor_mut(&mut self, monitor: &str) -> &mut MonitorConfig {
+        self.monitors.entry(monitor.to_string()).or_default()
+    }
+
+    pub fn set_offset(&mut self, monitor: &str, code: u8, offset: i8) {
+        let offsets = &mut self
+            .monitors
+            .entry(monitor.to_string())
+            .or_default()
+            .offsets;
+
+        if offset == 0 {
+            offsets.remove(&code_key(code));
+        } else {
+            offsets.insert(code_key(code), offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("config.toml");
+
+        let mut config = Config::default();
+        config.set_input_label("DELL U2715H", 0x0F, "Work laptop");
+        config.set_input_label("DELL U2715H", 0x11, "Desktop");
+        config.save(&path).unwrap();
+
+        let loaded = Config::load(&path).unwrap();
+        assert_eq!(loaded, config);
+        assert_eq!(loaded.input_label("DELL U2715H", 0x0F), Some("Work laptop"));
+        assert!(!path.with_extension("toml.tmp").exists());
+    }
+
+    #[test]
+    fn test_clear_label() {
+        let mut config = Config::default();
+        config.set_input_label("mon", 0x11, "Desktop");
+        config.set_input_label("mon", 0x11, "  ");
+
+        assert_eq!(config.input_label("mon", 0x11), None);
+    }
+
+    #[test]
+    fn test_missing_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            Config::load(&dir.path().join("nope.toml")),
+            Ok(Config::default())
+        );
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let config: Config = toml::from_str(
+            r#"
+            [monitors."DELL U2715H".input_labels]
+            0F = "Work laptop"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.input_label("DELL U2715H", 0x0F), Some("Work laptop"));
+        assert_eq!(
+            config.input_for_label("DELL U2715H", "work LAPTOP"),
+            Some(0x0F)
+        );
+        assert_eq!(config.writes, WriteConfig::default());
+    }
+
+    #[test]
+    fn test_write_and_command_settings() {
+        let config: Config = toml::from_str(
+            r#"
+            [writes]
+            attempts = 0
+            verify = false
+
+            [commands]
+            timeout_ms = 2500
+
+            [ui]
+            live_interval_ms = 50
+            tray = false
+            "#,
+        )
+        .unwrap();
+
+        let policy = config.writes.policy();
+        assert_eq!(policy.attempts, 1);
+        assert_eq!(policy.backoff, WritePolicy::default().backoff);
+        assert!(!policy.verify);
+        assert_eq!(config.commands.timeout(), Duration::from_millis(2500));
+        assert!(config.ui.live_updates);
+        assert_eq!(config.ui.live_interval(), Duration::from_millis(50));
+        assert!(!config.ui.tray);
+    }
+
+    #[test]
+    fn test_tray_setting_moved_to_features() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "[ui]\ntray = false\n").unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert!(!config.features.tray);
+        config.save(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("[ui]\ntray"));
+        assert!(!Config::load(&path).unwrap().features.tray);
+    }
+
+    #[test]
+    fn test_ranges_steps_and_monitor_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut config: Config = toml::from_str(
+            r#"
+            [steps]
+            scroll = 10
+
+            [ranges]
+            brightness = { min = 10, max = 80 }
+            contrast = { min = 90, max = 40 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.steps,
+            StepConfig {
+                slider: 1,
+                scroll: 10
+            }
+        );
+        assert_eq!(config.ranges.brightness.range(), 10..=80);
+        assert_eq!(config.ranges.brightness.clamp(95), 80);
+        assert_eq!(config.ranges.contrast.range(), 40..=40);
+        assert_eq!(config.ranges.dimming.range(), 20..=100);
+
+        config.ui.selected = Some("DELL U2715H".into());
+        config.monitor_mut("DELL U2715H").dimming = Some(70);
+        config.save(&path).unwrap();
+
+        let loaded = Config::load(&path).unwrap();
+        assert_eq!(loaded, config);
+        assert_eq!(
+            loaded.monitor("DELL U2715H").and_then(|m| m.dimming),
+            Some(70)
+        );
+        assert_eq!(
+            loaded.monitor("DELL U2715H").and_then(|m| m.brightness),
+            None
+        );
+    }
+
+    #[test]
+    fn test_links_and_offsets() {
+        let mut config = Config::default();
+        assert!(config.is_linked("mon"));
+        assert_eq!(config.offset("mon", 0x10), 0);
+
+        config.set_linked("mon", false);
+        config.set_offset("mon", 0x10, -15);
+        let text = toml::to_string(&config).unwrap();
+        let loaded: Config = toml::from_str(&text).unwrap();
+        assert!(!loaded.is_linked("mon"));
+        assert_eq!(loaded.offset("mon", 0x10), -15);
+
+        config.set_linked("mon", true);
+        config.set_offset("mon", 0x10, 0);
+        assert_eq!(config.monitors["mon"], MonitorConfig::default());
+    }
+}
@@ -0,0 +1,883 @@
+//! Long-running mode that owns the displays and their write workers, and
+//! serves the protocol in [`crate::ipc`] over a Unix socket so every
+//! frontend shares one view of the monitors.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::brightness::{BrightnessControl, EXTENDED, Extended, ExtendedRange};
+use crate::commands::CommandRunner;
+use crate::config::{Config, RangeConfig};
+use crate::dbus;
+use crate::ddc::{Ddc, VcpValue, WriteOutcome};
+use crate::display::{Display, enumerate_displays};
+use crate::error::Error;
+use crate::gamma::{self, GammaBackend};
+use crate::hotplug::{self, Connected};
+use crate::ipc::{self, DisplayInfo, Level, Reading, Reply, Request};
+use crate::worker::{Action, Completion, DisplayWorker, ddc_writer};
+
+/// Software dimming, served alongside the VCP features in [`ipc::FEATURES`].
+pub const DIMMING: &str = "dimming";
+
+/// How long a value is trusted before the monitor is asked again, since its
+/// own buttons and other tools change it behind the daemon's back.
+const VALUE_MAX_AGE: Duration = Duration::from_secs(5);
+
+/// Detect displays and serve clients until the process is killed.
+pub fn run(runner: Arc<dyn CommandRunner>, ddc: Ddc, config: &Config) -> Result<(), Error> {
+    let path =
+        ipc::socket_path().ok_or_else(|| Error::Failed("XDG_RUNTIME_DIR is not set".into()))?;
+    let gamma = gamma::create_backend(gamma::detect_session(), runner.clone());
+    let daemon = Daemon::new(runner, ddc, gamma, &config.ranges)?;
+    let listener = bind(&path)?;
+    if config.features.hotplug {
+        daemon.watch_hotplug();
+    }
+
+    // Not every session has a bus, and the socket works without it
+    let _bus = match config
+        .features
+        .dbus
+        .then(|| dbus::serve_session(daemon.clone()))
+    {
+        Some(Ok(connection)) => Some(connection),
+        Some(Err(e)) => {
+            eprintln!("D-Bus service unavailable: {}", e);
+            None
+        }
+        None => None,
+    };
+
+    eprintln!(
+        "Serving {} displays on {}",
+        daemon.displays().len(),
+        path.display()
+    );
+    daemon.serve(listener);
+    Ok(())
+}
+
+/// Listen on `path`, replacing a socket left behind by a daemon that is no
+/// longer running.
+pub fn bind(path: &Path) -> Result<UnixListener, Error> {
+    if UnixStream::connect(path).is_ok() {
+        return Err(Error::Failed(format!(
+            "A daemon is already listening on {}",
+            path.display()
+        )));
+    }
+
+    let _ = fs::remove_file(path);
+    UnixListener::bind(path).map_err(|e| Error::Failed(format!("{}: {}", path.display(), e)))
+}
+
+#[derive(Clone)]
+pub struct Daemon {
+    state: Arc<State>,
+}
+
+/// A display key and VCP code. Keys rather than ids, which detection
+/// hands out afresh when monitors come and go.
+type FeatureKey = (String, u8);
+
+struct State {
+    runner: Arc<dyn CommandRunner>,
+    ddc: Ddc,
+    desk: RwLock<Desk>,
+    /// Held while detecting, so only one detection runs at a time.
+    detecting: Mutex<()>,
+    /// Last known value of each display's features, and when it was seen.
+    values: Mutex<HashMap<FeatureKey, (VcpValue, Instant)>>,
+    gamma: Option<Box<dyn GammaBackend>>,
+    /// Software dimming per display key; missing means undimmed.
+    dimming: Mutex<HashMap<String, u8>>,
+    ranges: RangeConfig,
+    extended: ExtendedRange,
+    /// Clients waiting for a write to land, by request id.
+    waiters: Mutex<HashMap<u64, Sender<Completion>>>,
+    next_request: AtomicU64,
+    subscribers: Mutex<Vec<Sender<Reading>>>,
+    display_subscribers: Mutex<Vec<Sender<Vec<Display>>>>,
+}
+
+/// What one detection found. Replaced as a whole when monitors come and
+/// go, since the ids of the displays that stay may change too.
+struct Desk {
+    /// Connectors as they were when detecting.
+    connected: Connected,
+    displays: Vec<Display>,
+    workers: HashMap<String, DisplayWorker>,
+    /// Gamma output name for each display id.
+    gamma_outputs: HashMap<String, String>,
+}
+
+impl Desk {
+    /// Detect the displays, read their capabilities once for all attached
+    /// frontends and start a write worker for each.
+    fn detect(
+        runner: &dyn CommandRunner,
+        ddc: &Ddc,
+        gamma: Option<&dyn GammaBackend>,
+    ) -> Result<(Self, Receiver<Completion>), Error> {
+        let connected = hotplug::connected(&runner.sysfs());
+        let mut displays = enumerate_displays(runner)?;
+        for display in &mut displays {
+            display.capabilities = ddc.capabilities(display).ok();
+        }
+        let (completion_sender, completions) = mpsc::channel();
+        let workers = displays
+            .iter()
+            .map(|display| {
+                let worker = DisplayWorker::spawn(
+                    ddc_writer(ddc.clone()),
+                    display.clone(),
+                    completion_sender.clone(),
+                    Arc::new(AtomicUsize::new(0)),
+                    None,
+                );
+                (display.id.clone(), worker)
+            })
+            .collect();
+
+        let gamma_outputs = gamma
+            .and_then(|backend| gamma::map_outputs(backend, &displays).ok())
+            .unwrap_or_default();
+
+        let desk = Self {
+            connected,
+            displays,
+            workers,
+            gamma_outputs,
+        };
+        Ok((desk, completions))
+    }
+}
+
+impl Daemon {
+    /// Detect the displays and start taking requests for them.
+    pub fn new(
+        runner: Arc<dyn CommandRunner>,
+        ddc: Ddc,
+        gamma: Option<Box<dyn GammaBackend>>,
+        ranges: &RangeConfig,
+    ) -> Result<Self, Error> {
+        let (desk, completions) = Desk::detect(runner.as_ref(), &ddc, gamma.as_deref())?;
+        let displays = desk.displays.clone();
+
+        let state = Arc::new(State {
+            runner,
+            ddc,
+            desk: RwLock::new(desk),
+            detecting: Mutex::default(),
+            values: Mutex::default(),
+            gamma,
+            dimming: Mutex::default(),
+            ranges: ranges.clone(),
+            extended: ExtendedRange::new(ranges),
+            waiters: Mutex::default(),
+            next_request: AtomicU64::new(0),
+            subscribers: Mutex::default(),
+            display_subscribers: Mutex::default(),
+        });
+
+        state.start_dispatch(displays, completions);
+
+        Ok(Self { state })
+    }
+
+    pub fn displays(&self) -> Vec<Display> {
+        self.state.desk.read().unwrap().displays.clone()
+    }
+
+    /// Detect again whenever a monitor is plugged in or unplugged.
+    pub fn watch_hotplug(&self) {
+        let since = self.state.desk.read().unwrap().connected.clone();
+        let watcher = hotplug::watch(
+            self.state.runner.sysfs(),
+            hotplug::POLL_INTERVAL,
+            since,
+            None,
+        );
+        let state = self.state.clone();
+        thread::spawn(move || {
+            for _ in &watcher.changes {
+                state.redetect();
+            }
+        });
+    }
+
+    /// A feature's value on a display picked by id or model name.
+    pub fn get(&self, display: &str, feature: &str) -> Result<Reading, Error> {
+        self.state.get(display, feature)
+    }
+
+    /// Change a feature and wait for the monitor to take it.
+    pub fn set(&self, display: &str, feature: &str, level: Level) -> Result<Reading, Error> {
+        self.state.set(display, feature, level)
+    }
+
+    /// Every change from now on, whoever made it.
+    pub fn subscribe(&self) -> Receiver<Reading> {
+        let (sender, changes) = mpsc::channel();
+        self.state.subscribers.lock().unwrap().push(sender);
+        changes
+    }
+
+    /// The displays served, each time monitors come or go.
+    pub fn watch_displays(&self) -> Receiver<Vec<Display>> {
+        let (sender, changes) = mpsc::channel();
+        self.state.display_subscribers.lock().unwrap().push(sender);
+        changes
+    }
+
+    /// Accept clients forever, each on its own thread.
+    pub fn serve(&self, listener: UnixListener) {
+        for stream in listener.incoming().flatten() {
+            let state = self.state.clone();
+            thread::spawn(move || state.serve_connection(stream));
+        }
+    }
+}
+
+impl State {
+    /// Answer one request. Subscriptions need a connection, so they're
+    /// handled by [`State::serve_connection`] instead.
+    fn handle(self: &Arc<Self>, request: Request) -> Reply {
+        let result = match request {
+            // Frontends list again after noticing a monitor come or go,
+            // possibly before the watcher here has
+            Request::List => {
+                self.redetect();
+                let desk = self.desk.read().unwrap();
+                Ok(Reply::Displays {
+                    displays: desk.displays.iter().map(DisplayInfo::from).collect(),
+                })
+            }
+            Request::Get { display, feature } => self.get(&display, &feature).map(Reply::Value),
+            Request::Set {
+                display,
+                feature,
+                level,
+            } => self.set(&display, &feature, level).map(Reply::Value),
+            Request::Subscribe => Err(Error::Failed("subscribe needs a socket connection".into())),
+        };
+
+        result.unwrap_or_else(Reply::error)
+    }
+
+    /// Detect again if the connectors changed since the last detection, so
+    /// monitors plugged in or out are served without a restart. Requests
+    /// are served from the old displays until the new ones are in.
+    fn redetect(self: &Arc<Self>) {
+        let changed =
+            || self.desk.read().unwrap().connected != hotplug::connected(&self.runner.sysfs());
+        if !changed() {
+            return;
+        }
+        let _detecting = self.detecting.lock().unwrap();
+
+        // Checked again after each round: someone else may have detected
+        // while we waited our turn, or a monitor may have come or gone
+        // while detection was running
+        while changed() {
+            match Desk::detect(self.runner.as_ref(), &self.ddc, self.gamma.as_deref()) {
+                Ok((found, completions)) => self.replace_desk(found, completions),
+                Err(e) => {
+                    eprintln!("Could not detect displays: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn replace_desk(self: &Arc<Self>, found: Desk, completions: Receiver<Completion>) {
+        let mut desk = self.desk.write().unwrap();
+        // A monitor that was away may have been changed elsewhere, and its
+        // output's gamma was reset when it was plugged back in
+        let before: HashSet<&str> = desk.displays.iter().map(Display::key).collect();
+        let stayed: HashSet<String> = found
+            .displays
+            .iter()
+            .map(|d| d.key().to_string())
+            .filter(|key| before.contains(key.as_str()))
+            .collect();
+        self.values
+            .lock()
+            .unwrap()
+            .retain(|(key, _), _| stayed.contains(key));
+        self.dimming
+            .lock()
+            .unwrap()
+            .retain(|key, _| stayed.contains(key));
+
+        eprintln!("Displays changed, serving {}", found.displays.len());
+        let displays = found.displays.clone();
+        self.start_dispatch(displays.clone(), completions);
+        // Writes already queued for the old displays still finish
+        *desk = found;
+        drop(desk);
+
+        self.display_subscribers
+            .lock()
+            .unwrap()
+            .retain(|s| s.send(displays.clone()).is_ok());
+    }
+
+    /// Take in the completions of one detection's workers on a thread of
+    /// their own, matched against the displays those workers were for.
+    fn start_dispatch(self: &Arc<Self>, displays: Vec<Display>, completions: Receiver<Completion>) {
+        let state = self.clone();
+        thread::spawn(move || state.dispatch(&displays, completions));
+    }
+
+    fn find(&self, wanted: &str) -> Result<Display, Error> {
+        self.desk
+            .read()
+            .unwrap()
+            .displays
+            .iter()
+            .find(|d| d.matches(wanted))
+            .cloned()
+            .ok_or_else(|| Error::Failed(format!("No display matching '{}'", wanted)))
+    }
+
+    /// The cached value while it's fresh, otherwise the monitor's answer.
+    fn value(&self, display: &Display, code: u8) -> Result<VcpValue, Error> {
+        let key = (display.key.clone(), code);
+        if let Some(&(v, seen)) = self.values.lock().unwrap().get(&key)
+            && seen.elapsed() < VALUE_MAX_AGE
+        {
+            return Ok(v);
+        }
+
+        let v = self.ddc.read_vcp(display, code)?;
+        self.values.lock().unwrap().insert(key, (v, Instant::now()));
+        Ok(v)
+    }
+
+    fn get(&self, display: &str, feature: &str) -> Result<Reading, Error> {
+        let display = self.find(display)?;
+        if feature == DIMMING {
+            let level = self
+                .dimming
+                .lock()
+                .unwrap()
+                .get(&display.key)
+                .copied()
+                .unwrap_or(100);
+            return Ok(dimming_reading(&display, level));
+        }
+        if feature == EXTENDED {
+            let percent = self.extended(&display).get()?;
+            return Ok(Reading::percent(&display, EXTENDED, percent));
+        }
+        let code = ipc::feature_code(feature)?;
+
+        self.value(&display, code)
+            .map(|v| Reading::vcp(&display, feature, v))
+    }
+
+    /// Queue a write on the display's worker and wait for it to land. If a
+    /// newer write to the same feature overtakes it, the reply reports that.
+    fn set(&self, display: &str, feature: &str, level: Level) -> Result<Reading, Error> {
+        let display = self.find(display)?;
+        if feature == DIMMING {
+            return self.set_dimming(&display, level);
+        }
+        if feature == EXTENDED {
+            let mut control = self.extended(&display);
+            let percent = level
+                .apply(VcpValue::new(control.get()?.into(), 100))
+                .current as u8;
+            control.set(percent)?;
+            return Ok(Reading::percent(&display, EXTENDED, percent));
+        }
+        let code = ipc::feature_code(feature)?;
+        let value = match (level, self.value(&display, code)) {
+            // Raw values come from frontends that know the feature. Write-only
+            // ones like a factory reset can't be read first, and a maximum of
+            // 0 leaves the range unknown, as for non-continuous features
+            (Level::Raw(raw), current) => VcpValue::new(raw, current.map_or(0, |v| v.max)),
+            (_, current) => self.ranges.clamp(code, level.apply(current?)),
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let request = self.next_request.fetch_add(1, Ordering::Relaxed);
+        self.waiters.lock().unwrap().insert(request, sender);
+
+        let action = Action::Slider {
+            label: feature.to_string(),
+            value,
+        };
+        {
+            let desk = self.desk.read().unwrap();
+            // Detection may have run since, handing the id to another display
+            match desk.workers.get(&display.id) {
+                Some(worker)
+                    if desk
+                        .displays
+                        .iter()
+                        .any(|d| d.id == display.id && d.key == display.key) =>
+                {
+                    worker.submit_request(code, value.current, action, request);
+                }
+                _ => {
+                    self.waiters.lock().unwrap().remove(&request);
+                    return Err(Error::Failed(format!("{} was disconnected", display.name)));
+                }
+            }
+        }
+
+        let completion = receiver
+            .recv()
+            .map_err(|_| Error::Failed("write worker stopped".into()))?;
+        completion.outcome.check(feature)?;
+        Ok(Reading::vcp(
+            &display,
+            feature,
+            VcpValue::new(completion.value, value.max),
+        ))
+    }
+
+    /// Brightness into dimming, each part going through the daemon so the
+    /// writes are queued, cached and announced like any other.
+    fn extended<'a>(&'a self, display: &'a Display) -> Extended<'a> {
+        let part = |feature| {
+            Box::new(Feature {
+                state: self,
+                display,
+                feature,
+            })
+        };
+        Extended {
+            range: self.extended,
+            hardware: part("brightness"),
+            software: part(DIMMING),
+        }
+    }
+
+    fn set_dimming(&self, display: &Display, level: Level) -> Result<Reading, Error> {
+        let output = self
+            .desk
+            .read()
+            .unwrap()
+            .gamma_outputs
+            .get(&display.id)
+            .cloned();
+        let (Some(backend), Some(output)) = (&self.gamma, output) else {
+            return Err(Error::Failed(format!(
+                "No software dimming for {}",
+                display.name
+            )));
+        };
+
+        let current = self.get(&display.key, DIMMING)?.percent;
+        let percent = level.apply(VcpValue::new(current.into(), 100)).current as u8;
+        let percent = self.ranges.dimming.clamp(percent);
+        backend.apply_dimming(&output, percent)?;
+        self.dimming
+            .lock()
+            .unwrap()
+            .insert(display.key.clone(), percent);
+
+        let reading = dimming_reading(display, percent);
+        self.broadcast(&reading);
+        Ok(reading)
+    }
+
+    fn broadcast(&self, reading: &Reading) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|s| s.send(reading.clone()).is_ok());
+    }
+
+    /// Record finished writes, tell subscribers and wake whoever asked.
+    fn dispatch(&self, displays: &[Display], completions: Receiver<Completion>) {
+        for completion in completions {
+            if let Some(display) = displays.iter().find(|d| d.id == completion.display_id) {
+                self.record(display, &completion);
+            }
+
+            let mut waiters = self.waiters.lock().unwrap();
+            for request in &completion.requests {
+                if let Some(waiter) = waiters.remove(request) {
+                    let _ = waiter.send(completion.clone());
+                }
+            }
+        }
+    }
+
+    /// Cache what a write left on the monitor and tell subscribers.
+    fn record(&self, display: &Display, completion: &Completion) {
+        let key = (display.key.clone(), completion.code);
+        let max = match &completion.action {
+            Action::Slider { value, .. } => value.max,
+            _ => 0,
+        };
+        let kept = match completion.outcome {
+            WriteOutcome::Mismatch { actual, .. } => Some(actual),
+            ref outcome if outcome.is_applied() => Some(completion.value),
+            _ => None,
+        };
+
+        // A failed write may have landed or not, so ask the monitor next time
+        let Some(kept) = kept else {
+            self.values.lock().unwrap().remove(&key);
+            return;
+        };
+        let value = VcpValue::new(kept, max);
+        if max > 0 {
+            self.values
+                .lock()
+                .unwrap()
+                .insert(key, (value, Instant::now()));
+        }
+        self.broadcast(&Reading::vcp(
+            display,
+            &ipc::feature(completion.code),
+            value,
+        ));
+    }
+
+    fn serve_connection(self: &Arc<Self>, stream: UnixStream) {
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let reply = match serde_json::from_str(&line) {
+                Ok(Request::Subscribe) => return self.stream_changes(writer),
+                Ok(request) => self.handle(request),
+                Err(e) => Reply::error(Error::Parse(format!("request: {}", e))),
+            };
+            if send(&mut writer, &reply).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn stream_changes(&self, mut writer: UnixStream) {
+        let (sender, changes) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+
+        if send(&mut writer, &Reply::Subscribed).is_err() {
+            return;
+        }
+        for reading in changes {
+            if send(&mut writer, &Reply::Changed(reading)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// One of the daemon's own features as a brightness control.
+struct Feature<'a> {
+    state: &'a State,
+    display: &'a Display,
+    feature: &'static str,
+}
+
+impl BrightnessControl for Feature<'_> {
+    fn get(&mut self) -> Result<u8, Error> {
+        self.state
+            .get(&self.display.key, self.feature)
+            .map(|r| r.percent)
+    }
+
+    fn set(&mut self, percent: u8) -> Result<(), Error> {
+        self.state
+            .set(&self.display.key, self.feature, Level::Absolute(percent))
+            .map(|_| ())
+    }
+}
+
+fn dimming_reading(display: &Display, percent: u8) -> Reading {
+    Reading::percent(display, DIMMING, percent)
+}
+
+fn send(writer: &mut UnixStream, reply: &Reply) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(reply)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::app::tests::{DDCUTIL_DETECT, scripted_desk};
+    use crate::commands::ScriptedRunner;
+    use crate::ddc::WritePolicy;
+    use crate::ipc::Client;
+
+    pub fn scripted_daemon(runner: Arc<ScriptedRunner>) -> Daemon {
+        let ddc = Ddc::new(runner.clone(), false).with_policy(WritePolicy {
+            verify: false,
+            ..WritePolicy::immediate()
+        });
+        let gamma = gamma::create_backend(gamma::SessionType::X11, runner.clone());
+
+        Daemon::new(runner, ddc, gamma, &RangeConfig::default()).unwrap()
+    }
+
+    /// A daemon for the scripted desk, listening in a temporary directory.
+    pub fn spawn_daemon(runner: Arc<ScriptedRunner>, dir: &Path) -> std::path::PathBuf {
+        let daemon = scripted_daemon(runner);
+        let path = dir.join("daemon.sock");
+        let listener = bind(&path).unwrap();
+
+        thread::spawn(move || daemon.serve(listener));
+        path
+    }
+
+    /// Detect again as if the hotplug watcher had seen a change.
+    pub fn redetect(daemon: &Daemon) {
+        daemon.state.redetect();
+    }
+
+    #[test]
+    fn test_list_get_set() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 30 --bus 8", "")
+                .on("ddcutil setvcp 10 1 --bus 8", ""),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let mut client = Client::connect(&spawn_daemon(runner.clone(), dir.path())).unwrap();
+
+        let displays = client.list().unwrap();
+        assert_eq!(displays.len(), 2);
+        assert_eq!(displays[1].connector, "card1-HDMI-A-1");
+
+        assert_eq!(client.get("2", "brightness").unwrap().percent, 20);
+        let reading = client
+            .set("Samsung 27\"", "brightness", Level::Relative(10))
+            .unwrap();
+        assert_eq!(reading.percent, 30);
+
+        // Answered from the cache, without asking the monitor again
+        assert_eq!(client.get("2", "brightness").unwrap().percent, 30);
+        let reads = runner
+            .calls()
+            .iter()
+            .filter(|c| c.starts_with("ddcutil getvcp 10 --bus 8"))
+            .count();
+        assert_eq!(reads, 1);
+
+        // Held at the configured floor, as the GUI's slider is
+        let reading = client.set("2", "brightness", Level::Absolute(0)).unwrap();
+        assert_eq!(reading.percent, 1);
+
+        let err = client.get("2", "sharpness").unwrap_err();
+        assert!(matches!(err, Error::Failed(_)));
+        assert!(client.get("9", "brightness").is_err());
+    }
+
+    #[test]
+    fn test_stale_values_are_read_again() {
+        let runner = Arc::new(scripted_desk());
+        let daemon = scripted_daemon(runner.clone());
+        let reads = || {
+            runner
+                .calls()
+                .iter()
+                .filter(|c| c.starts_with("ddcutil getvcp 10 --bus 7"))
+                .count()
+        };
+
+        assert_eq!(daemon.get("1", "brightness").unwrap().percent, 70);
+        assert_eq!(daemon.get("1", "brightness").unwrap().percent, 70);
+        assert_eq!(reads(), 1);
+
+        // Changed on the monitor's own buttons in the meantime
+        runner.set(
+            "ddcutil getvcp 10 --bus 7 --terse",
+            Ok("VCP 10 C 55 100".into()),
+        );
+        for (_, seen) in daemon.state.values.lock().unwrap().values_mut() {
+            *seen -= VALUE_MAX_AGE;
+        }
+        assert_eq!(daemon.get("1", "brightness").unwrap().percent, 55);
+        assert_eq!(reads(), 2);
+    }
+
+    #[test]
+    fn test_subscribers_see_every_write() {
+        let runner = Arc::new(scripted_desk().on("ddcutil setvcp 12 55 --bus 7", ""));
+        let dir = tempfile::tempdir().unwrap();
+        let path = spawn_daemon(runner, dir.path());
+
+        let mut events = Client::connect(&path).unwrap().subscribe().unwrap();
+        let mut client = Client::connect(&path).unwrap();
+        client.set("1", "contrast", Level::Absolute(55)).unwrap();
+
+        let change = events.next_change().unwrap();
+        // Changes name displays by their stable key
+        assert_eq!(
+            (change.display.as_str(), change.feature.as_str()),
+            ("DEL-40E6-ABC123", "contrast")
+        );
+        assert_eq!(change.percent, 55);
+    }
+
+    #[test]
+    fn test_software_dimming() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("xrandr --output HDMI-A-0 --brightness 0.6", "")
+                .on("xrandr --output HDMI-A-0 --brightness 0.2", ""),
+        );
+        let daemon = scripted_daemon(runner);
+        let changes = daemon.subscribe();
+
+        assert_eq!(daemon.get("2", DIMMING).unwrap().percent, 100);
+        assert_eq!(
+            daemon
+                .set("2", DIMMING, Level::Relative(-40))
+                .unwrap()
+                .percent,
+            60
+        );
+        assert_eq!(daemon.get("2", DIMMING).unwrap().percent, 60);
+        assert_eq!(
+            changes.try_recv().map(|r| r.feature),
+            Ok(DIMMING.to_string())
+        );
+
+        // Held at the configured floor, as the GUI's slider is
+        assert_eq!(
+            daemon
+                .set("2", DIMMING, Level::Absolute(5))
+                .unwrap()
+                .percent,
+            20
+        );
+    }
+
+    #[test]
+    fn test_extended_range() {
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 1 --bus 8", "")
+                .on("xrandr --output HDMI-A-0 --brightness 0.73", "")
+                .on("ddcutil setvcp 10 100 --bus 8", "")
+                .on("xrandr --output HDMI-A-0 --brightness 1", ""),
+        );
+        let daemon = scripted_daemon(runner.clone());
+
+        assert_eq!(daemon.get("2", EXTENDED).unwrap().percent, 56);
+
+        // Brightness bottoms out, then dimming takes over
+        assert_eq!(
+            daemon
+                .set("2", EXTENDED, Level::Absolute(30))
+                .unwrap()
+                .percent,
+            30
+        );
+        assert_eq!(daemon.get("2", "brightness").unwrap().percent, 1);
+        assert_eq!(daemon.get("2", DIMMING).unwrap().percent, 73);
+        assert_eq!(daemon.get("2", EXTENDED).unwrap().percent, 30);
+
+        assert_eq!(
+            daemon
+                .set("2", EXTENDED, Level::Relative(100))
+                .unwrap()
+                .percent,
+            100
+        );
+        assert_eq!(daemon.get("2", DIMMING).unwrap().percent, 100);
+        assert!(
+            runner
+                .calls()
+                .contains(&"ddcutil setvcp 10 100 --bus 8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_monitors_changing_are_detected() {
+        let root = tempfile::tempdir().unwrap();
+        let runner = Arc::new(scripted_desk().with_sysfs(root.path()));
+        let daemon = scripted_daemon(runner.clone());
+        assert_eq!(daemon.get("1", "brightness").unwrap().percent, 70);
+
+        // Unplugging the Dell moves the Samsung up to ddcutil's display 1
+        let samsung_only = DDCUTIL_DETECT
+            .split("\n\n")
+            .nth(1)
+            .unwrap()
+            .replace("Display 2", "Display 1");
+        runner.set("ddcutil detect", Ok(samsung_only));
+        // Listing before anything changed doesn't detect again
+        let changes = daemon.watch_displays();
+        daemon.state.handle(Request::List);
+        assert_eq!(daemon.displays().len(), 2);
+        assert!(changes.try_recv().is_err());
+
+        hotplug::tests::plug(root.path(), "card1-DP-2", &[]);
+        let Reply::Displays { displays } = daemon.state.handle(Request::List) else {
+            panic!("expected displays");
+        };
+        assert_eq!(displays.len(), 1);
+        assert_eq!(changes.try_recv().unwrap()[0].key, "SAM-04D2-XYZ789");
+        assert_eq!(
+            (displays[0].display.as_str(), displays[0].number.as_str()),
+            ("SAM-04D2-XYZ789", "1")
+        );
+        // Not the Dell's cached brightness
+        assert_eq!(daemon.get("1", "brightness").unwrap().percent, 20);
+    }
+
+    #[test]
+    fn test_bad_request_and_stale_socket() {
+        let runner = Arc::new(ScriptedRunner::new().on("ddcutil detect", "No displays found.\n"));
+        let daemon = Daemon::new(
+            runner.clone(),
+            Ddc::new(runner, false),
+            None,
+            &RangeConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            daemon.state.handle(Request::List),
+            Reply::Displays {
+                displays: Vec::new()
+            }
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.sock");
+        drop(bind(&path).unwrap());
+        // Nothing is listening on the leftover socket file any more
+        let listener = bind(&path).unwrap();
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        thread::spawn(move || daemon.state.serve_connection(accepted));
+        stream.write_all(b"{\"cmd\":\"dance\"}\n").unwrap();
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        assert!(line.starts_with(r#"{"reply":"error","error":{"kind":"parse""#));
+
+        // A live daemon is left alone
+        assert!(bind(&path).is_err());
+    }
+}
@@ -0,0 +1,459 @@
+//! The daemon's displays on the D-Bus session bus, for desktop shells,
+//! extensions and anything else that would rather not speak the socket
+//! protocol.
+//!
+//! Each display is an object at `/io/github/rykilleen/DimAndDimmer/display/<key>`
+//! with read-only percentage properties and `Set`/`Step` methods. Changes
+//! made anywhere, including through the socket, are announced with
+//! `PropertiesChanged`.
+
+use std::collections::HashMap;
+use std::thread;
+
+use zbus::blocking::{Connection, connection};
+use zbus::fdo;
+use zbus::interface;
+use zbus::zvariant::Value;
+
+use crate::daemon::{DIMMING, Daemon};
+use crate::display::Display;
+use crate::error::Error;
+use crate::ipc::{Level, Reading};
+
+pub const BUS_NAME: &str = "io.github.rykilleen.DimAndDimmer";
+pub const ROOT_PATH: &str = "/io/github/rykilleen/DimAndDimmer";
+pub const DISPLAY_INTERFACE: &str = "io.github.rykilleen.DimAndDimmer.Display";
+
+pub fn display_path(key: &str) -> String {
+    // Object path elements only allow [A-Za-z0-9_]
+    let element: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}/display/{}", ROOT_PATH, element)
+}
+
+/// Publish the daemon on the user's session bus.
+pub fn serve_session(daemon: Daemon) -> Result<Connection, Error> {
+    serve(daemon, connection::Builder::session().map_err(bus_error)?)
+}
+
+/// Claim the bus name, publish every display and keep announcing changes
+/// for as long as the returned connection lives. Monitors plugged in or
+/// out later are published or withdrawn, with the object manager's
+/// `InterfacesAdded`/`InterfacesRemoved`.
+pub fn serve(daemon: Daemon, builder: connection::Builder) -> Result<Connection, Error> {
+    // Before reading the displays, so no change slips in between
+    let display_changes = daemon.watch_displays();
+    let published = daemon.displays();
+
+    let mut builder = builder
+        .name(BUS_NAME)
+        .and_then(|b| b.serve_at(ROOT_PATH, fdo::ObjectManager))
+        .map_err(bus_error)?;
+    for display in &published {
+        builder = builder
+            .serve_at(
+                display_path(&display.key),
+                DisplayObject::new(&daemon, display),
+            )
+            .map_err(bus_error)?;
+    }
+
+    let connection = builder.build().map_err(bus_error)?;
+    let changes = daemon.subscribe();
+    let announcer = connection.clone();
+    thread::spawn(move || {
+        for reading in changes {
+            if announce(&announcer, &reading).is_err() {
+                return;
+            }
+        }
+    });
+
+    let publisher = connection.clone();
+    thread::spawn(move || {
+        let mut published = published;
+        for displays in display_changes {
+            if let Err(e) = republish(&publisher, &daemon, &published, &displays) {
+                eprintln!("Could not update the D-Bus displays: {}", e);
+            }
+            published = displays;
+        }
+    });
+
+    Ok(connection)
+}
+
+/// Withdraw the objects of displays that went away or were renumbered and
+/// publish the ones that are new or changed.
+fn republish(
+    connection: &Connection,
+    daemon: &Daemon,
+    before: &[Display],
+    after: &[Display],
+) -> zbus::Result<()> {
+    let server = connection.object_server();
+    for gone in before
+        .iter()
+        .filter(|d| !after.iter().any(|a| same_object(a, d)))
+    {
+        server.remove::<DisplayObject, _>(display_path(&gone.key))?;
+    }
+    for new in after
+        .iter()
+        .filter(|d| !before.iter().any(|b| same_object(b, d)))
+    {
+        server.at(display_path(&new.key), DisplayObject::new(daemon, new))?;
+    }
+    Ok(())
+}
+
+/// Whether a display's object still shows it as it is.
+fn same_object(a: &Display, b: &Display) -> bool {
+    a.key == b.key && a.id == b.id && a.name == b.name && a.drm_connector == b.drm_connector
+}
+
+fn announce(connection: &Connection, reading: &Reading) -> zbus::Result<()> {
+    let Some(property) = property_name(&reading.feature) else {
+        return Ok(());
+    };
+    let changed = HashMap::from([(property, Value::from(u32::from(reading.percent)))]);
+
+    connection.emit_signal(
+        None::<()>,
+        display_path(&reading.display),
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        &(DISPLAY_INTERFACE, changed, Vec::<&str>::new()),
+    )
+}
+
+fn property_name(feature: &str) -> Option<&'static str> {
+    match feature {
+        "brightness" => Some("Brightness"),
+        "contrast" => Some("Contrast"),
+        "volume" => Some("Volume"),
+        DIMMING => Some("Dimming"),
+        _ => None,
+    }
+}
+
+fn bus_error(e: zbus::Error) -> Error {
+    Error::Failed(format!("D-Bus: {}", e))
+}
+
+fn method_error(e: Error) -> fdo::Error {
+    match e {
+        Error::UnsupportedFeature(_) => fdo::Error::NotSupported(e.to_string()),
+        Error::PermissionDenied(_) => fdo::Error::AccessDenied(e.to_string()),
+        Error::Timeout(_) => fdo::Error::TimedOut(e.to_string()),
+        _ => fdo::Error::Failed(e.to_string()),
+    }
+}
+
+struct DisplayObject {
+    daemon: Daemon,
+    key: String,
+    number: String,
+    name: String,
+    connector: String,
+}
+
+impl DisplayObject {
+    fn new(daemon: &Daemon, display: &Display) -> Self {
+        Self {
+            daemon: daemon.clone(),
+            key: display.key.clone(),
+            number: display.id.clone(),
+            name: display.name.clone(),
+            connector: display.drm_connector.clone(),
+        }
+    }
+
+    fn percent(&self, feature: &str) -> fdo::Result<u32> {
+        self.daemon
+            .get(&self.key, feature)
+            .map(|r| r.percent.into())
+            .map_err(method_error)
+    }
+
+    fn write(&self, feature: &str, level: Level) -> fdo::Result<u32> {
+        self.daemon
+            .set(&self.key, feature, level)
+            .map(|r| r.percent.into())
+            .map_err(method_error)
+    }
+}
+
+// Properties are read-only so every change goes through the daemon and is
+// announced exactly once, by `serve`'s change thread.
+#[interface(name = "io.github.rykilleen.DimAndDimmer.Display")]
+impl DisplayObject {
+    /// Stable key built from the EDID, e.g. `DEL-40E6-ABC123`.
+    #[zbus(property)]
+    fn id(&self) -> String {
+        self.key.clone()
+    }
+
+    /// ddcutil display number, which can change when monitors are replugged.
+    #[zbus(property)]
+    fn number(&self) -> String {
+        self.number.clone()
+    }
+
+    #[zbus(property)]
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// DRM connector, e.g. `card1-DP-1`.
+    #[zbus(property)]
+    fn connector(&self) -> String {
+        self.connector.clone()
+    }
+
+    #[zbus(property)]
+    fn brightness(&self) -> fdo::Result<u32> {
+        self.percent("brightness")
+    }
+
+    #[zbus(property)]
+    fn contrast(&self) -> fdo::Result<u32> {
+        self.percent("contrast")
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> fdo::Result<u32> {
+        self.percent("volume")
+    }
+
+    /// Software dimming, 100 when undimmed.
+    #[zbus(property)]
+    fn dimming(&self) -> fdo::Result<u32> {
+        self.percent(DIMMING)
+    }
+
+    /// Set a feature to a percentage and return the value the display took.
+    fn set(&self, feature: &str, percent: u32) -> fdo::Result<u32> {
+        let percent = u8::try_from(percent)
+            .ok()
+            .filter(|p| *p <= 100)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("{} is not a percentage", percent)))?;
+        self.write(feature, Level::Absolute(percent))
+    }
+
+    /// Move a feature by `delta` percentage points, clamped to 0-100.
+    fn step(&self, feature: &str, delta: i32) -> fdo::Result<u32> {
+        let delta = delta.clamp(-100, 100) as i16;
+        self.write(feature, Level::Relative(delta))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Arc;
+
+    use zbus::MatchRule;
+    use zbus::blocking::{MessageIterator, Proxy, proxy};
+    use zbus::proxy::CacheProperties;
+
+    use crate::app::tests::{DDCUTIL_DETECT, scripted_desk};
+    use crate::daemon::tests::{redetect, scripted_daemon};
+    use crate::hotplug;
+
+    /// A private session bus, so tests never touch the user's own.
+    pub struct TestBus {
+        process: Child,
+        address: String,
+    }
+
+    impl TestBus {
+        pub fn start() -> Option<Self> {
+            let mut process = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(process.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+
+            Some(Self {
+                process,
+                address: address.trim().to_string(),
+            })
+        }
+
+        pub fn connect(&self) -> connection::Builder<'static> {
+            connection::Builder::address(self.address.as_str()).unwrap()
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            let _ = self.process.kill();
+            let _ = self.process.wait();
+        }
+    }
+
+    #[test]
+    fn test_object_paths() {
+        assert_eq!(
+            display_path("2"),
+            "/io/github/rykilleen/DimAndDimmer/display/2"
+        );
+        assert_eq!(
+            display_path("GSM-7780@card0-DP-1"),
+            "/io/github/rykilleen/DimAndDimmer/display/GSM_7780_card0_DP_1"
+        );
+    }
+
+    #[test]
+    fn test_service_on_private_bus() {
+        let Some(bus) = TestBus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let runner = Arc::new(
+            scripted_desk()
+                .on("ddcutil setvcp 10 30 --bus 8", "")
+                .on("xrandr --output HDMI-A-0 --brightness 0.8", ""),
+        );
+        let _service = serve(scripted_daemon(runner.clone()), bus.connect()).unwrap();
+
+        let client = bus.connect().build().unwrap();
+        let rule = MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus.Properties")
+            .unwrap()
+            .member("PropertiesChanged")
+            .unwrap()
+            .build();
+        let mut signals = MessageIterator::for_match_rule(rule, &client, None).unwrap();
+        let display: Proxy = proxy::Builder::new(&client)
+            .destination(BUS_NAME)
+            .unwrap()
+            .path(display_path("SAM-04D2-XYZ789"))
+            .unwrap()
+            .interface(DISPLAY_INTERFACE)
+            .unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            display.get_property::<String>("Name").unwrap(),
+            "Samsung 27\""
+        );
+        assert_eq!(
+            display.get_property::<String>("Id").unwrap(),
+            "SAM-04D2-XYZ789"
+        );
+        assert_eq!(display.get_property::<String>("Number").unwrap(), "2");
+        assert_eq!(display.get_property::<u32>("Brightness").unwrap(), 20);
+
+        let kept: u32 = display.call("Step", &("brightness", 10i32)).unwrap();
+        assert_eq!(kept, 30);
+        assert_eq!(display.get_property::<u32>("Brightness").unwrap(), 30);
+        assert!(
+            runner
+                .calls()
+                .contains(&"ddcutil setvcp 10 30 --bus 8".to_string())
+        );
+
+        let signal = signals.next().unwrap().unwrap();
+        let (interface, changed, _): (
+            String,
+            HashMap<String, zbus::zvariant::OwnedValue>,
+            Vec<String>,
+        ) = signal.body().deserialize().unwrap();
+        assert_eq!(interface, DISPLAY_INTERFACE);
+        assert_eq!(u32::try_from(&changed["Brightness"]).unwrap(), 30);
+
+        let dimmed: u32 = display.call("Set", &("dimming", 80u32)).unwrap();
+        assert_eq!(dimmed, 80);
+        assert!(
+            display
+                .call::<_, _, u32>("Set", &("brightness", 140u32))
+                .is_err()
+        );
+        assert!(
+            display
+                .call::<_, _, u32>("Set", &("sharpness", 10u32))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_objects_follow_monitors() {
+        let Some(bus) = TestBus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let root = tempfile::tempdir().unwrap();
+        let runner = Arc::new(scripted_desk().with_sysfs(root.path()));
+        let daemon = scripted_daemon(runner.clone());
+        let _service = serve(daemon.clone(), bus.connect()).unwrap();
+
+        let client = bus.connect().build().unwrap();
+        let rule = MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus.ObjectManager")
+            .unwrap()
+            .build();
+        let mut signals = MessageIterator::for_match_rule(rule, &client, None).unwrap();
+        let objects = |path: String| {
+            proxy::Builder::<Proxy>::new(&client)
+                .destination(BUS_NAME)
+                .unwrap()
+                .path(path)
+                .unwrap()
+                .interface(DISPLAY_INTERFACE)
+                .unwrap()
+                .cache_properties(CacheProperties::No)
+                .build()
+                .unwrap()
+        };
+        let dell = objects(display_path("DEL-40E6-ABC123"));
+        assert_eq!(dell.get_property::<u32>("Brightness").unwrap(), 70);
+
+        // Unplugging the Dell moves the Samsung up to ddcutil's display 1
+        let samsung_only = DDCUTIL_DETECT
+            .split("\n\n")
+            .nth(1)
+            .unwrap()
+            .replace("Display 2", "Display 1");
+        runner.set("ddcutil detect", Ok(samsung_only));
+        hotplug::tests::plug(root.path(), "card1-DP-2", &[]);
+        redetect(&daemon);
+
+        // The Dell goes, the renumbered Samsung is published again
+        let mut removed = Vec::new();
+        loop {
+            let signal = signals.next().unwrap().unwrap();
+            let (path, _): (zbus::zvariant::OwnedObjectPath, Vec<String>) =
+                match signal.header().member().unwrap().as_str() {
+                    "InterfacesRemoved" => signal.body().deserialize().unwrap(),
+                    _ => break,
+                };
+            removed.push(path.to_string());
+        }
+        assert_eq!(
+            removed,
+            [
+                display_path("DEL-40E6-ABC123"),
+                display_path("SAM-04D2-XYZ789")
+            ]
+        );
+        assert!(dell.get_property::<u32>("Brightness").is_err());
+
+        let samsung = objects(display_path("SAM-04D2-XYZ789"));
+        assert_eq!(samsung.get_property::<String>("Number").unwrap(), "1");
+        assert_eq!(samsung.get_property::<u32>("Brightness").unwrap(), 20);
+    }
+}
@@ -0,0 +1,28 @@
+pub const VCP_AUDIO_VOLUME: u8 = 0x62;
+pub const VCP_AUDIO_MUTE: u8 = 0x8D;
+
+/// VCP 0x8D values. MCCS shares this code with screen blanking on some
+/// monitors, but the speaker meaning is by far the common one.
+const MUTED: u8 = 0x01;
+const UNMUTED: u8 = 0x02;
+
+pub fn mute_value(muted: bool) -> u8 {
+    if muted { MUTED } else { UNMUTED }
+}
+
+pub fn is_muted(value: u8) -> bool {
+    value == MUTED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mute_values() {
+        assert_eq!(mute_value(true), 0x01);
+        assert_eq!(mute_value(false), 0x02);
+        assert!(is_muted(mute_value(true)));
+        assert!(!is_muted(mute_value(false)));
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+
+use crate::ddc::VcpValue;
+use crate::ddc::mccs::{self, Access, FeatureKind};
+use crate::error::Error;
+
+/// One VCP feature advertised in a monitor's capabilities string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    pub code: u8,
+    pub kind: FeatureKind,
+    pub access: Access,
+    /// Allowed values for non-continuous features, as advertised.
+    pub values: Vec<u8>,
+}
+
+impl Feature {
+    fn new(code: u8, values: Vec<u8>) -> Self {
+        let (kind, access) = match mccs::lookup(code) {
+            Some(info) => (info.kind, info.access),
+            // Unknown features are only writable when the monitor tells us
+            // which values are legal.
+            None if !values.is_empty() => (FeatureKind::NonContinuous, Access::ReadWrite),
+            None => (FeatureKind::Continuous, Access::ReadOnly),
+        };
+
+        Self {
+            code,
+            kind,
+            access,
+            values,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        mccs::feature_name(self.code)
+    }
+
+    pub fn value_name(&self, value: u8) -> String {
+        mccs::lookup(self.code)
+            .and_then(|info| info.value_name(value))
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("0x{:02X}", value))
+    }
+
+    /// Values offered for a non-continuous feature: the advertised list, or
+    /// every value the MCCS table names when the monitor lists none.
+    pub fn choices(&self) -> Vec<u8> {
+        if !self.values.is_empty() {
+            return self.values.clone();
+        }
+
+        mccs::lookup(self.code)
+            .map(|info| info.values.iter().map(|(v, _)| *v).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn format_value(&self, value: VcpValue) -> String {
+        match self.kind {
+            FeatureKind::NonContinuous => self.value_name(value.low_byte()),
+            _ => format!("{} / {}", value.current, value.max),
+        }
+    }
+
+    pub fn readable(&self) -> bool {
+        self.access != Access::WriteOnly && self.kind != FeatureKind::Table
+    }
+
+    pub fn writable(&self) -> bool {
+        self.access != Access::ReadOnly && self.kind != FeatureKind::Table
+    }
+}
+
+/// Parsed MCCS capabilities string, e.g.
+/// `(prot(monitor)type(lcd)model(U2715H)cmds(01 02 03)vcp(10 12 60(0F 11))mccs_ver(2.1))`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capabilities {
+    pub raw: String,
+    pub model: Option<String>,
+    pub display_type: Option<String>,
+    pub mccs_version: Option<String>,
+    pub commands: Vec<u8>,
+    pub features: BTreeMap<u8, Feature>,
+}
+
+impl Capabilities {
+    pub fn parse(raw: &str) -> Result<Self, Error> {
+        let raw = raw.trim().trim_end_matches('\0');
+        let tags = parse_tags(raw)?;
+
+        let mut caps = Capabilities {
+            raw: raw.to_string(),
+            ..Default::default()
+        };
+
+        for (tag, value) in tags {
+            match tag.as_str() {
+                "model" => caps.model = Some(value.trim().to_string()),
+                "type" => caps.display_type = Some(value.trim().to_string()),
+                "mccs_ver" => caps.mccs_version = Some(value.trim().to_string()),
+                "cmds" => caps.commands = parse_hex_list(&value),
+                "vcp" => {
+                    for (code, values) in parse_vcp_list(&value) {
+                        caps.features.insert(code, Feature::new(code, values));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if caps.features.is_empty() {
+            return Err(Error::Parse(
+                "capabilities string: no VCP features listed".into(),
+            ));
+        }
+
+        Ok(caps)
+    }
+
+    pub fn supports(&self, code: u8) -> bool {
+        self.features.contains_key(&code)
+    }
+
+    pub fn feature(&self, code: u8) -> Option<&Feature> {
+        self.features.get(&code)
+    }
+}
+
+/// Split `tag(value)tag(value)...` into pairs, honouring nested parentheses.
+/// The outer parentheses are optional since some monitors omit one or both.
+fn parse_tags(raw: &str) -> Result<Vec<(String, String)>, Error> {
+    let balanced = raw.matches('(').count() == raw.matches(')').count();
+    let inner = match raw.strip_prefix('(') {
+        Some(rest) if balanced => rest.strip_suffix(')').unwrap_or(rest),
+        Some(rest) => rest,
+        None => raw,
+    };
+
+    let mut tags = Vec::new();
+    let mut chars = inner.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut tag = String::new();
+        while let Some(c) = chars.next_if(|&c| c != '(') {
+            tag.push(c);
+        }
+        if chars.next().is_none() {
+            break;
+        }
+
+        let mut depth = 1;
+        let mut value = String::new();
+        for c in chars.by_ref() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+            value.push(c);
+        }
+        if depth != 0 {
+            return Err(Error::Parse(format!(
+                "capabilities string: unbalanced parentheses in tag '{}'",
+                tag.trim()
+            )));
+        }
+
+        tags.push((tag.trim().to_lowercase(), value));
+    }
+
+    Ok(tags)
+}
+
+/// Read hex bytes written as two-digit pairs, with or without separating spaces.
+fn parse_hex_list(s: &str) -> Vec<u8> {
+    let digits: Vec<char> = s.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+    digits
+        .chunks_exact(2)
+        .filter_map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+        .collect()
+}
+
+/// Parse the body of `vcp(...)`: codes optionally followed by a value list.
+fn parse_vcp_list(s: &str) -> Vec<(u8, Vec<u8>)> {
+    let mut features: Vec<(u8, Vec<u8>)> = Vec::new();
+    let mut chars = s.chars();
+    let mut pending = String::new();
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_hexdigit() {
+            pending.push(c);
+            if pending.len() == 2 {
+                if let Ok(code) = u8::from_str_radix(&pending, 16) {
+                    features.push((code, Vec::new()));
+                }
+                pending.clear();
+            }
+        } else if c == '(' {
+            let mut depth = 1;
+            let mut values = String::new();
+            for c in chars.by_ref() {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    break;
+                }
+                values.push(c);
+            }
+            if let Some((_, list)) = features.last_mut() {
+                *list = parse_hex_list(&values);
+            }
+        } else {
+            pending.clear();
+        }
+    }
+
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELL_U2715H: &str = "(prot(monitor)type(lcd)model(U2715H)cmds(01 02 03 07 0C E3 F3)vcp(02 04 05 08 10 12 14(05 08 0B 0C) 16 18 1A 52 60(01 0F 11) AA(01 02) AC AE B2 B6 C6 C8 C9 D6(01 04 05) DC(00 02 03 05) DF E0 E1 E2(00 1D 01 02 04 0E 12 14 23 24 27) F0(00 05) F1 F2 FD)mswhql(1)asset_eep(40)mccs_ver(2.1))";
+
+    #[test]
+    fn test_parse_dell_capabilities() {
+        let caps = Capabilities::parse(DELL_U2715H).unwrap();
+
+        assert_eq!(caps.model.as_deref(), Some("U2715H"));
+        assert_eq!(caps.display_type.as_deref(), Some("lcd"));
+        assert_eq!(caps.mccs_version.as_deref(), Some("2.1"));
+        assert_eq!(
+            caps.commands,
+            vec![0x01, 0x02, 0x03, 0x07, 0x0C, 0xE3, 0xF3]
+        );
+
+        assert!(caps.supports(0x10));
+        assert_eq!(caps.feature(0x10).unwrap().kind, FeatureKind::Continuous);
+
+        let input = caps.feature(0x60).unwrap();
+        assert_eq!(input.kind, FeatureKind::NonContinuous);
+        assert_eq!(input.values, vec![0x01, 0x0F, 0x11]);
+
+        assert_eq!(caps.feature(0xE2).unwrap().values.len(), 11);
+        assert!(!caps.feature(0xE0).unwrap().writable());
+        assert!(!caps.feature(0x04).unwrap().readable());
+    }
+
+    #[test]
+    fn test_parse_without_spaces_or_outer_parens() {
+        let caps = Capabilities::parse("prot(monitor)vcp(101214(0508)60(0F11))").unwrap();
+
+        assert_eq!(
+            caps.features.keys().copied().collect::<Vec<_>>(),
+            vec![0x10, 0x12, 0x14, 0x60]
+        );
+        assert_eq!(caps.feature(0x14).unwrap().values, vec![0x05, 0x08]);
+        assert_eq!(caps.feature(0x60).unwrap().values, vec![0x0F, 0x11]);
+    }
+
+    #[test]
+    fn test_parse_missing_closing_paren() {
+        let caps = Capabilities::parse("(prot(monitor)vcp(10 12)mccs_ver(2.2)").unwrap();
+        assert!(caps.supports(0x12));
+        assert_eq!(caps.mccs_version.as_deref(), Some("2.2"));
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!(Capabilities::parse("(prot(monitor)vcp(10 12").is_err());
+        assert!(Capabilities::parse("(prot(monitor)type(lcd))").is_err());
+    }
+
+    #[test]
+    fn test_value_names() {
+        let caps = Capabilities::parse(DELL_U2715H).unwrap();
+        let mode = caps.feature(0xDC).unwrap();
+
+        assert_eq!(mode.value_name(0x03), "Movie");
+        assert_eq!(mode.value_name(0x42), "0x42");
+    }
+}
@@ -0,0 +1,39 @@
+pub const VCP_COLOR_PRESET: u8 = 0x14;
+
+pub const VCP_RED_GAIN: u8 = 0x16;
+pub const VCP_GREEN_GAIN: u8 = 0x18;
+pub const VCP_BLUE_GAIN: u8 = 0x1A;
+
+pub const VCP_RED_BLACK_LEVEL: u8 = 0x6C;
+pub const VCP_GREEN_BLACK_LEVEL: u8 = 0x6E;
+pub const VCP_BLUE_BLACK_LEVEL: u8 = 0x70;
+
+/// Per-channel video gain (white point) controls, in R, G, B order.
+pub const GAINS: [(u8, &str); 3] = [
+    (VCP_RED_GAIN, "Red"),
+    (VCP_GREEN_GAIN, "Green"),
+    (VCP_BLUE_GAIN, "Blue"),
+];
+
+/// Per-channel black level controls, in R, G, B order.
+pub const BLACK_LEVELS: [(u8, &str); 3] = [
+    (VCP_RED_BLACK_LEVEL, "Red"),
+    (VCP_GREEN_BLACK_LEVEL, "Green"),
+    (VCP_BLUE_BLACK_LEVEL, "Blue"),
+];
+
+/// Every VCP code the color panel reads and writes.
+pub const COLOR_CODES: [u8; 7] = [
+    VCP_COLOR_PRESET,
+    VCP_RED_GAIN,
+    VCP_GREEN_GAIN,
+    VCP_BLUE_GAIN,
+    VCP_RED_BLACK_LEVEL,
+    VCP_GREEN_BLACK_LEVEL,
+    VCP_BLUE_BLACK_LEVEL,
+];
+
+/// Most monitors ignore gain changes unless a user preset is active.
+pub fn is_user_preset(value: u8) -> bool {
+    matches!(value, 0x0B..=0x0D)
+}
@@ -0,0 +1,279 @@
+use std::sync::Arc;
+
+use crate::commands::CommandRunner;
+use crate::ddc::{DdcTransport, VcpValue};
+use crate::display::Display;
+use crate::error::Error;
+
+/// Fallback transport that shells out to `ddcutil` for every operation.
+pub struct DdcutilTransport {
+    runner: Arc<dyn CommandRunner>,
+    /// How ddcutil is told which monitor, e.g. `--bus 7`.
+    target: [String; 2],
+}
+
+impl DdcutilTransport {
+    /// Address a monitor by ddcutil's display number.
+    pub fn new(runner: Arc<dyn CommandRunner>, display_id: &str) -> Self {
+        Self {
+            runner,
+            target: ["--display".into(), display_id.to_string()],
+        }
+    }
+
+    /// Address a monitor by its I2C bus where it has one, since display
+    /// numbers shift when another monitor comes or goes.
+    pub fn for_display(runner: Arc<dyn CommandRunner>, display: &Display) -> Self {
+        let bus = display
+            .i2c_bus
+            .as_deref()
+            .and_then(|path| path.strip_prefix("/dev/i2c-"));
+        match bus {
+            Some(bus) => Self {
+                runner,
+                target: ["--bus".into(), bus.to_string()],
+            },
+            None => Self::new(runner, &display.id),
+        }
+    }
+}
+
+impl DdcTransport for DdcutilTransport {
+    fn get_vcp(&mut self, code: u8) -> Result<VcpValue, Error> {
+        let output = self
+            .runner
+            .run_capture(
+                "ddcutil",
+                &[
+                    "getvcp",
+                    &format!("{:02x}", code),
+                    &self.target[0],
+                    &self.target[1],
+                    "--terse",
+                ],
+            )
+            .map_err(|e| classify_error(e, Some(code)))?;
+
+        parse_terse(code, &output)
+    }
+
+    fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), Error> {
+        self.runner
+            .run(
+                "ddcutil",
+                &[
+                    "setvcp",
+                    &format!("{:02x}", code),
+                    &value.to_string(),
+                    &self.target[0],
+                    &self.target[1],
+                ],
+            )
+            .map_err(|e| classify_error(e, Some(code)))
+    }
+
+    fn capabilities(&mut self) -> Result<String, Error> {
+        let output = self
+            .runner
+            .run_capture(
+                "ddcutil",
+                &[
+                    "capabilities",
+                    &self.target[0],
+                    &self.target[1],
+                    "--verbose",
+                ],
+            )
+            .map_err(|e| classify_error(e, None))?;
+
+        // "Unparsed capabilities string: (prot(monitor)type(lcd)...)"
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Unparsed capabilities string:"))
+            .map(|s| s.trim().to_string())
+            .ok_or_else(|| Error::Parse("ddcutil capabilities output".into()))
+    }
+}
+
+/// Sort a failed ddcutil run into an error kind by its stderr.
+pub fn classify_error(error: Error, code: Option<u8>) -> Error {
+    let Error::Failed(stderr) = error else {
+        return error;
+    };
+    let lower = stderr.to_lowercase();
+
+    if lower.contains("permission denied") || lower.contains("no permission") {
+        let device = stderr
+            .split_whitespace()
+            .find(|w| w.starts_with("/dev/i2c"))
+            .map(|w| w.trim_end_matches([',', ':', '.']).to_string())
+            .unwrap_or_else(|| "the I2C bus".into());
+        Error::PermissionDenied(device)
+    } else if let Some(code) = code
+        && (lower.contains("unsupported feature") || lower.contains("not supported"))
+    {
+        Error::UnsupportedFeature(code)
+    } else if lower.contains("timeout") || lower.contains("timed out") {
+        Error::Timeout(stderr)
+    } else if lower.contains("ddc communication failed")
+        || lower.contains("no monitor detected")
+        || lower.contains("display not found")
+        || lower.contains("invalid display")
+    {
+        Error::NotResponding(stderr)
+    } else {
+        Error::Failed(stderr)
+    }
+}
+
+/// Parse `getvcp --terse` output for continuous and non-continuous features.
+fn parse_terse(code: u8, output: &str) -> Result<VcpValue, Error> {
+    let tokens: Vec<&str> = output.split_whitespace().collect();
+    let dec = |i: usize| tokens.get(i).and_then(|v| v.parse::<u16>().ok());
+    let hex = |i: usize| {
+        tokens
+            .get(i)
+            .and_then(|v| u8::from_str_radix(v.trim_start_matches('x'), 16).ok())
+    };
+
+    let value = match tokens.get(2).copied() {
+        // "VCP 10 C 50 100"
+        Some("C") => dec(3)
+            .zip(dec(4))
+            .map(|(current, max)| VcpValue::new(current, max)),
+        // "VCP 60 SNC x0f"
+        Some("SNC") => hex(3).map(|sl| VcpValue::new(sl.into(), 0)),
+        // "VCP 62 CNC x00 x64 x00 x32" (mh ml sh sl)
+        // "VCP 62 ERR" when the monitor rejects the feature
+        Some("ERR") => return Err(Error::UnsupportedFeature(code)),
+        Some("CNC") => match (hex(3), hex(4), hex(5), hex(6)) {
+            (Some(mh), Some(ml), Some(sh), Some(sl)) => Some(VcpValue::new(
+                u16::from_be_bytes([sh, sl]),
+                u16::from_be_bytes([mh, ml]),
+            )),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    value.ok_or_else(|| Error::Parse(format!("ddcutil output '{}'", output.trim())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ScriptedRunner;
+
+    #[test]
+    fn test_get_vcp_parses_terse_output() {
+        let runner =
+            ScriptedRunner::new().on("ddcutil getvcp 10 --display 2 --terse", "VCP 10 C 42 100\n");
+        let mut transport = DdcutilTransport::new(Arc::new(runner), "2");
+
+        assert_eq!(transport.get_vcp(0x10), Ok(VcpValue::new(42, 100)));
+    }
+
+    #[test]
+    fn test_parse_terse_variants() {
+        assert_eq!(
+            parse_terse(0x10, "VCP 10 C 50 100"),
+            Ok(VcpValue::new(50, 100))
+        );
+        assert_eq!(
+            parse_terse(0x10, "VCP 10 C 300 1000"),
+            Ok(VcpValue::new(300, 1000))
+        );
+        assert_eq!(
+            parse_terse(0x60, "VCP 60 SNC x0f"),
+            Ok(VcpValue::new(0x0f, 0))
+        );
+        assert_eq!(
+            parse_terse(0x62, "VCP 62 CNC x00 x64 x01 x32"),
+            Ok(VcpValue::new(0x0132, 0x64))
+        );
+        assert_eq!(
+            parse_terse(0x62, "VCP 62 ERR"),
+            Err(Error::UnsupportedFeature(0x62))
+        );
+        assert!(matches!(parse_terse(0x10, "garbage"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_capabilities_from_verbose_output() {
+        let runner = ScriptedRunner::new().on(
+            "ddcutil capabilities --display 1 --verbose",
+            "Unparsed capabilities string: (prot(monitor)vcp(10 12))\nModel: U2715H\n",
+        );
+        let mut transport = DdcutilTransport::new(Arc::new(runner), "1");
+
+        assert_eq!(
+            transport.capabilities().as_deref(),
+            Ok("(prot(monitor)vcp(10 12))")
+        );
+    }
+
+    #[test]
+    fn test_classify_stderr() {
+        let classified = |stderr: &str| classify_error(Error::Failed(stderr.into()), Some(0x10));
+
+        assert_eq!(
+            classified("Open failed for /dev/i2c-7: Permission denied"),
+            Error::PermissionDenied("/dev/i2c-7".into())
+        );
+        assert!(matches!(
+            classified("DDC communication failed"),
+            Error::NotResponding(_)
+        ));
+        assert!(matches!(
+            classified("Display not found"),
+            Error::NotResponding(_)
+        ));
+        assert_eq!(
+            classified("Unsupported feature code: 0x10"),
+            Error::UnsupportedFeature(0x10)
+        );
+        assert!(matches!(classified("something else"), Error::Failed(_)));
+        assert_eq!(
+            classify_error(Error::ToolMissing("ddcutil".into()), None),
+            Error::ToolMissing("ddcutil".into())
+        );
+    }
+
+    #[test]
+    fn test_set_vcp_formats_hex_code() {
+        let runner = Arc::new(ScriptedRunner::new().on("ddcutil setvcp 12 30 --display 1", ""));
+        let mut transport = DdcutilTransport::new(runner.clone(), "1");
+
+        transport.set_vcp(0x12, 30).unwrap();
+        assert_eq!(runner.calls(), vec!["ddcutil setvcp 12 30 --display 1"]);
+    }
+
+    #[test]
+    fn test_addressed_by_bus() {
+        let runner = Arc::new(ScriptedRunner::new().on("ddcutil setvcp 10 30 --bus 7", ""));
+        let mut display = Display {
+            id: "1".into(),
+            name: "DELL U2715H".into(),
+            drm_connector: "card1-DP-1".into(),
+            i2c_bus: Some("/dev/i2c-7".into()),
+            backlight: None,
+            edid: Default::default(),
+            key: "DEL-40E6-ABC123".into(),
+            capabilities: None,
+        };
+
+        DdcutilTransport::for_display(runner.clone(), &display)
+            .set_vcp(0x10, 30)
+            .unwrap();
+        // Without a bus, ddcutil's own numbering is all there is
+        display.i2c_bus = None;
+        let _ = DdcutilTransport::for_display(runner.clone(), &display).set_vcp(0x10, 30);
+        assert_eq!(
+            runner.calls(),
+            [
+                "ddcutil setvcp 10 30 --bus 7",
+                "ddcutil setvcp 10 30 --display 1"
+            ]
+        );
+    }
+}
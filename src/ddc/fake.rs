@@ -0,0 +1,98 @@
+//! In-memory monitor that speaks DDC/CI, for exercising the native transport.
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::ddc::i2c::{I2cDevice, encode_request};
+
+#[derive(Default)]
+pub struct FakeMonitor {
+    features: HashMap<u8, (u16, u16)>,
+    capabilities: String,
+    pending: Option<Vec<u8>>,
+}
+
+impl FakeMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_feature(mut self, code: u8, current: u16, max: u16) -> Self {
+        self.features.insert(code, (current, max));
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: &str) -> Self {
+        self.capabilities = capabilities.to_string();
+        self
+    }
+
+    fn handle(&mut self, payload: &[u8]) -> Option<Vec<u8>> {
+        match payload {
+            [0x01, code] => {
+                let (result, (current, max)) = match self.features.get(code) {
+                    Some(&value) => (0x00, value),
+                    None => (0x01, (0, 0)),
+                };
+                let [max_hi, max_lo] = max.to_be_bytes();
+                let [cur_hi, cur_lo] = current.to_be_bytes();
+                Some(vec![
+                    0x02, result, *code, 0x00, max_hi, max_lo, cur_hi, cur_lo,
+                ])
+            }
+            [0x03, code, hi, lo] => {
+                if let Some((current, _)) = self.features.get_mut(code) {
+                    *current = u16::from_be_bytes([*hi, *lo]);
+                }
+                None
+            }
+            [0xF3, hi, lo] => {
+                let offset = u16::from_be_bytes([*hi, *lo]) as usize;
+                let bytes = self.capabilities.as_bytes();
+                let fragment = &bytes[offset.min(bytes.len())..(offset + 32).min(bytes.len())];
+
+                let mut reply = vec![0xE3, *hi, *lo];
+                reply.extend_from_slice(fragment);
+                Some(reply)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Frame a reply the way a monitor would, reusing the host encoder and
+/// swapping in the monitor's source address and reply checksum seed.
+fn encode_reply(payload: &[u8]) -> Vec<u8> {
+    let mut packet = encode_request(payload);
+    packet[0] = 0x6E;
+    let len = packet.len();
+    packet[len - 1] = packet[..len - 1].iter().fold(0x50, |acc, b| acc ^ b);
+    packet
+}
+
+impl I2cDevice for FakeMonitor {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let (&checksum, body) = bytes
+            .split_last()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty write"))?;
+        if body.iter().fold(0x6E, |acc, b| acc ^ b) != checksum {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "bad checksum"));
+        }
+
+        self.pending = self
+            .handle(&body[2..])
+            .map(|payload| encode_reply(&payload));
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let reply = self
+            .pending
+            .take()
+            .unwrap_or_else(|| vec![0x6E, 0x80, 0xBE]);
+        buf.fill(0);
+        let n = reply.len().min(buf.len());
+        buf[..n].copy_from_slice(&reply[..n]);
+        Ok(())
+    }
+}
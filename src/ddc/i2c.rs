@@ -0,0 +1,382 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::ddc::{DdcTransport, VcpValue};
+use crate::error::Error;
+
+/// 7-bit I2C slave address every DDC/CI capable monitor answers on.
+pub const DDC_CI_ADDR: u16 = 0x37;
+
+const I2C_SLAVE: u64 = 0x0703;
+
+// The destination address byte is implied by the I2C transaction but still
+// takes part in the checksum: 0x6E for host writes, 0x50 for monitor replies.
+const DISPLAY_WRITE_ADDR: u8 = 0x6E;
+const REPLY_CHECKSUM_SEED: u8 = 0x50;
+const HOST_ADDR: u8 = 0x51;
+const LENGTH_FLAG: u8 = 0x80;
+
+const OP_GET_VCP: u8 = 0x01;
+const OP_GET_VCP_REPLY: u8 = 0x02;
+const OP_SET_VCP: u8 = 0x03;
+const OP_CAPABILITIES: u8 = 0xF3;
+const OP_CAPABILITIES_REPLY: u8 = 0xE3;
+
+const GET_VCP_REPLY_LEN: usize = 8;
+const CAPABILITIES_FRAGMENT_LEN: usize = 32;
+// Guards against monitors that never send the terminating empty fragment
+const CAPABILITIES_MAX_LEN: usize = 4096;
+
+/// Raw byte-level access to a monitor's DDC/CI slave.
+pub trait I2cDevice: Send {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()>;
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()>;
+}
+
+/// `/dev/i2c-N` character device bound to the DDC/CI slave address.
+pub struct LinuxI2c {
+    file: File,
+}
+
+impl LinuxI2c {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        // SAFETY: I2C_SLAVE takes the slave address by value and the fd is
+        // owned by `file` for the duration of the call.
+        let ret = unsafe {
+            libc::ioctl(
+                file.as_raw_fd(),
+                I2C_SLAVE as _,
+                DDC_CI_ADDR as libc::c_ulong,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { file })
+    }
+}
+
+impl I2cDevice for LinuxI2c {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.file.write_all(bytes)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.file.read_exact(buf)
+    }
+}
+
+/// Delays the DDC/CI spec requires between host and monitor traffic.
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    /// Wait between sending a request and reading its reply.
+    pub reply_delay: Duration,
+    /// Minimum gap between the end of one command and the start of the next.
+    pub command_interval: Duration,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            reply_delay: Duration::from_millis(40),
+            command_interval: Duration::from_millis(50),
+        }
+    }
+}
+
+impl Timing {
+    #[cfg(test)]
+    pub fn none() -> Self {
+        Self {
+            reply_delay: Duration::ZERO,
+            command_interval: Duration::ZERO,
+        }
+    }
+}
+
+/// Native DDC/CI protocol speaker on top of any [`I2cDevice`].
+pub struct DdcCi<D: I2cDevice> {
+    device: D,
+    timing: Timing,
+    last_command: Option<Instant>,
+}
+
+impl DdcCi<LinuxI2c> {
+    pub fn open(bus: &str) -> Result<Self, Error> {
+        let device = LinuxI2c::open(bus).map_err(|e| Error::from_io(bus, e))?;
+        Ok(Self::new(device, Timing::default()))
+    }
+}
+
+impl<D: I2cDevice> DdcCi<D> {
+    pub fn new(device: D, timing: Timing) -> Self {
+        Self {
+            device,
+            timing,
+            last_command: None,
+        }
+    }
+
+    /// Carry on from earlier traffic on the same bus, so the command
+    /// interval holds even though the device was opened again.
+    pub fn after(mut self, last_command: Option<Instant>) -> Self {
+        self.last_command = last_command;
+        self
+    }
+
+    pub fn last_command(&self) -> Option<Instant> {
+        self.last_command
+    }
+
+    fn wait_for_bus(&self) {
+        if let Some(last) = self.last_command {
+            let elapsed = last.elapsed();
+            if elapsed < self.timing.command_interval {
+                thread::sleep(self.timing.command_interval - elapsed);
+            }
+        }
+    }
+
+    fn send(&mut self, payload: &[u8]) -> Result<(), Error> {
+        self.wait_for_bus();
+        let result = self.device.write(&encode_request(payload));
+        self.last_command = Some(Instant::now());
+        result.map_err(|e| transfer_error("I2C write", e))
+    }
+
+    fn receive(&mut self, payload_len: usize) -> Result<Vec<u8>, Error> {
+        thread::sleep(self.timing.reply_delay);
+
+        let mut buf = vec![0u8; payload_len + 3];
+        let result = self.device.read(&mut buf);
+        self.last_command = Some(Instant::now());
+        result.map_err(|e| transfer_error("I2C read", e))?;
+
+        decode_reply(&buf)
+    }
+}
+
+impl<D: I2cDevice> DdcTransport for DdcCi<D> {
+    fn get_vcp(&mut self, code: u8) -> Result<VcpValue, Error> {
+        self.send(&[OP_GET_VCP, code])?;
+        let reply = self.receive(GET_VCP_REPLY_LEN)?;
+
+        // [opcode, result, code, type, max_hi, max_lo, cur_hi, cur_lo]
+        if reply.len() != GET_VCP_REPLY_LEN || reply[0] != OP_GET_VCP_REPLY {
+            return Err(Error::NotResponding(format!(
+                "unexpected reply to Get VCP {:02x}",
+                code
+            )));
+        }
+        if reply[1] != 0 {
+            return Err(Error::UnsupportedFeature(code));
+        }
+        if reply[2] != code {
+            return Err(Error::NotResponding(format!(
+                "reply was for VCP {:02x}, expected {:02x}",
+                reply[2], code
+            )));
+        }
+
+        Ok(VcpValue::new(
+            u16::from_be_bytes([reply[6], reply[7]]),
+            u16::from_be_bytes([reply[4], reply[5]]),
+        ))
+    }
+
+    fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), Error> {
+        let [hi, lo] = value.to_be_bytes();
+        self.send(&[OP_SET_VCP, code, hi, lo])
+    }
+
+    fn capabilities(&mut self) -> Result<String, Error> {
+        let mut data = Vec::new();
+
+        // The string is read in fragments; an empty fragment marks the end
+        while data.len() < CAPABILITIES_MAX_LEN {
+            let [hi, lo] = (data.len() as u16).to_be_bytes();
+            self.send(&[OP_CAPABILITIES, hi, lo])?;
+            let reply = self.receive(CAPABILITIES_FRAGMENT_LEN + 3)?;
+
+            if reply.len() < 3 || reply[0] != OP_CAPABILITIES_REPLY {
+                return Err(Error::NotResponding(
+                    "unexpected reply to capabilities request".into(),
+                ));
+            }
+            if reply[1..3] != [hi, lo] {
+                return Err(Error::NotResponding(
+                    "capabilities fragment offset mismatch".into(),
+                ));
+            }
+            if reply.len() == 3 {
+                break;
+            }
+
+            data.extend_from_slice(&reply[3..]);
+        }
+
+        let text = String::from_utf8_lossy(&data);
+        Ok(text.trim_end_matches('\0').to_string())
+    }
+}
+
+/// A transfer on an open bus that goes wrong means the monitor didn't take
+/// part in it.
+fn transfer_error(what: &str, e: io::Error) -> Error {
+    match Error::from_io(what, e) {
+        Error::Failed(detail) => Error::NotResponding(detail),
+        other => other,
+    }
+}
+
+fn checksum(seed: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(seed, |acc, b| acc ^ b)
+}
+
+/// Frame a host-to-monitor payload: source address, length, payload, checksum.
+pub fn encode_request(payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(payload.len() + 3);
+    packet.push(HOST_ADDR);
+    packet.push(LENGTH_FLAG | payload.len() as u8);
+    packet.extend_from_slice(payload);
+    packet.push(checksum(DISPLAY_WRITE_ADDR, &packet));
+    packet
+}
+
+/// Validate a monitor-to-host frame and return its payload.
+/// Garbled frames are reported as the monitor not responding, since a
+/// retry is the only sensible reaction.
+pub fn decode_reply(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    let garbled = |what: &str| Err(Error::NotResponding(what.to_string()));
+
+    if buf.len() < 3 || buf[0] != DISPLAY_WRITE_ADDR {
+        return garbled("malformed DDC/CI reply");
+    }
+    if buf[1] & LENGTH_FLAG == 0 {
+        return garbled("malformed DDC/CI reply length");
+    }
+
+    let len = (buf[1] & !LENGTH_FLAG) as usize;
+    if len == 0 {
+        return garbled("monitor returned a null message");
+    }
+    if buf.len() < len + 3 {
+        return garbled("truncated DDC/CI reply");
+    }
+
+    let expected = checksum(REPLY_CHECKSUM_SEED, &buf[..len + 2]);
+    if buf[len + 2] != expected {
+        return garbled("DDC/CI reply checksum mismatch");
+    }
+
+    Ok(buf[2..len + 2].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddc::fake::FakeMonitor;
+
+    #[test]
+    fn test_encode_get_vcp_request() {
+        // Brightness query as documented in the DDC/CI spec
+        assert_eq!(
+            encode_request(&[OP_GET_VCP, 0x10]),
+            vec![0x51, 0x82, 0x01, 0x10, 0xAC]
+        );
+    }
+
+    #[test]
+    fn test_decode_reply_rejects_bad_checksum() {
+        let mut reply = vec![0x6E, 0x88, 0x02, 0x00, 0x10, 0x00, 0x00, 0x64, 0x00, 0x32];
+        reply.push(checksum(REPLY_CHECKSUM_SEED, &reply));
+        assert!(decode_reply(&reply).is_ok());
+
+        let last = reply.len() - 1;
+        reply[last] ^= 0xFF;
+        assert!(decode_reply(&reply).is_err());
+    }
+
+    #[test]
+    fn test_decode_null_message() {
+        assert!(decode_reply(&[0x6E, 0x80, 0xBE]).is_err());
+    }
+
+    #[test]
+    fn test_get_and_set_against_fake_monitor() {
+        let monitor = FakeMonitor::new()
+            .with_feature(0x10, 70, 100)
+            .with_feature(0x12, 50, 100);
+        let mut ddc = DdcCi::new(monitor, Timing::none());
+
+        assert_eq!(ddc.get_vcp(0x10), Ok(VcpValue::new(70, 100)));
+        assert_eq!(ddc.get_vcp(0x12), Ok(VcpValue::new(50, 100)));
+
+        ddc.set_vcp(0x10, 25).unwrap();
+        assert_eq!(ddc.get_vcp(0x10), Ok(VcpValue::new(25, 100)));
+    }
+
+    #[test]
+    fn test_sixteen_bit_values() {
+        let mut ddc = DdcCi::new(
+            FakeMonitor::new().with_feature(0x0C, 6500, 10000),
+            Timing::none(),
+        );
+
+        assert_eq!(ddc.get_vcp(0x0C), Ok(VcpValue::new(6500, 10000)));
+        ddc.set_vcp(0x0C, 5000).unwrap();
+        assert_eq!(ddc.get_vcp(0x0C).map(|v| v.current), Ok(5000));
+    }
+
+    #[test]
+    fn test_unsupported_feature() {
+        let mut ddc = DdcCi::new(FakeMonitor::new(), Timing::none());
+        assert_eq!(ddc.get_vcp(0x62), Err(Error::UnsupportedFeature(0x62)));
+    }
+
+    #[test]
+    fn test_capabilities_are_reassembled_from_fragments() {
+        let caps = "(prot(monitor)type(lcd)model(TEST)cmds(01 02 03 F3)vcp(02 10 12 60(0F 11))mccs_ver(2.2))";
+        let mut ddc = DdcCi::new(FakeMonitor::new().with_capabilities(caps), Timing::none());
+
+        assert_eq!(ddc.capabilities().as_deref(), Ok(caps));
+    }
+
+    #[test]
+    fn test_command_interval_is_respected() {
+        let timing = Timing {
+            reply_delay: Duration::ZERO,
+            command_interval: Duration::from_millis(20),
+        };
+        let mut ddc = DdcCi::new(FakeMonitor::new().with_feature(0x10, 1, 100), timing);
+
+        let start = Instant::now();
+        ddc.set_vcp(0x10, 2).unwrap();
+        ddc.set_vcp(0x10, 3).unwrap();
+        ddc.get_vcp(0x10).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_command_interval_spans_reopening() {
+        let timing = Timing {
+            reply_delay: Duration::ZERO,
+            command_interval: Duration::from_millis(20),
+        };
+        let mut write = DdcCi::new(FakeMonitor::new().with_feature(0x10, 1, 100), timing);
+        write.set_vcp(0x10, 2).unwrap();
+
+        // The verify read comes through a freshly opened device
+        let start = Instant::now();
+        let mut verify = DdcCi::new(FakeMonitor::new().with_feature(0x10, 2, 100), timing)
+            .after(write.last_command());
+        verify.get_vcp(0x10).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(15));
+    }
+}
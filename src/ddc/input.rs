@@ -0,0 +1,57 @@
+use crate::ddc::mccs::INPUT_SOURCES;
+
+pub const VCP_INPUT_SOURCE: u8 = 0x60;
+
+/// Friendly name for an input source value, e.g. "DP-1" or "HDMI-2".
+pub fn input_name(code: u8) -> String {
+    INPUT_SOURCES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("Input 0x{:02X}", code))
+}
+
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .replace("displayport", "dp")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
+
+/// Parse an input given on the command line: a friendly name in any case
+/// ("hdmi2", "DisplayPort-1", "usb-c") or a raw hex value ("0x0f").
+pub fn parse_input(s: &str) -> Option<u8> {
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return u8::from_str_radix(hex, 16).ok();
+    }
+
+    let wanted = normalize(s);
+    INPUT_SOURCES
+        .iter()
+        .find(|(_, name)| normalize(name) == wanted)
+        .map(|(code, _)| *code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_names() {
+        assert_eq!(input_name(0x0F), "DP-1");
+        assert_eq!(input_name(0x12), "HDMI-2");
+        assert_eq!(input_name(0x1B), "USB-C");
+        assert_eq!(input_name(0x42), "Input 0x42");
+    }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(parse_input("DP-1"), Some(0x0F));
+        assert_eq!(parse_input("displayport-2"), Some(0x10));
+        assert_eq!(parse_input("hdmi1"), Some(0x11));
+        assert_eq!(parse_input("USB-C"), Some(0x1B));
+        assert_eq!(parse_input("0x11"), Some(0x11));
+        assert_eq!(parse_input("thunderbolt"), None);
+    }
+}
@@ -0,0 +1,361 @@
+//! VESA MCCS feature table: names, value types and access modes for the VCP
+//! codes a monitor may advertise in its capabilities string.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureKind {
+    /// Any value between 0 and the monitor's reported maximum.
+    Continuous,
+    /// One of a fixed set of values, usually listed in the capabilities string.
+    NonContinuous,
+    /// Multi-byte table read/written with dedicated commands; not supported.
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    ReadWrite,
+    ReadOnly,
+    WriteOnly,
+}
+
+#[derive(Debug)]
+pub struct FeatureInfo {
+    pub code: u8,
+    pub name: &'static str,
+    pub kind: FeatureKind,
+    pub access: Access,
+    pub values: &'static [(u8, &'static str)],
+}
+
+impl FeatureInfo {
+    const fn read_only(self) -> Self {
+        Self {
+            access: Access::ReadOnly,
+            ..self
+        }
+    }
+
+    const fn write_only(self) -> Self {
+        Self {
+            access: Access::WriteOnly,
+            ..self
+        }
+    }
+
+    const fn with_values(self, values: &'static [(u8, &'static str)]) -> Self {
+        Self { values, ..self }
+    }
+
+    pub fn value_name(&self, value: u8) -> Option<&'static str> {
+        self.values
+            .iter()
+            .find(|(v, _)| *v == value)
+            .map(|(_, name)| *name)
+    }
+}
+
+const fn c(code: u8, name: &'static str) -> FeatureInfo {
+    FeatureInfo {
+        code,
+        name,
+        kind: FeatureKind::Continuous,
+        access: Access::ReadWrite,
+        values: &[],
+    }
+}
+
+const fn nc(code: u8, name: &'static str) -> FeatureInfo {
+    FeatureInfo {
+        code,
+        name,
+        kind: FeatureKind::NonContinuous,
+        access: Access::ReadWrite,
+        values: &[],
+    }
+}
+
+const fn table(code: u8, name: &'static str) -> FeatureInfo {
+    FeatureInfo {
+        code,
+        name,
+        kind: FeatureKind::Table,
+        access: Access::ReadOnly,
+        values: &[],
+    }
+}
+
+/// Short names for VCP 0x60 values. 0x1B is not in MCCS but is what Dell,
+/// HP and Lenovo docks report for USB-C.
+pub const INPUT_SOURCES: &[(u8, &str)] = &[
+    (0x01, "VGA-1"),
+    (0x02, "VGA-2"),
+    (0x03, "DVI-1"),
+    (0x04, "DVI-2"),
+    (0x05, "Composite-1"),
+    (0x06, "Composite-2"),
+    (0x07, "S-Video-1"),
+    (0x08, "S-Video-2"),
+    (0x09, "Tuner-1"),
+    (0x0A, "Tuner-2"),
+    (0x0B, "Tuner-3"),
+    (0x0C, "Component-1"),
+    (0x0D, "Component-2"),
+    (0x0E, "Component-3"),
+    (0x0F, "DP-1"),
+    (0x10, "DP-2"),
+    (0x11, "HDMI-1"),
+    (0x12, "HDMI-2"),
+    (0x1B, "USB-C"),
+];
+
+const COLOR_PRESETS: &[(u8, &str)] = &[
+    (0x01, "sRGB"),
+    (0x02, "Display Native"),
+    (0x03, "4000 K"),
+    (0x04, "5000 K"),
+    (0x05, "6500 K"),
+    (0x06, "7500 K"),
+    (0x07, "8200 K"),
+    (0x08, "9300 K"),
+    (0x09, "10000 K"),
+    (0x0A, "11500 K"),
+    (0x0B, "User 1"),
+    (0x0C, "User 2"),
+    (0x0D, "User 3"),
+];
+
+const POWER_MODE: &[(u8, &str)] = &[
+    (0x01, "On"),
+    (0x02, "Standby"),
+    (0x03, "Suspend"),
+    (0x04, "Off"),
+    (0x05, "Power off"),
+];
+
+const AUDIO_MUTE: &[(u8, &str)] = &[(0x01, "Muted"), (0x02, "Unmuted")];
+
+const SCREEN_ORIENTATION: &[(u8, &str)] = &[
+    (0x01, "0°"),
+    (0x02, "90°"),
+    (0x03, "180°"),
+    (0x04, "270°"),
+    (0xFF, "Not applicable"),
+];
+
+const DISPLAY_TECHNOLOGY: &[(u8, &str)] = &[
+    (0x01, "CRT (shadow mask)"),
+    (0x02, "CRT (aperture grille)"),
+    (0x03, "LCD (active matrix)"),
+    (0x04, "LCoS"),
+    (0x05, "Plasma"),
+    (0x06, "OLED"),
+    (0x07, "EL"),
+    (0x08, "Dynamic MEM"),
+    (0x09, "Static MEM"),
+];
+
+const DISPLAY_MODE: &[(u8, &str)] = &[
+    (0x00, "Standard"),
+    (0x01, "Productivity"),
+    (0x02, "Mixed"),
+    (0x03, "Movie"),
+    (0x04, "User defined"),
+    (0x05, "Games"),
+    (0x06, "Sports"),
+    (0x07, "Professional"),
+    (0x08, "Standard (intermediate power)"),
+    (0x09, "Standard (low power)"),
+    (0x0A, "Demonstration"),
+    (0xF0, "Dynamic contrast"),
+];
+
+pub const FEATURES: &[FeatureInfo] = &[
+    nc(0x02, "New Control Value"),
+    nc(0x04, "Restore Factory Defaults").write_only(),
+    nc(0x05, "Restore Factory Brightness/Contrast").write_only(),
+    nc(0x06, "Restore Factory Geometry").write_only(),
+    nc(0x08, "Restore Factory Color").write_only(),
+    nc(0x0A, "Restore Factory TV Defaults").write_only(),
+    nc(0x0B, "Color Temperature Increment").read_only(),
+    c(0x0C, "Color Temperature Request"),
+    c(0x0E, "Clock"),
+    c(0x10, "Brightness"),
+    nc(0x11, "Flesh Tone Enhancement"),
+    c(0x12, "Contrast"),
+    c(0x13, "Backlight Control"),
+    nc(0x14, "Select Color Preset").with_values(COLOR_PRESETS),
+    c(0x16, "Video Gain: Red"),
+    c(0x17, "User Color Vision Compensation"),
+    c(0x18, "Video Gain: Green"),
+    c(0x1A, "Video Gain: Blue"),
+    c(0x1C, "Focus"),
+    nc(0x1E, "Auto Setup"),
+    nc(0x1F, "Auto Color Setup"),
+    c(0x20, "Horizontal Position"),
+    c(0x22, "Horizontal Size"),
+    c(0x24, "Horizontal Pincushion"),
+    c(0x26, "Horizontal Pincushion Balance"),
+    c(0x28, "Horizontal Convergence R/B"),
+    c(0x29, "Horizontal Convergence M/G"),
+    c(0x2A, "Horizontal Linearity"),
+    c(0x2C, "Horizontal Linearity Balance"),
+    c(0x2E, "Gray Scale Expansion"),
+    c(0x30, "Vertical Position"),
+    c(0x32, "Vertical Size"),
+    c(0x34, "Vertical Pincushion"),
+    c(0x36, "Vertical Pincushion Balance"),
+    c(0x38, "Vertical Convergence R/B"),
+    c(0x39, "Vertical Convergence M/G"),
+    c(0x3A, "Vertical Linearity"),
+    c(0x3C, "Vertical Linearity Balance"),
+    c(0x3E, "Clock Phase"),
+    c(0x40, "Horizontal Parallelogram"),
+    c(0x41, "Vertical Parallelogram"),
+    c(0x42, "Horizontal Keystone"),
+    c(0x43, "Vertical Keystone"),
+    c(0x44, "Rotation"),
+    c(0x46, "Top Corner Flare"),
+    c(0x48, "Top Corner Hook"),
+    c(0x4A, "Bottom Corner Flare"),
+    c(0x4C, "Bottom Corner Hook"),
+    nc(0x52, "Active Control").read_only(),
+    nc(0x54, "Performance Preservation"),
+    c(0x56, "Horizontal Moiré"),
+    c(0x58, "Vertical Moiré"),
+    c(0x59, "6 Axis Saturation: Red"),
+    c(0x5A, "6 Axis Saturation: Yellow"),
+    c(0x5B, "6 Axis Saturation: Green"),
+    c(0x5C, "6 Axis Saturation: Cyan"),
+    c(0x5D, "6 Axis Saturation: Blue"),
+    c(0x5E, "6 Axis Saturation: Magenta"),
+    nc(0x60, "Input Source").with_values(INPUT_SOURCES),
+    c(0x62, "Audio: Speaker Volume"),
+    nc(0x63, "Speaker Select"),
+    c(0x64, "Audio: Microphone Volume"),
+    nc(0x66, "Ambient Light Sensor"),
+    c(0x6B, "Backlight Level: White"),
+    c(0x6C, "Video Black Level: Red"),
+    c(0x6D, "Backlight Level: Red"),
+    c(0x6E, "Video Black Level: Green"),
+    c(0x6F, "Backlight Level: Green"),
+    c(0x70, "Video Black Level: Blue"),
+    c(0x71, "Backlight Level: Blue"),
+    nc(0x72, "Gamma"),
+    table(0x73, "LUT Size"),
+    table(0x74, "Single Point LUT Operation"),
+    table(0x75, "Block LUT Operation"),
+    nc(0x76, "Remote Procedure Call").write_only(),
+    nc(0x78, "Display Identification Data Operation").read_only(),
+    nc(0x82, "Horizontal Mirror (Flip)"),
+    nc(0x84, "Vertical Mirror (Flip)"),
+    nc(0x86, "Display Scaling"),
+    c(0x87, "Sharpness"),
+    c(0x88, "Velocity Scan Modulation"),
+    c(0x8A, "Color Saturation"),
+    nc(0x8B, "TV Channel Up/Down").write_only(),
+    c(0x8C, "TV Sharpness"),
+    nc(0x8D, "Audio Mute / Screen Blank").with_values(AUDIO_MUTE),
+    c(0x8E, "TV Contrast"),
+    c(0x8F, "Audio: Treble"),
+    c(0x90, "Hue"),
+    c(0x91, "Audio: Bass"),
+    c(0x92, "TV Black Level"),
+    c(0x93, "Audio: Balance L/R"),
+    nc(0x94, "Audio Processor Mode"),
+    c(0x95, "Window Position (TL_X)"),
+    c(0x96, "Window Position (TL_Y)"),
+    c(0x97, "Window Position (BR_X)"),
+    c(0x98, "Window Position (BR_Y)"),
+    nc(0x99, "Window Control On/Off"),
+    c(0x9A, "Window Background"),
+    c(0x9B, "6 Axis Hue: Red"),
+    c(0x9C, "6 Axis Hue: Yellow"),
+    c(0x9D, "6 Axis Hue: Green"),
+    c(0x9E, "6 Axis Hue: Cyan"),
+    c(0x9F, "6 Axis Hue: Blue"),
+    c(0xA0, "6 Axis Hue: Magenta"),
+    nc(0xA2, "Auto Setup On/Off").write_only(),
+    nc(0xA4, "Window Mask Control"),
+    nc(0xA5, "Window Select"),
+    nc(0xAA, "Screen Orientation")
+        .read_only()
+        .with_values(SCREEN_ORIENTATION),
+    c(0xAC, "Horizontal Frequency").read_only(),
+    c(0xAE, "Vertical Frequency").read_only(),
+    nc(0xB0, "Settings").write_only(),
+    nc(0xB2, "Flat Panel Sub-Pixel Layout").read_only(),
+    nc(0xB4, "Source Timing Mode"),
+    nc(0xB6, "Display Technology Type")
+        .read_only()
+        .with_values(DISPLAY_TECHNOLOGY),
+    c(0xB7, "Monitor Status").read_only(),
+    c(0xB8, "Packet Count").read_only(),
+    c(0xB9, "Monitor X Origin"),
+    c(0xBA, "Monitor Y Origin"),
+    c(0xBB, "Header Error Count").read_only(),
+    c(0xBC, "Body CRC Error Count").read_only(),
+    c(0xBD, "Client ID").read_only(),
+    nc(0xBE, "Link Control"),
+    c(0xC0, "Display Usage Time").read_only(),
+    table(0xC2, "Display Descriptor Length"),
+    table(0xC3, "Transmit Display Descriptor"),
+    nc(0xC4, "Enable Display of Display Descriptor"),
+    nc(0xC6, "Application Enable Key").read_only(),
+    nc(0xC8, "Display Controller Type").read_only(),
+    c(0xC9, "Display Firmware Level").read_only(),
+    nc(0xCA, "OSD / Button Control"),
+    nc(0xCC, "OSD Language"),
+    nc(0xCD, "Status Indicators"),
+    table(0xCE, "Auxiliary Display Size"),
+    table(0xCF, "Auxiliary Display Data"),
+    nc(0xD0, "Output Select"),
+    table(0xD2, "Asset Tag"),
+    nc(0xD4, "Stereo Video Mode"),
+    nc(0xD6, "Power Mode").with_values(POWER_MODE),
+    nc(0xD7, "Auxiliary Power Output"),
+    nc(0xDA, "Scan Mode"),
+    nc(0xDB, "Image Mode"),
+    nc(0xDC, "Display Mode").with_values(DISPLAY_MODE),
+    nc(0xDE, "Scratch Pad"),
+    nc(0xDF, "VCP Version").read_only(),
+];
+
+/// Restores that throw away the user's own monitor settings.
+pub fn is_destructive(code: u8) -> bool {
+    matches!(code, 0x04 | 0x05 | 0x06 | 0x08 | 0x0A)
+}
+
+pub fn lookup(code: u8) -> Option<&'static FeatureInfo> {
+    FEATURES.iter().find(|f| f.code == code)
+}
+
+/// Human readable name for any VCP code, including ones outside the table.
+pub fn feature_name(code: u8) -> String {
+    match lookup(code) {
+        Some(info) => info.name.to_string(),
+        None if code >= 0xE0 => format!("Manufacturer Specific ({:02X})", code),
+        None => format!("Unknown Feature ({:02X})", code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_is_sorted_and_unique() {
+        assert!(FEATURES.windows(2).all(|w| w[0].code < w[1].code));
+    }
+
+    #[test]
+    fn test_feature_names() {
+        assert_eq!(feature_name(0x10), "Brightness");
+        assert_eq!(feature_name(0xE2), "Manufacturer Specific (E2)");
+        assert_eq!(
+            lookup(0xB6).and_then(|f| f.value_name(0x03)),
+            Some("LCD (active matrix)")
+        );
+        assert!(is_destructive(0x04));
+        assert!(!is_destructive(0xB0));
+    }
+}
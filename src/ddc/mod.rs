@@ -0,0 +1,225 @@
+mod audio;
+mod capabilities;
+mod color;
+mod ddcutil;
+#[cfg(test)]
+pub mod fake;
+mod i2c;
+mod input;
+pub mod mccs;
+mod power;
+mod write;
+
+pub use audio::{VCP_AUDIO_MUTE, VCP_AUDIO_VOLUME, is_muted, mute_value};
+pub use capabilities::{Capabilities, Feature};
+pub use color::{BLACK_LEVELS, COLOR_CODES, GAINS, VCP_COLOR_PRESET, is_user_preset};
+pub use ddcutil::{DdcutilTransport, classify_error};
+pub use i2c::DdcCi;
+pub use input::{VCP_INPUT_SOURCE, input_name, parse_input};
+pub use power::{PowerMode, VCP_POWER_MODE};
+pub use write::{WriteOutcome, WritePolicy};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use crate::backlight::BacklightTransport;
+use crate::commands::CommandRunner;
+use crate::display::Display;
+use crate::error::Error;
+
+pub const VCP_BRIGHTNESS: u8 = 0x10;
+pub const VCP_CONTRAST: u8 = 0x12;
+
+/// A feature's value together with the maximum the monitor reports for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VcpValue {
+    pub current: u16,
+    pub max: u16,
+}
+
+impl Default for VcpValue {
+    fn default() -> Self {
+        Self::new(0, 100)
+    }
+}
+
+impl VcpValue {
+    pub fn new(current: u16, max: u16) -> Self {
+        Self { current, max }
+    }
+
+    /// Position on a 0-100 scale, so sliders behave the same whatever the
+    /// monitor's native range is.
+    pub fn percent(&self) -> u8 {
+        if self.max == 0 {
+            return 0;
+        }
+        let current = self.current.min(self.max) as u32;
+        ((current * 100 + self.max as u32 / 2) / self.max as u32) as u8
+    }
+
+    /// The raw value closest to `percent` of this feature's range.
+    pub fn with_percent(self, percent: u8) -> Self {
+        let percent = percent.min(100) as u32;
+        let current = (percent * self.max as u32 + 50) / 100;
+        Self {
+            current: current as u16,
+            ..self
+        }
+    }
+
+    /// Non-continuous features carry their value in the low byte.
+    pub fn low_byte(&self) -> u8 {
+        self.current.to_be_bytes()[1]
+    }
+}
+
+/// A channel capable of reading and writing VCP features on one monitor.
+pub trait DdcTransport: Send {
+    fn get_vcp(&mut self, code: u8) -> Result<VcpValue, Error>;
+    fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), Error>;
+    /// Raw MCCS capabilities string as reported by the monitor.
+    fn capabilities(&mut self) -> Result<String, Error>;
+}
+
+/// One bus's lock, guarding when native traffic last went over it.
+type BusLock = Arc<Mutex<Option<Instant>>>;
+
+/// Entry point to the DDC layer, deciding which transport talks to a monitor.
+/// Clones share per-bus locks, so threads never interleave traffic on one bus.
+#[derive(Clone)]
+pub struct Ddc {
+    runner: Arc<dyn CommandRunner>,
+    native_i2c: bool,
+    policy: WritePolicy,
+    bus_locks: Arc<Mutex<HashMap<String, BusLock>>>,
+}
+
+impl Ddc {
+    pub fn new(runner: Arc<dyn CommandRunner>, native_i2c: bool) -> Self {
+        Self {
+            runner,
+            native_i2c,
+            policy: WritePolicy::default(),
+            bus_locks: Arc::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: WritePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The same buses, locks included, reached through another runner.
+    pub fn with_runner(&self, runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            runner,
+            ..self.clone()
+        }
+    }
+
+    /// Run `f` with the bus held and the fastest transport available for a
+    /// display: the backlight for a laptop panel, native I2C when enabled
+    /// and the bus device is accessible, falling back to `ddcutil`.
+    fn with_transport<T>(
+        &self,
+        display: &Display,
+        f: impl FnOnce(&mut dyn DdcTransport) -> T,
+    ) -> T {
+        let lock = self.bus_lock(display);
+        let mut last_command = lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(device) = &display.backlight {
+            return f(&mut BacklightTransport::new(&self.runner.sysfs(), device));
+        }
+
+        if self.native_i2c
+            && let Some(bus) = &display.i2c_bus
+            && let Ok(transport) = DdcCi::open(bus)
+        {
+            let mut transport = transport.after(*last_command);
+            let result = f(&mut transport);
+            *last_command = transport.last_command();
+            return result;
+        }
+
+        f(&mut DdcutilTransport::for_display(
+            self.runner.clone(),
+            display,
+        ))
+    }
+
+    fn bus_lock(&self, display: &Display) -> BusLock {
+        let bus = display
+            .i2c_bus
+            .clone()
+            .unwrap_or_else(|| display.id.clone());
+        let mut locks = self
+            .bus_locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        locks.entry(bus).or_default().clone()
+    }
+
+    pub fn read_vcp(&self, display: &Display, code: u8) -> Result<VcpValue, Error> {
+        self.with_transport(display, |transport| transport.get_vcp(code))
+    }
+
+    /// Write a feature under the retry policy, reading it back when the
+    /// policy asks for it and the monitor can report the feature.
+    pub fn set_vcp(&self, display: &Display, code: u8, value: u16) -> WriteOutcome {
+        let readable = display
+            .capabilities
+            .as_ref()
+            .is_none_or(|c| c.feature(code).is_some_and(|f| f.readable()));
+        // A monitor switching input or powering down may stop answering
+        // before it can be read back, and a backlight write either lands
+        // or fails outright
+        let verify = self.policy.verify
+            && readable
+            && display.backlight.is_none()
+            && !matches!(code, VCP_INPUT_SOURCE | VCP_POWER_MODE);
+
+        self.with_transport(display, |transport| {
+            write::write_with_policy(transport, code, value, &self.policy, verify)
+        })
+    }
+
+    pub fn capabilities(&self, display: &Display) -> Result<Capabilities, Error> {
+        let raw = self.with_transport(display, |transport| transport.capabilities())?;
+        Capabilities::parse(&raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_scaling() {
+        assert_eq!(VcpValue::new(50, 100).percent(), 50);
+        assert_eq!(VcpValue::new(25, 50).percent(), 50);
+        assert_eq!(VcpValue::new(255, 255).percent(), 100);
+        assert_eq!(VcpValue::new(0, 0).percent(), 0);
+
+        assert_eq!(VcpValue::new(0, 50).with_percent(40).current, 20);
+        assert_eq!(VcpValue::new(0, 255).with_percent(50).current, 128);
+        assert_eq!(VcpValue::new(0, 1000).with_percent(100).current, 1000);
+    }
+
+    #[test]
+    fn test_percent_round_trip() {
+        for max in [50, 100, 255, 1000] {
+            for pct in 0..=100 {
+                let value = VcpValue::new(0, max).with_percent(pct);
+                assert!(
+                    value.percent().abs_diff(pct) <= 1,
+                    "max {} pct {}",
+                    max,
+                    pct
+                );
+            }
+        }
+    }
+}
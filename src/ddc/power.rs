@@ -0,0 +1,78 @@
+pub const VCP_POWER_MODE: u8 = 0xD6;
+
+/// DPM power states from VCP 0xD6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerMode {
+    On,
+    Standby,
+    Suspend,
+    Off,
+    /// Equivalent to pressing the power button; most monitors cannot be
+    /// woken from this over DDC.
+    PowerOff,
+}
+
+impl PowerMode {
+    pub fn from_vcp(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(PowerMode::On),
+            0x02 => Some(PowerMode::Standby),
+            0x03 => Some(PowerMode::Suspend),
+            0x04 => Some(PowerMode::Off),
+            0x05 => Some(PowerMode::PowerOff),
+            _ => None,
+        }
+    }
+
+    pub fn vcp_value(self) -> u8 {
+        match self {
+            PowerMode::On => 0x01,
+            PowerMode::Standby => 0x02,
+            PowerMode::Suspend => 0x03,
+            PowerMode::Off => 0x04,
+            PowerMode::PowerOff => 0x05,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerMode::On => "On",
+            PowerMode::Standby => "Standby",
+            PowerMode::Suspend => "Suspend",
+            PowerMode::Off => "Off",
+            PowerMode::PowerOff => "Power off",
+        }
+    }
+
+    /// State as it reads after "the monitor is", e.g. "in standby".
+    pub fn describe(self) -> &'static str {
+        match self {
+            PowerMode::On => "on",
+            PowerMode::Standby => "in standby",
+            PowerMode::Suspend => "suspended",
+            PowerMode::Off => "off",
+            PowerMode::PowerOff => "powered off",
+        }
+    }
+
+    /// Whether the monitor is expected to answer DDC requests in this state.
+    pub fn is_on(self) -> bool {
+        self == PowerMode::On
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vcp_round_trip() {
+        for value in 0x01..=0x05 {
+            assert_eq!(
+                PowerMode::from_vcp(value).map(PowerMode::vcp_value),
+                Some(value)
+            );
+        }
+        assert_eq!(PowerMode::from_vcp(0x00), None);
+    }
+}
@@ -0,0 +1,312 @@
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+use crate::ddc::{DdcTransport, VcpValue};
+use crate::error::Error;
+
+/// How hard to try before giving up on a VCP write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WritePolicy {
+    /// Total number of writes, including the first.
+    pub attempts: u32,
+    /// Delay before the first retry; doubled for each retry after it.
+    pub backoff: Duration,
+    /// Read the feature back after writing and retry if it didn't stick.
+    pub verify: bool,
+}
+
+impl Default for WritePolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(100),
+            verify: true,
+        }
+    }
+}
+
+impl WritePolicy {
+    /// Retry without sleeping, so tests don't wait on backoff.
+    #[cfg(test)]
+    pub fn immediate() -> Self {
+        Self {
+            backoff: Duration::ZERO,
+            ..Self::default()
+        }
+    }
+
+    fn delay_before(&self, attempt: u32) -> Duration {
+        self.backoff * 2u32.saturating_pow(attempt.saturating_sub(2))
+    }
+}
+
+/// What happened to a VCP write once the retry policy ran its course.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Applied,
+    AppliedAfterRetry {
+        attempts: u32,
+    },
+    /// The monitor accepted the write but kept reporting a different value.
+    Mismatch {
+        expected: u16,
+        actual: u16,
+    },
+    Unresponsive(Error),
+}
+
+impl WriteOutcome {
+    pub fn is_applied(&self) -> bool {
+        matches!(
+            self,
+            WriteOutcome::Applied | WriteOutcome::AppliedAfterRetry { .. }
+        )
+    }
+
+    /// `Ok` once the write landed, otherwise why it didn't.
+    pub fn check(self, feature: &str) -> Result<(), Error> {
+        match self {
+            WriteOutcome::Unresponsive(e) => Err(e),
+            outcome if !outcome.is_applied() => Err(Error::Failed(format!(
+                "Failed to set {}: {}",
+                feature, outcome
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for WriteOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteOutcome::Applied => write!(f, "applied"),
+            WriteOutcome::AppliedAfterRetry { attempts } => {
+                write!(f, "applied after {} attempts", attempts)
+            }
+            WriteOutcome::Mismatch { expected, actual } => {
+                write!(f, "monitor reports {} instead of {}", actual, expected)
+            }
+            WriteOutcome::Unresponsive(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Write a feature, optionally reading it back, and retry with exponential
+/// backoff until it sticks or the policy's attempts run out.
+pub fn write_with_policy(
+    transport: &mut dyn DdcTransport,
+    code: u8,
+    value: u16,
+    policy: &WritePolicy,
+    verify: bool,
+) -> WriteOutcome {
+    let mut outcome = WriteOutcome::Unresponsive(Error::Failed("no write attempted".into()));
+
+    for attempt in 1..=policy.attempts.max(1) {
+        if attempt > 1 {
+            thread::sleep(policy.delay_before(attempt));
+        }
+
+        match transport.set_vcp(code, value) {
+            Ok(()) => {}
+            Err(e) if e.is_transient() => {
+                outcome = WriteOutcome::Unresponsive(e);
+                continue;
+            }
+            Err(e) => return WriteOutcome::Unresponsive(e),
+        }
+
+        // A failed read-back says nothing about the write itself, which the
+        // monitor already acknowledged
+        if verify
+            && let Ok(actual) = transport.get_vcp(code)
+            && !reads_back_as(actual, value)
+        {
+            outcome = WriteOutcome::Mismatch {
+                expected: value,
+                actual: actual.current,
+            };
+            continue;
+        }
+
+        return match attempt {
+            1 => WriteOutcome::Applied,
+            attempts => WriteOutcome::AppliedAfterRetry { attempts },
+        };
+    }
+
+    outcome
+}
+
+/// Whether a read-back shows `value`. Non-continuous features, which have no
+/// maximum, keep their value in the low byte, and many monitors put junk in
+/// the high one.
+fn reads_back_as(actual: VcpValue, value: u16) -> bool {
+    if actual.max == 0 {
+        actual.low_byte() == value as u8
+    } else {
+        actual.current == value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A monitor that fails its first writes and may ignore the rest.
+    struct FlakyMonitor {
+        failures: u32,
+        drops_writes: bool,
+        value: u16,
+        /// Reported maximum, and high byte it reads back with when that's 0.
+        max: u16,
+        junk: u16,
+        writes: u32,
+    }
+
+    impl FlakyMonitor {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures,
+                drops_writes: false,
+                value: 10,
+                max: 100,
+                junk: 0,
+                writes: 0,
+            }
+        }
+    }
+
+    impl DdcTransport for FlakyMonitor {
+        fn get_vcp(&mut self, _code: u8) -> Result<VcpValue, Error> {
+            Ok(VcpValue::new(self.value | self.junk, self.max))
+        }
+
+        fn set_vcp(&mut self, _code: u8, value: u16) -> Result<(), Error> {
+            self.writes += 1;
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(Error::NotResponding("DDC communication failed".into()));
+            }
+            if !self.drops_writes {
+                self.value = value;
+            }
+            Ok(())
+        }
+
+        fn capabilities(&mut self) -> Result<String, Error> {
+            Err(Error::Failed("unsupported".into()))
+        }
+    }
+
+    #[test]
+    fn test_applied_first_time() {
+        let mut monitor = FlakyMonitor::new(0);
+        let outcome = write_with_policy(&mut monitor, 0x10, 40, &WritePolicy::immediate(), true);
+
+        assert_eq!(outcome, WriteOutcome::Applied);
+        assert_eq!(monitor.value, 40);
+    }
+
+    #[test]
+    fn test_applied_after_retry() {
+        let mut monitor = FlakyMonitor::new(2);
+        let outcome = write_with_policy(&mut monitor, 0x10, 40, &WritePolicy::immediate(), true);
+
+        assert_eq!(outcome, WriteOutcome::AppliedAfterRetry { attempts: 3 });
+        assert!(outcome.is_applied());
+    }
+
+    #[test]
+    fn test_unresponsive_after_all_attempts() {
+        let mut monitor = FlakyMonitor::new(5);
+        let outcome = write_with_policy(&mut monitor, 0x10, 40, &WritePolicy::immediate(), true);
+
+        assert_eq!(
+            outcome,
+            WriteOutcome::Unresponsive(Error::NotResponding("DDC communication failed".into()))
+        );
+        assert_eq!(monitor.writes, 3);
+    }
+
+    #[test]
+    fn test_permanent_errors_are_not_retried() {
+        struct Unsupported(u32);
+
+        impl DdcTransport for Unsupported {
+            fn get_vcp(&mut self, code: u8) -> Result<VcpValue, Error> {
+                Err(Error::UnsupportedFeature(code))
+            }
+
+            fn set_vcp(&mut self, code: u8, _value: u16) -> Result<(), Error> {
+                self.0 += 1;
+                Err(Error::UnsupportedFeature(code))
+            }
+
+            fn capabilities(&mut self) -> Result<String, Error> {
+                Err(Error::Failed("unsupported".into()))
+            }
+        }
+
+        let mut monitor = Unsupported(0);
+        let outcome = write_with_policy(&mut monitor, 0x62, 40, &WritePolicy::immediate(), true);
+
+        assert_eq!(
+            outcome,
+            WriteOutcome::Unresponsive(Error::UnsupportedFeature(0x62))
+        );
+        assert_eq!(monitor.0, 1);
+    }
+
+    #[test]
+    fn test_dropped_write_is_a_mismatch() {
+        let mut monitor = FlakyMonitor::new(0);
+        monitor.drops_writes = true;
+
+        let outcome = write_with_policy(&mut monitor, 0x10, 40, &WritePolicy::immediate(), true);
+        assert_eq!(
+            outcome,
+            WriteOutcome::Mismatch {
+                expected: 40,
+                actual: 10
+            }
+        );
+        assert_eq!(outcome.to_string(), "monitor reports 10 instead of 40");
+
+        // Without verification the dropped write goes unnoticed
+        let outcome = write_with_policy(&mut monitor, 0x10, 40, &WritePolicy::immediate(), false);
+        assert_eq!(outcome, WriteOutcome::Applied);
+    }
+
+    #[test]
+    fn test_non_continuous_compares_the_low_byte() {
+        let mut monitor = FlakyMonitor::new(0);
+        monitor.max = 0;
+        monitor.junk = 0x3400;
+
+        let outcome = write_with_policy(&mut monitor, 0x14, 5, &WritePolicy::immediate(), true);
+        assert_eq!(outcome, WriteOutcome::Applied);
+        assert_eq!(monitor.writes, 1);
+
+        // Still a mismatch when the low byte is wrong too
+        monitor.drops_writes = true;
+        let outcome = write_with_policy(&mut monitor, 0x14, 6, &WritePolicy::immediate(), true);
+        assert!(matches!(
+            outcome,
+            WriteOutcome::Mismatch { expected: 6, .. }
+        ));
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = WritePolicy {
+            backoff: Duration::from_millis(100),
+            ..WritePolicy::default()
+        };
+
+        assert_eq!(policy.delay_before(2), Duration::from_millis(100));
+        assert_eq!(policy.delay_before(3), Duration::from_millis(200));
+        assert_eq!(policy.delay_before(4), Duration::from_millis(400));
+    }
+}
@@ -1,18 +1,126 @@
-use crate::commands::run_capture;
+use std::fs;
+use std::path::Path;
 
-#[derive(Debug)]
+use crate::backlight::{self, Backlight};
+use crate::commands::CommandRunner;
+use crate::ddc::{Capabilities, classify_error};
+use crate::edid::{self, DRM_CLASS, Edid};
+use crate::error::Error;
+
+#[derive(Debug, Clone)]
 pub struct Display {
+    /// ddcutil's display number, which only holds until the next replug.
     pub id: String,
     pub name: String,
     pub drm_connector: String,
+    pub i2c_bus: Option<String>,
+    /// `/sys/class/backlight` device of a laptop panel, which is driven
+    /// through it instead of DDC.
+    pub backlight: Option<String>,
+    pub edid: Edid,
+    /// Stable identity, see [`Edid::key`].
+    pub key: String,
+    pub capabilities: Option<Capabilities>,
+}
+
+impl Display {
+    /// Key used to store per-monitor settings and to address the monitor
+    /// across processes.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Whether `wanted` names this display: by key, ddcutil number or model.
+    pub fn matches(&self, wanted: &str) -> bool {
+        self.key == wanted || self.id == wanted || self.name.eq_ignore_ascii_case(wanted)
+    }
+
+    /// Whether the monitor advertised a VCP feature in its capabilities.
+    pub fn supports(&self, code: u8) -> bool {
+        self.capabilities.as_ref().is_some_and(|c| c.supports(code))
+    }
+}
+
+pub fn enumerate_displays(runner: &dyn CommandRunner) -> Result<Vec<Display>, Error> {
+    let sysfs = runner.sysfs();
+    let panels = backlight::enumerate(&sysfs);
+
+    let mut displays = match runner.run_capture("ddcutil", &["detect"]) {
+        Ok(output) => parse_ddcutil_detect(&output),
+        // A laptop on its own has no DDC monitors to find, and may not
+        // have ddcutil at all
+        Err(_) if !panels.is_empty() => Vec::new(),
+        Err(e) => return Err(classify_error(e, None)),
+    };
+    // The backlight is the better way to a panel that also answers DDC
+    displays.retain(|d| !panels.iter().any(|p| p.connector == d.drm_connector));
+    displays.extend(panels.into_iter().map(panel_display));
+
+    read_edids(&mut displays, &sysfs.join(DRM_CLASS));
+    assign_keys(&mut displays);
+    Ok(displays)
 }
 
-pub fn enumerate_displays() -> Vec<Display> {
-    let Ok(output) = run_capture("ddcutil", &["detect"]) else {
+fn panel_display(panel: Backlight) -> Display {
+    Display {
+        id: panel.device.clone(),
+        name: "Built-in display".to_string(),
+        drm_connector: panel.connector,
+        i2c_bus: None,
+        backlight: Some(panel.device),
+        edid: Edid::default(),
+        key: String::new(),
+        capabilities: None,
+    }
+}
+
+/// Every connector under `root` with a monitor on it, e.g. `card1-DP-1`.
+pub fn connected_edids(root: &Path) -> Vec<(String, Edid)> {
+    let Ok(entries) = fs::read_dir(root) else {
         return Vec::new();
     };
+    let mut connected: Vec<(String, Edid)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let connector = entry.file_name().into_string().ok()?;
+            // card1 is the card itself, card1-DP-1 one of its connectors
+            connector.starts_with("card").then_some(())?;
+            connector.contains('-').then_some(())?;
+            let edid = edid::read_connector(root, &connector).ok()?;
+            Some((connector, edid))
+        })
+        .collect();
+    connected.sort_by(|a, b| a.0.cmp(&b.0));
+    connected
+}
+
+/// Replace what ddcutil summarised with the kernel's own EDID, and trust
+/// the EDID over ddcutil about which connector a monitor is on.
+fn read_edids(displays: &mut [Display], root: &Path) {
+    let connected = connected_edids(root);
+    if connected.is_empty() {
+        return;
+    }
+
+    for display in displays.iter_mut() {
+        let wanted = display.edid.key();
+        let mut same = connected
+            .iter()
+            .filter(|(_, e)| wanted.is_some() && e.key() == wanted);
+        let found = match (same.next(), same.next()) {
+            (Some(only), None) => Some(only),
+            // Identical monitors: only the connector can tell them apart
+            _ => connected.iter().find(|(c, _)| *c == display.drm_connector),
+        };
 
-    parse_ddcutil_detect(&output)
+        if let Some((connector, edid)) = found {
+            display.drm_connector = connector.clone();
+            if !edid.model.is_empty() {
+                display.name = edid.model.clone();
+            }
+            display.edid = edid.clone();
+        }
+    }
 }
 
 fn save_display(
@@ -20,10 +128,42 @@ fn save_display(
     id: Option<String>,
     name: Option<String>,
     connector: Option<String>,
+    i2c_bus: Option<String>,
+    edid: Edid,
 ) {
     if let (Some(id), Some(connector)) = (id, connector) {
         let name = name.unwrap_or_else(|| format!("Display {}", id));
-        displays.push(Display { id, name, drm_connector: connector });
+        displays.push(Display {
+            id,
+            name,
+            drm_connector: connector,
+            i2c_bus,
+            backlight: None,
+            edid,
+            key: String::new(),
+            capabilities: None,
+        });
+    }
+}
+
+/// Key each display by its EDID. Identical monitors without serial numbers
+/// share an EDID key; tell them apart by connector.
+fn assign_keys(displays: &mut [Display]) {
+    for display in displays.iter_mut() {
+        // Without an EDID the port is the best we have
+        display.key = display
+            .edid
+            .key()
+            .unwrap_or_else(|| display.drm_connector.clone());
+    }
+    for i in 0..displays.len() {
+        let shared = displays.iter().enumerate().any(|(j, d)| {
+            j != i && d.edid.key().is_some() && d.edid.key() == displays[i].edid.key()
+        });
+        if shared {
+            let connector = displays[i].drm_connector.clone();
+            displays[i].key = format!("{}@{}", displays[i].key, connector);
+        }
     }
 }
 
@@ -32,15 +172,66 @@ fn parse_ddcutil_detect(output: &str) -> Vec<Display> {
     let mut current_id: Option<String> = None;
     let mut current_name: Option<String> = None;
     let mut current_connector: Option<String> = None;
+    let mut current_bus: Option<String> = None;
+    let mut current_edid = Edid::default();
+    let mut binary_serial: Option<String> = None;
 
     for line in output.lines() {
         let trimmed = line.trim();
 
-        if let Some(new_id) = trimmed.strip_prefix("Display ").filter(|s| !s.contains("not found")) {
-            save_display(&mut displays, current_id.take(), current_name.take(), current_connector.take());
+        if let Some(new_id) = trimmed
+            .strip_prefix("Display ")
+            .filter(|s| !s.contains("not found"))
+        {
+            current_edid.serial = current_edid.serial.or(binary_serial.take());
+            save_display(
+                &mut displays,
+                current_id.take(),
+                current_name.take(),
+                current_connector.take(),
+                current_bus.take(),
+                std::mem::take(&mut current_edid),
+            );
             current_id = Some(new_id.to_string());
-            current_name = None;
-            current_connector = None;
+        }
+
+        // "Mfg id: DEL - Dell Inc."
+        if let Some(mfg) = trimmed.strip_prefix("Mfg id:") {
+            current_edid.manufacturer = mfg
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+        }
+
+        // "Product code: 16614  (0x40E6)"
+        if let Some(code) = trimmed.strip_prefix("Product code:") {
+            current_edid.product_code = code
+                .split_whitespace()
+                .next()
+                .and_then(|c| c.parse().ok())
+                .unwrap_or_default();
+        }
+
+        // "Serial number: ABC123", empty on many monitors
+        if let Some(serial) = trimmed.strip_prefix("Serial number:") {
+            current_edid.serial = Some(serial.trim().to_string()).filter(|s| !s.is_empty());
+        }
+
+        // "Binary serial number: 123456 (0x0001E240)", zero when unset
+        if let Some(serial) = trimmed.strip_prefix("Binary serial number:") {
+            binary_serial = serial
+                .split_whitespace()
+                .next()
+                .filter(|s| s.parse::<u32>().is_ok_and(|n| n != 0))
+                .map(str::to_string);
+        }
+
+        // "I2C bus:  /dev/i2c-7"
+        if trimmed.starts_with("I2C bus:") {
+            current_bus = trimmed
+                .strip_prefix("I2C bus:")
+                .map(|s| s.trim().to_string());
         }
 
         // "DRM connector: card1-DP-1"
@@ -52,9 +243,8 @@ fn parse_ddcutil_detect(output: &str) -> Vec<Display> {
 
         // "Model: DELL U2715H"
         if trimmed.starts_with("Model:") {
-            current_name = trimmed
-                .strip_prefix("Model:")
-                .map(|s| s.trim().to_string());
+            current_name = trimmed.strip_prefix("Model:").map(|s| s.trim().to_string());
+            current_edid.model = current_name.clone().unwrap_or_default();
         }
 
         // Fallback to monitor name if Model not found
@@ -66,7 +256,16 @@ fn parse_ddcutil_detect(output: &str) -> Vec<Display> {
         }
     }
 
-    save_display(&mut displays, current_id, current_name, current_connector);
+    current_edid.serial = current_edid.serial.or(binary_serial);
+    save_display(
+        &mut displays,
+        current_id,
+        current_name,
+        current_connector,
+        current_bus,
+        current_edid,
+    );
+    assign_keys(&mut displays);
 
     displays
 }
@@ -108,9 +307,126 @@ Display 2
         assert_eq!(displays[0].id, "1");
         assert_eq!(displays[0].name, "DELL U2715H");
         assert_eq!(displays[0].drm_connector, "card1-DP-1");
+        assert_eq!(displays[0].i2c_bus.as_deref(), Some("/dev/i2c-7"));
+
+        assert_eq!(
+            displays[0].edid,
+            Edid {
+                manufacturer: "DEL".into(),
+                model: "DELL U2715H".into(),
+                product_code: 0x40E6,
+                serial: Some("ABC123".into()),
+                ..Edid::default()
+            }
+        );
+        assert_eq!(displays[0].key(), "DEL-40E6-ABC123");
 
         assert_eq!(displays[1].id, "2");
         assert_eq!(displays[1].name, "Samsung 27\"");
         assert_eq!(displays[1].drm_connector, "card1-HDMI-A-1");
+        assert_eq!(displays[1].key(), "SAM-04D2-XYZ789");
+    }
+
+    #[test]
+    fn test_keys_without_serials() {
+        let output = r#"Display 1
+   I2C bus:  /dev/i2c-4
+   DRM connector:           card0-DP-1
+   EDID synopsis:
+      Mfg id:               GSM - Goldstar Company Ltd
+      Model:                LG ULTRAGEAR
+      Product code:         30592  (0x7780)
+      Serial number:
+      Binary serial number: 0 (0x00000000)
+Display 2
+   I2C bus:  /dev/i2c-5
+   DRM connector:           card0-DP-2
+   EDID synopsis:
+      Mfg id:               GSM - Goldstar Company Ltd
+      Model:                LG ULTRAGEAR
+      Product code:         30592  (0x7780)
+      Serial number:
+      Binary serial number: 0 (0x00000000)
+Display 3
+   I2C bus:  /dev/i2c-6
+   DRM connector:           card0-HDMI-A-1
+   EDID synopsis:
+      Mfg id:               AUS - Asustek Computer Inc
+      Model:                VG248
+      Product code:         9346  (0x2482)
+      Serial number:
+      Binary serial number: 12345 (0x00003039)
+Display 4
+   I2C bus:  /dev/i2c-7
+   DRM connector:           card0-DP-3
+"#;
+
+        let keys: Vec<String> = parse_ddcutil_detect(output)
+            .into_iter()
+            .map(|d| d.key)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "GSM-7780@card0-DP-1",
+                "GSM-7780@card0-DP-2",
+                "AUS-2482-12345",
+                "card0-DP-3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_edids_from_sysfs() {
+        let output = r#"Display 1
+   I2C bus:  /dev/i2c-7
+   DRM connector:           card1-DP-1
+   EDID synopsis:
+      Mfg id:               DEL - Dell Inc.
+      Model:                DELL U2715H
+      Product code:         16614  (0x40E6)
+      Serial number:        ABC123
+Display 2
+   I2C bus:  /dev/i2c-8
+   DRM connector:           card1-HDMI-A-1
+   EDID synopsis:
+      Mfg id:               GSM - Goldstar Company Ltd
+      Model:                LG
+      Product code:         30592  (0x7780)
+"#;
+        let root = tempfile::tempdir().unwrap();
+        let plug = |connector: &str, blob: &[u8]| {
+            fs::create_dir_all(root.path().join(connector)).unwrap();
+            fs::write(root.path().join(connector).join("edid"), blob).unwrap();
+        };
+        // The Dell is really on DP-2, and DP-1 is empty
+        plug("card1-DP-1", b"");
+        plug(
+            "card1-DP-2",
+            include_bytes!("../tests/fixtures/dell-u2715h.edid"),
+        );
+        plug(
+            "card1-HDMI-A-1",
+            include_bytes!("../tests/fixtures/lg-ultragear.edid"),
+        );
+        fs::create_dir_all(root.path().join("card1")).unwrap();
+
+        let mut displays = parse_ddcutil_detect(output);
+        read_edids(&mut displays, root.path());
+        assign_keys(&mut displays);
+
+        assert_eq!(displays[0].drm_connector, "card1-DP-2");
+        assert_eq!(displays[0].key(), "DEL-40E6-ABC123");
+        assert_eq!(displays[0].edid.size_cm, Some((60, 34)));
+        assert_eq!(displays[1].drm_connector, "card1-HDMI-A-1");
+        assert_eq!(displays[1].name, "LG ULTRAGEAR");
+        assert!(displays[1].edid.supports_hdr());
+        assert_eq!(
+            connected_edids(root.path())
+                .iter()
+                .map(|(c, _)| c.as_str())
+                .collect::<Vec<_>>(),
+            ["card1-DP-2", "card1-HDMI-A-1"]
+        );
     }
 }
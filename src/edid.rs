@@ -0,0 +1,381 @@
+//! Decoding of raw EDID blobs, as the kernel exposes them in
+//! `/sys/class/drm/<connector>/edid` and xrandr prints them with `--verbose`.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Connectors and their EDIDs, relative to where sysfs is mounted.
+pub const DRM_CLASS: &str = "class/drm";
+
+const BLOCK: usize = 128;
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const DESCRIPTORS: [usize; 4] = [54, 72, 90, 108];
+const CTA_EXTENSION: u8 = 0x02;
+
+/// What a monitor's EDID says about it. The identifying fields are always
+/// there; the rest only when the EDID was read from the blob itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edid {
+    /// Three-letter PNP manufacturer id, e.g. `DEL`.
+    pub manufacturer: String,
+    pub model: String,
+    pub product_code: u16,
+    /// The serial number string, or the binary serial number when the
+    /// monitor has no string.
+    pub serial: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_cm: Option<(u8, u8)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_mode: Option<Mode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufactured: Option<Manufactured>,
+    /// Transfer functions from the HDR static metadata block, e.g. `PQ`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hdr: Vec<String>,
+    /// Extended colorimetry from the CTA-861 colorimetry block, e.g. `BT2020RGB`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colorimetry: Vec<String>,
+}
+
+/// The preferred timing from the first detailed timing descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mode {
+    pub width: u16,
+    pub height: u16,
+    pub refresh_hz: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manufactured {
+    pub year: u16,
+    /// `None` when the monitor only gives a year, or gives a model year.
+    pub week: Option<u8>,
+}
+
+impl Edid {
+    /// `DEL-40E6-ABC123`: the same monitor gets the same key whichever
+    /// port, dock or bus it turns up on. `None` without an EDID.
+    pub fn key(&self) -> Option<String> {
+        if self.manufacturer.is_empty() {
+            return None;
+        }
+
+        let mut key = format!("{}-{:04X}", self.manufacturer, self.product_code);
+        if let Some(serial) = &self.serial {
+            key.push('-');
+            key.push_str(serial);
+        }
+        Some(key)
+    }
+
+    pub fn supports_hdr(&self) -> bool {
+        self.hdr.iter().any(|eotf| eotf == "PQ" || eotf == "HLG")
+    }
+
+    /// `2560x1440@60Hz, 27", 2016, HDR`, empty when only the identifying
+    /// fields are known.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(mode) = self.native_mode {
+            parts.push(format!(
+                "{}x{}@{}Hz",
+                mode.width, mode.height, mode.refresh_hz
+            ));
+        }
+        if let Some((width, height)) = self.size_cm {
+            let diagonal = f64::from(width).hypot(f64::from(height)) / 2.54;
+            parts.push(format!("{:.0}\"", diagonal));
+        }
+        if let Some(manufactured) = self.manufactured {
+            parts.push(manufactured.year.to_string());
+        }
+        if self.supports_hdr() {
+            parts.push("HDR".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Read the EDID of a DRM connector like `card1-DP-1` under `root`.
+pub fn read_connector(root: &Path, connector: &str) -> Result<Edid, Error> {
+    let path = root.join(connector).join("edid");
+    let bytes = fs::read(&path).map_err(|e| Error::Failed(format!("{}: {}", path.display(), e)))?;
+    parse(&bytes)
+}
+
+/// Decode an EDID: the base block and any CTA-861 extension blocks.
+pub fn parse(bytes: &[u8]) -> Result<Edid, Error> {
+    let invalid = |why: &str| Error::Parse(format!("EDID {}", why));
+
+    // An empty file is how sysfs says nothing is plugged in
+    if bytes.len() < BLOCK {
+        return Err(invalid("is shorter than one block"));
+    }
+    if bytes[..8] != HEADER {
+        return Err(invalid("has no header"));
+    }
+    if !checksum_ok(&bytes[..BLOCK]) {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let mut edid = Edid {
+        manufacturer: pnp_id(u16::from_be_bytes([bytes[8], bytes[9]]))
+            .ok_or_else(|| invalid("has a bad manufacturer id"))?,
+        product_code: u16::from_le_bytes([bytes[10], bytes[11]]),
+        ..Edid::default()
+    };
+    let binary_serial = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+
+    edid.manufactured = match (bytes[16], bytes[17]) {
+        (_, 0) => None,
+        (week @ 1..=54, year) => Some(Manufactured {
+            year: 1990 + u16::from(year),
+            week: Some(week),
+        }),
+        (_, year) => Some(Manufactured {
+            year: 1990 + u16::from(year),
+            week: None,
+        }),
+    };
+    // Zero means the size is unknown or the display is a projector
+    edid.size_cm = Some((bytes[21], bytes[22])).filter(|(w, h)| *w != 0 && *h != 0);
+
+    for offset in DESCRIPTORS {
+        let descriptor = &bytes[offset..offset + 18];
+        if descriptor[0] != 0 || descriptor[1] != 0 {
+            if edid.native_mode.is_none() {
+                edid.native_mode = detailed_timing(descriptor);
+            }
+            continue;
+        }
+        match descriptor[3] {
+            0xFC => edid.model = descriptor_text(descriptor),
+            0xFF => edid.serial = Some(descriptor_text(descriptor)).filter(|s| !s.is_empty()),
+            _ => {}
+        }
+    }
+    if edid.serial.is_none() && binary_serial != 0 {
+        edid.serial = Some(binary_serial.to_string());
+    }
+
+    // Extensions that are missing or corrupt only cost the extras
+    let extensions = usize::from(bytes[126]);
+    for block in bytes[BLOCK..].chunks_exact(BLOCK).take(extensions) {
+        if block[0] == CTA_EXTENSION && checksum_ok(block) {
+            parse_cta(block, &mut edid);
+        }
+    }
+
+    Ok(edid)
+}
+
+/// Decode the hex dump xrandr prints under `EDID:`.
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+fn checksum_ok(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) == 0
+}
+
+/// Three letters packed five bits each, `A` = 1.
+fn pnp_id(packed: u16) -> Option<String> {
+    [10, 5, 0]
+        .iter()
+        .map(|shift| match (packed >> shift) & 0x1F {
+            letter @ 1..=26 => Some(char::from(b'A' + letter as u8 - 1)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn descriptor_text(descriptor: &[u8]) -> String {
+    let text = &descriptor[5..18];
+    let end = text.iter().position(|b| *b == b'\n').unwrap_or(text.len());
+    String::from_utf8_lossy(&text[..end]).trim().to_string()
+}
+
+fn detailed_timing(d: &[u8]) -> Option<Mode> {
+    let clock_hz = u64::from(u16::from_le_bytes([d[0], d[1]])) * 10_000;
+    let width = u16::from(d[2]) | (u16::from(d[4] & 0xF0) << 4);
+    let h_blank = u16::from(d[3]) | (u16::from(d[4] & 0x0F) << 8);
+    let height = u16::from(d[5]) | (u16::from(d[7] & 0xF0) << 4);
+    let v_blank = u16::from(d[6]) | (u16::from(d[7] & 0x0F) << 8);
+
+    let total = u64::from(width + h_blank) * u64::from(height + v_blank);
+    if width == 0 || height == 0 || total == 0 {
+        return None;
+    }
+    Some(Mode {
+        width,
+        height,
+        refresh_hz: ((clock_hz + total / 2) / total) as u16,
+    })
+}
+
+/// Walk the data block collection of a CTA-861 extension.
+fn parse_cta(block: &[u8], edid: &mut Edid) {
+    let end = usize::from(block[2]).clamp(4, 127);
+    let mut offset = 4;
+
+    while offset < end {
+        let tag = block[offset] >> 5;
+        let len = usize::from(block[offset] & 0x1F);
+        let Some(payload) = block
+            .get(offset + 1..offset + 1 + len)
+            .filter(|_| offset + 1 + len <= end)
+        else {
+            return;
+        };
+        offset += 1 + len;
+
+        // Tag 7 blocks carry their real tag in the first payload byte
+        if tag != 7 || payload.len() < 2 {
+            continue;
+        }
+        match payload[0] {
+            0x05 => {
+                edid.colorimetry = flags(payload[1], &COLORIMETRY)
+                    .chain(
+                        payload
+                            .get(2)
+                            .filter(|b| *b & 0x80 != 0)
+                            .map(|_| "DCI-P3".to_string()),
+                    )
+                    .collect()
+            }
+            0x06 => edid.hdr = flags(payload[1], &EOTFS).collect(),
+            _ => {}
+        }
+    }
+}
+
+const COLORIMETRY: [&str; 8] = [
+    "xvYCC601",
+    "xvYCC709",
+    "sYCC601",
+    "opYCC601",
+    "opRGB",
+    "BT2020cYCC",
+    "BT2020YCC",
+    "BT2020RGB",
+];
+const EOTFS: [&str; 4] = ["SDR", "HDR", "PQ", "HLG"];
+
+fn flags<'a>(bits: u8, names: &'a [&str]) -> impl Iterator<Item = String> + 'a {
+    names
+        .iter()
+        .enumerate()
+        .filter(move |(bit, _)| bits & (1 << bit) != 0)
+        .map(|(_, name)| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELL: &[u8] = include_bytes!("../tests/fixtures/dell-u2715h.edid");
+    const LG: &[u8] = include_bytes!("../tests/fixtures/lg-ultragear.edid");
+
+    #[test]
+    fn test_parse_base_block() {
+        let edid = parse(DELL).unwrap();
+
+        assert_eq!(edid.manufacturer, "DEL");
+        assert_eq!(edid.model, "DELL U2715H");
+        assert_eq!(edid.product_code, 0x40E6);
+        assert_eq!(edid.serial.as_deref(), Some("ABC123"));
+        assert_eq!(edid.key().as_deref(), Some("DEL-40E6-ABC123"));
+        assert_eq!(edid.size_cm, Some((60, 34)));
+        assert_eq!(
+            edid.native_mode,
+            Some(Mode {
+                width: 2560,
+                height: 1440,
+                refresh_hz: 60
+            })
+        );
+        assert_eq!(
+            edid.manufactured,
+            Some(Manufactured {
+                year: 2016,
+                week: Some(52)
+            })
+        );
+        assert!(edid.hdr.is_empty());
+        assert_eq!(edid.summary(), "2560x1440@60Hz, 27\", 2016");
+    }
+
+    #[test]
+    fn test_parse_cta_extension() {
+        let edid = parse(LG).unwrap();
+
+        assert_eq!(edid.key().as_deref(), Some("GSM-7780"));
+        assert_eq!(edid.model, "LG ULTRAGEAR");
+        assert_eq!(
+            edid.native_mode,
+            Some(Mode {
+                width: 3840,
+                height: 2160,
+                refresh_hz: 60
+            })
+        );
+        assert_eq!(edid.hdr, ["SDR", "PQ", "HLG"]);
+        assert_eq!(edid.colorimetry, ["BT2020YCC", "BT2020RGB", "DCI-P3"]);
+        assert_eq!(edid.summary(), "3840x2160@60Hz, 32\", 2020, HDR");
+    }
+
+    #[test]
+    fn test_reject_broken_blobs() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&DELL[..100]).is_err());
+
+        let mut corrupt = DELL.to_vec();
+        corrupt[20] ^= 0xFF;
+        assert!(parse(&corrupt).is_err());
+
+        // A corrupt extension only loses the extension
+        let mut corrupt = LG.to_vec();
+        corrupt[200] ^= 0xFF;
+        let edid = parse(&corrupt).unwrap();
+        assert_eq!(edid.model, "LG ULTRAGEAR");
+        assert!(edid.hdr.is_empty());
+    }
+
+    #[test]
+    fn test_read_connector_and_hex() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("card1-DP-1")).unwrap();
+        fs::write(root.path().join("card1-DP-1/edid"), DELL).unwrap();
+        fs::create_dir_all(root.path().join("card1-HDMI-A-1")).unwrap();
+        fs::write(root.path().join("card1-HDMI-A-1/edid"), b"").unwrap();
+
+        assert_eq!(
+            read_connector(root.path(), "card1-DP-1").unwrap().model,
+            "DELL U2715H"
+        );
+        assert!(read_connector(root.path(), "card1-HDMI-A-1").is_err());
+        assert!(read_connector(root.path(), "card1-DP-2").is_err());
+
+        let hex: String = DELL
+            .chunks(16)
+            .map(|row| {
+                let line: String = row.iter().map(|b| format!("{:02x}", b)).collect();
+                format!("\t\t{}\n", line)
+            })
+            .collect();
+        assert_eq!(from_hex(&hex).as_deref(), Some(DELL));
+        assert_eq!(from_hex("0g"), None);
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+/// Failures from talking to monitors and the tools behind them, split by
+/// what the user can do about each.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "kebab-case")]
+pub enum Error {
+    /// An external program isn't installed.
+    ToolMissing(String),
+    /// The user lacks access to a device, usually `/dev/i2c-N`.
+    PermissionDenied(String),
+    /// The monitor didn't answer or its reply was garbled.
+    NotResponding(String),
+    /// The monitor doesn't implement this VCP feature.
+    UnsupportedFeature(u8),
+    /// A tool or the monitor returned output we couldn't make sense of.
+    Parse(String),
+    /// A command or the monitor took too long; names what was waited on.
+    Timeout(String),
+    /// The operation was abandoned, e.g. because the user moved on.
+    Cancelled,
+    /// Anything else, such as a command failing for an unrecognised reason.
+    Failed(String),
+    /// The command line didn't make sense; carries the usage text.
+    Usage(String),
+}
+
+impl Error {
+    /// Short stable name for scripts and machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::ToolMissing(_) => "tool-missing",
+            Error::PermissionDenied(_) => "permission-denied",
+            Error::NotResponding(_) => "not-responding",
+            Error::UnsupportedFeature(_) => "unsupported-feature",
+            Error::Parse(_) => "parse",
+            Error::Timeout(_) => "timeout",
+            Error::Cancelled => "cancelled",
+            Error::Failed(_) => "failed",
+            Error::Usage(_) => "usage",
+        }
+    }
+
+    /// Whether trying the same operation again might succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Error::NotResponding(_) | Error::Timeout(_) | Error::Failed(_)
+        )
+    }
+
+    /// Process exit status the CLI uses for this kind of failure.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Failed(_) => 1,
+            Error::Usage(_) => 2,
+            Error::ToolMissing(_) => 3,
+            Error::PermissionDenied(_) => 4,
+            Error::NotResponding(_) => 5,
+            Error::UnsupportedFeature(_) => 6,
+            Error::Parse(_) => 7,
+            Error::Timeout(_) => 8,
+            Error::Cancelled => 9,
+        }
+    }
+
+    /// What the user can try next, when there's something better than
+    /// retrying.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Error::ToolMissing(tool) => Some(format!("Install {} with your package manager", tool)),
+            Error::PermissionDenied(_) => Some(
+                "Add yourself to the i2c group (sudo usermod -aG i2c $USER) and log in again"
+                    .into(),
+            ),
+            Error::NotResponding(_) => {
+                Some("Check that DDC/CI is enabled in the monitor's on-screen menu".into())
+            }
+            Error::UnsupportedFeature(_) => None,
+            Error::Parse(_) => None,
+            Error::Timeout(_) => Some("The monitor may be busy; try again in a moment".into()),
+            Error::Cancelled => None,
+            Error::Failed(_) => None,
+            Error::Usage(_) => None,
+        }
+    }
+
+    /// The message followed by the hint, for showing to a person.
+    pub fn explain(&self) -> String {
+        match self.hint() {
+            Some(hint) => format!("{}. {}", self, hint),
+            None => self.to_string(),
+        }
+    }
+
+    /// Map an I/O error from spawning a tool or opening a device to the
+    /// closest kind.
+    pub fn from_io(context: &str, e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => Error::ToolMissing(context.to_string()),
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(context.to_string()),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                Error::Timeout(format!("{}: {}", context, e))
+            }
+            _ => Error::Failed(format!("{}: {}", context, e)),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ToolMissing(tool) => write!(f, "{} not found", tool),
+            Error::PermissionDenied(what) => write!(f, "permission denied on {}", what),
+            Error::NotResponding(detail) => write!(f, "monitor not responding: {}", detail),
+            Error::UnsupportedFeature(code) => {
+                write!(f, "VCP feature {:02X} is not supported", code)
+            }
+            Error::Parse(detail) => write!(f, "could not parse {}", detail),
+            Error::Timeout(what) => write!(f, "timed out: {}", what),
+            Error::Cancelled => write!(f, "cancelled"),
+            Error::Failed(detail) | Error::Usage(detail) => write!(f, "{}", detail),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors_map_to_kinds() {
+        let denied = Error::from_io(
+            "/dev/i2c-7",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(denied, Error::PermissionDenied("/dev/i2c-7".into()));
+        assert_eq!(denied.to_string(), "permission denied on /dev/i2c-7");
+        assert!(denied.hint().is_some_and(|h| h.contains("i2c group")));
+
+        let missing = Error::from_io("ddcutil", io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(missing.kind(), "tool-missing");
+        assert_eq!(missing.exit_code(), 3);
+
+        // Nothing says the monitor is at fault
+        let broken = Error::from_io("ddcutil", io::Error::from(io::ErrorKind::BrokenPipe));
+        assert_eq!(broken.kind(), "failed");
+    }
+}
@@ -3,15 +3,46 @@ mod x11;
 
 use std::collections::HashMap;
 use std::env;
+use std::sync::Arc;
+
+use crate::commands::CommandRunner;
+use crate::display::Display;
+use crate::edid::Edid;
+use crate::error::Error;
 
-pub use x11::X11Backend;
 pub use wayland::WaylandBackend;
+pub use x11::X11Backend;
+
+/// Where an output sits on the desktop, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
 
 pub trait GammaBackend: Send + Sync {
     fn name(&self) -> &'static str;
-    fn enumerate_outputs(&self) -> Result<HashMap<String, String>, String>;
-    fn apply_dimming(&self, output: &str, value: u8) -> Result<(), String>;
-    fn reset(&self, output: &str) -> Result<(), String>;
+    fn enumerate_outputs(&self) -> Result<HashMap<String, String>, Error>;
+    /// Desktop position of each active output, where the session exposes it.
+    fn output_geometry(&self) -> Result<HashMap<String, OutputGeometry>, Error> {
+        Ok(HashMap::new())
+    }
+    /// EDID of each connected output, where the session exposes it.
+    fn output_edids(&self) -> Result<HashMap<String, Edid>, Error> {
+        Ok(HashMap::new())
+    }
+    fn apply_dimming(&self, output: &str, value: u8) -> Result<(), Error>;
+    /// The dimming an output is at now, where the session can tell.
+    fn current_dimming(&self, output: &str) -> Result<u8, Error> {
+        Err(Error::Failed(format!(
+            "{} can't read back the dimming of {}",
+            self.name(),
+            output
+        )))
+    }
+    fn reset(&self, output: &str) -> Result<(), Error>;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -41,9 +72,12 @@ pub fn detect_session() -> SessionType {
     SessionType::Unknown
 }
 
-pub fn create_backend() -> Option<Box<dyn GammaBackend>> {
-    match detect_session() {
-        SessionType::X11 => X11Backend::new().map(|b| Box::new(b) as Box<dyn GammaBackend>),
+pub fn create_backend(
+    session: SessionType,
+    runner: Arc<dyn CommandRunner>,
+) -> Option<Box<dyn GammaBackend>> {
+    match session {
+        SessionType::X11 => X11Backend::new(runner).map(|b| Box::new(b) as Box<dyn GammaBackend>),
         SessionType::Wayland => WaylandBackend::new().map(|b| Box::new(b) as Box<dyn GammaBackend>),
         SessionType::Unknown => None,
     }
@@ -63,7 +97,10 @@ fn parse_connector_type_and_index(parts: &[&str]) -> Option<(String, i32)> {
 }
 
 /// Map a DRM connector name (e.g., "card1-DP-1") to an xrandr output name (e.g., "DP-0")
-pub fn map_drm_to_xrandr(drm_connector: &str, xrandr_outputs: &HashMap<String, String>) -> Option<String> {
+pub fn map_drm_to_xrandr(
+    drm_connector: &str,
+    xrandr_outputs: &HashMap<String, String>,
+) -> Option<String> {
     // Extract connector type and index from DRM name
     // Format: "card{N}-{TYPE}-{INDEX}" e.g., "card1-DP-1", "card0-HDMI-A-1"
     let parts: Vec<&str> = drm_connector.split('-').collect();
@@ -89,6 +126,35 @@ pub fn map_drm_to_xrandr(drm_connector: &str, xrandr_outputs: &HashMap<String, S
     }
 }
 
+/// The output showing each display, keyed by display id. A display is
+/// matched to the output with the same EDID where the session exposes
+/// EDIDs, and by connector name otherwise.
+pub fn map_outputs(
+    backend: &dyn GammaBackend,
+    displays: &[Display],
+) -> Result<HashMap<String, String>, Error> {
+    let outputs = backend.enumerate_outputs()?;
+    let edids = backend.output_edids().unwrap_or_default();
+
+    Ok(displays
+        .iter()
+        .filter_map(|display| {
+            let output = output_with_edid(&display.edid, &edids)
+                .or_else(|| map_drm_to_xrandr(&display.drm_connector, &outputs))?;
+            Some((display.id.clone(), output))
+        })
+        .collect())
+}
+
+fn output_with_edid(edid: &Edid, edids: &HashMap<String, Edid>) -> Option<String> {
+    let key = edid.key()?;
+    let mut same = edids.iter().filter(|(_, e)| e.key().as_ref() == Some(&key));
+    match (same.next(), same.next()) {
+        (Some((output, _)), None) => Some(output.clone()),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,4 +178,55 @@ mod tests {
         let result = map_drm_to_xrandr("card0-HDMI-A-1", &outputs);
         assert_eq!(result, Some("HDMI-A-0".to_string()));
     }
+
+    struct EdidBackend;
+
+    impl GammaBackend for EdidBackend {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+        fn enumerate_outputs(&self) -> Result<HashMap<String, String>, Error> {
+            Ok(["DP-0", "DP-1", "HDMI-A-0"]
+                .map(|o| (o.to_string(), "connected".to_string()))
+                .into())
+        }
+        fn output_edids(&self) -> Result<HashMap<String, Edid>, Error> {
+            let dell = crate::edid::parse(include_bytes!("../../tests/fixtures/dell-u2715h.edid"))?;
+            Ok(HashMap::from([("DP-1".to_string(), dell)]))
+        }
+        fn apply_dimming(&self, _output: &str, _value: u8) -> Result<(), Error> {
+            Ok(())
+        }
+        fn reset(&self, _output: &str) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_map_outputs_by_edid() {
+        let display = |id: &str, connector: &str, manufacturer: &str, serial: &str| Display {
+            id: id.into(),
+            name: String::new(),
+            drm_connector: connector.into(),
+            i2c_bus: None,
+            backlight: None,
+            edid: Edid {
+                manufacturer: manufacturer.into(),
+                product_code: 0x40E6,
+                serial: Some(serial.into()),
+                ..Edid::default()
+            },
+            key: String::new(),
+            capabilities: None,
+        };
+        let displays = [
+            // The name alone would guess DP-0
+            display("1", "card1-DP-1", "DEL", "ABC123"),
+            display("2", "card1-HDMI-A-1", "SAM", "XYZ789"),
+        ];
+
+        let outputs = map_outputs(&EdidBackend, &displays).unwrap();
+        assert_eq!(outputs["1"], "DP-1");
+        assert_eq!(outputs["2"], "HDMI-A-0");
+    }
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::error::Error;
 use crate::gamma::GammaBackend;
 
 pub struct WaylandBackend;
@@ -18,15 +19,27 @@ impl GammaBackend for WaylandBackend {
         "Wayland"
     }
 
-    fn enumerate_outputs(&self) -> Result<HashMap<String, String>, String> {
-        Err("Wayland gamma control not yet implemented".to_string())
+    fn enumerate_outputs(&self) -> Result<HashMap<String, String>, Error> {
+        Err(Error::Failed(
+            "Wayland gamma control not yet implemented".into(),
+        ))
     }
 
-    fn apply_dimming(&self, _output: &str, _value: u8) -> Result<(), String> {
-        Err("Wayland gamma control not yet implemented".to_string())
+    fn apply_dimming(&self, _output: &str, _value: u8) -> Result<(), Error> {
+        Err(Error::Failed(
+            "Wayland gamma control not yet implemented".into(),
+        ))
     }
 
-    fn reset(&self, _output: &str) -> Result<(), String> {
-        Err("Wayland gamma control not yet implemented".to_string())
+    /// Compositors drop a client's gamma ramps when it exits, and this one
+    /// never sets any, so no output is dimmed.
+    fn current_dimming(&self, _output: &str) -> Result<u8, Error> {
+        Ok(100)
+    }
+
+    fn reset(&self, _output: &str) -> Result<(), Error> {
+        Err(Error::Failed(
+            "Wayland gamma control not yet implemented".into(),
+        ))
     }
 }
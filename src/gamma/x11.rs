@@ -1,14 +1,19 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::commands::{command_exists, run, run_capture};
-use crate::gamma::GammaBackend;
+use crate::commands::CommandRunner;
+use crate::edid::{self, Edid};
+use crate::error::Error;
+use crate::gamma::{GammaBackend, OutputGeometry};
 
-pub struct X11Backend;
+pub struct X11Backend {
+    runner: Arc<dyn CommandRunner>,
+}
 
 impl X11Backend {
-    pub fn new() -> Option<Self> {
-        if command_exists("xrandr", &["--version"]) {
-            Some(Self)
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Option<Self> {
+        if runner.command_exists("xrandr", &["--version"]) {
+            Some(Self { runner })
         } else {
             None
         }
@@ -20,8 +25,8 @@ impl GammaBackend for X11Backend {
         "X11 (xrandr)"
     }
 
-    fn enumerate_outputs(&self) -> Result<HashMap<String, String>, String> {
-        let output = run_capture("xrandr", &["--query"])?;
+    fn enumerate_outputs(&self) -> Result<HashMap<String, String>, Error> {
+        let output = self.runner.run_capture("xrandr", &["--query"])?;
         let mut outputs = HashMap::new();
 
         for line in output.lines() {
@@ -36,12 +41,212 @@ impl GammaBackend for X11Backend {
         Ok(outputs)
     }
 
-    fn apply_dimming(&self, output: &str, value: u8) -> Result<(), String> {
+    fn output_geometry(&self) -> Result<HashMap<String, OutputGeometry>, Error> {
+        let output = self.runner.run_capture("xrandr", &["--query"])?;
+
+        // Only connected outputs that are switched on carry a geometry
+        Ok(output
+            .lines()
+            .filter(|line| line.split_whitespace().nth(1) == Some("connected"))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let name = parts.next()?;
+                let geometry = parts.find_map(parse_geometry)?;
+                Some((name.to_string(), geometry))
+            })
+            .collect())
+    }
+
+    fn output_edids(&self) -> Result<HashMap<String, Edid>, Error> {
+        let output = self.runner.run_capture("xrandr", &["--verbose"])?;
+        Ok(parse_edids(&output))
+    }
+
+    fn apply_dimming(&self, output: &str, value: u8) -> Result<(), Error> {
         let brightness = value as f32 / 100.0;
-        run("xrandr", &["--output", output, "--brightness", &brightness.to_string()])
+        self.runner.run(
+            "xrandr",
+            &["--output", output, "--brightness", &brightness.to_string()],
+        )
+    }
+
+    fn current_dimming(&self, output: &str) -> Result<u8, Error> {
+        let verbose = self.runner.run_capture("xrandr", &["--verbose"])?;
+        parse_brightness(&verbose, output).ok_or_else(|| {
+            Error::Parse(format!("no brightness for {} in xrandr --verbose", output))
+        })
+    }
+
+    fn reset(&self, output: &str) -> Result<(), Error> {
+        self.runner
+            .run("xrandr", &["--output", output, "--brightness", "1.0"])
+    }
+}
+
+/// Parse an xrandr mode and offset like `2560x1440+2560+0`.
+fn parse_geometry(token: &str) -> Option<OutputGeometry> {
+    let mut parts = token.split('+');
+    let (width, height) = parts.next()?.split_once('x')?;
+    let geometry = OutputGeometry {
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+        x: parts.next()?.parse().ok()?,
+        y: parts.next()?.parse().ok()?,
+    };
+
+    parts.next().is_none().then_some(geometry)
+}
+
+/// Pull the hex dump under each output's `EDID:` property out of
+/// `xrandr --verbose`.
+fn parse_edids(verbose: &str) -> HashMap<String, Edid> {
+    let mut dumps: Vec<(&str, String)> = Vec::new();
+    let mut output = None;
+    let mut in_edid = false;
+
+    for line in verbose.lines() {
+        let row = line.trim();
+        if !line.starts_with(char::is_whitespace) {
+            // "DP-2 connected primary ..." starts the next output
+            output = line.split_whitespace().next();
+            in_edid = false;
+        } else if row == "EDID:" {
+            if let Some(name) = output {
+                dumps.push((name, String::new()));
+                in_edid = true;
+            }
+        } else if in_edid && !row.is_empty() && row.bytes().all(|b| b.is_ascii_hexdigit()) {
+            if let Some((_, dump)) = dumps.last_mut() {
+                dump.push_str(row);
+            }
+        } else {
+            in_edid = false;
+        }
+    }
+
+    dumps
+        .into_iter()
+        .filter_map(|(name, dump)| {
+            Some((name.to_string(), edid::parse(&edid::from_hex(&dump)?).ok()?))
+        })
+        .collect()
+}
+
+/// The `Brightness: 0.70` property of one output in `xrandr --verbose`.
+fn parse_brightness(verbose: &str, output: &str) -> Option<u8> {
+    verbose
+        .lines()
+        .skip_while(|line| line.split_whitespace().next() != Some(output))
+        .skip(1)
+        .take_while(|line| line.starts_with(char::is_whitespace))
+        .find_map(|line| line.trim().strip_prefix("Brightness:"))
+        .and_then(|value| value.trim().parse::<f32>().ok())
+        .map(|brightness| (brightness * 100.0).round().clamp(0.0, 100.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ScriptedRunner;
+
+    const XRANDR_QUERY: &str = "Screen 0: minimum 8 x 8, current 5120 x 1440, maximum 32767 x 32767
+DP-0 connected primary 2560x1440+0+0 (normal left inverted right x axis y axis) 597mm x 336mm
+   2560x1440     59.95*+
+HDMI-0 disconnected (normal left inverted right x axis y axis)
+DP-2 connected 2560x1440+2560+0 (normal left inverted right x axis y axis) 597mm x 336mm
+";
+
+    #[test]
+    fn test_enumerate_outputs() {
+        let runner = ScriptedRunner::new().on("xrandr --query", XRANDR_QUERY);
+        let backend = X11Backend::new(Arc::new(runner)).unwrap();
+
+        let outputs = backend.enumerate_outputs().unwrap();
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs["DP-0"], "connected");
+        assert_eq!(outputs["HDMI-0"], "disconnected");
+    }
+
+    #[test]
+    fn test_output_geometry() {
+        let runner = ScriptedRunner::new().on("xrandr --query", XRANDR_QUERY);
+        let backend = X11Backend::new(Arc::new(runner)).unwrap();
+
+        let geometry = backend.output_geometry().unwrap();
+        assert_eq!(geometry.len(), 2);
+        assert_eq!(
+            geometry["DP-2"],
+            OutputGeometry {
+                x: 2560,
+                y: 0,
+                width: 2560,
+                height: 1440
+            }
+        );
+        assert_eq!(
+            parse_geometry("1920x1080+-1920+360").map(|g| g.x),
+            Some(-1920)
+        );
+        assert_eq!(parse_geometry("597mm"), None);
+    }
+
+    #[test]
+    fn test_apply_dimming_and_reset() {
+        let runner = Arc::new(
+            ScriptedRunner::new()
+                .on("xrandr --output DP-0 --brightness 0.7", "")
+                .on("xrandr --output DP-0 --brightness 1.0", ""),
+        );
+        let backend = X11Backend::new(runner.clone()).unwrap();
+
+        backend.apply_dimming("DP-0", 70).unwrap();
+        backend.reset("DP-0").unwrap();
+        assert_eq!(
+            runner.calls(),
+            vec![
+                "xrandr --output DP-0 --brightness 0.7",
+                "xrandr --output DP-0 --brightness 1.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_edids() {
+        let dump: String = include_bytes!("../../tests/fixtures/dell-u2715h.edid")
+            .chunks(16)
+            .map(|row| {
+                format!(
+                    "\t\t{}\n",
+                    row.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+                )
+            })
+            .collect();
+        let verbose = format!(
+            "Screen 0: minimum 8 x 8, current 2560 x 1440, maximum 32767 x 32767
+DP-0 disconnected (normal left inverted right x axis y axis)
+\tIdentifier: 0x1be
+DP-2 connected primary 2560x1440+0+0 (0x1c4) normal (normal left inverted right x axis y axis) 597mm x 336mm
+\tIdentifier: 0x1c0
+\tEDID: 
+{}\tBrightness: 0.70
+\tBorderDimensions: 4 
+  2560x1440 (0x1c4) 241.500MHz +HSync -VSync *current +preferred
+",
+            dump
+        );
+        let runner = ScriptedRunner::new().on("xrandr --verbose", &verbose);
+        let backend = X11Backend::new(Arc::new(runner)).unwrap();
+
+        let edids = backend.output_edids().unwrap();
+        assert_eq!(edids.len(), 1);
+        assert_eq!(edids["DP-2"].key().as_deref(), Some("DEL-40E6-ABC123"));
+        assert_eq!(backend.current_dimming("DP-2").unwrap(), 70);
+        assert!(backend.current_dimming("DP-0").is_err());
     }
 
-    fn reset(&self, output: &str) -> Result<(), String> {
-        run("xrandr", &["--output", output, "--brightness", "1.0"])
+    #[test]
+    fn test_missing_xrandr() {
+        let runner = ScriptedRunner::new().missing("xrandr");
+        assert!(X11Backend::new(Arc::new(runner)).is_none());
     }
 }
@@ -0,0 +1,191 @@
+//! Noticing monitors being plugged in and unplugged. The kernel keeps each
+//! DRM connector's `status` and `edid` current, so polling them is cheap and
+//! needs neither udev nor any extra permissions.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use eframe::egui;
+
+use crate::edid::DRM_CLASS;
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Every connector with a monitor on it and that monitor's raw EDID, so
+/// swapping one monitor for another on the same port is a change too.
+pub type Connected = BTreeMap<String, Vec<u8>>;
+
+pub fn connected(sysfs: &Path) -> Connected {
+    let Ok(entries) = fs::read_dir(sysfs.join(DRM_CLASS)) else {
+        return Connected::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            // Cards themselves have no status, only their connectors
+            let status = fs::read_to_string(entry.path().join("status")).ok()?;
+            (status.trim() == "connected").then_some(())?;
+            let connector = entry.file_name().into_string().ok()?;
+            Some((
+                connector,
+                fs::read(entry.path().join("edid")).unwrap_or_default(),
+            ))
+        })
+        .collect()
+}
+
+/// Connector changes reported by [`watch`]. Dropping it stops the thread
+/// doing the polling.
+pub struct Watcher {
+    pub changes: Receiver<Connected>,
+    _stop: Sender<()>,
+}
+
+/// Check the connectors every `interval` and send what is connected once
+/// it differs from `since` and has held for a whole interval. Monitors
+/// take a moment to answer DDC after being plugged in, and a loose cable
+/// shouldn't cause a rescan per bounce.
+pub fn watch(
+    sysfs: PathBuf,
+    interval: Duration,
+    since: Connected,
+    repaint: Option<egui::Context>,
+) -> Watcher {
+    let (sender, changes) = mpsc::channel();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    thread::spawn(move || {
+        let mut reported = since;
+        let mut previous = reported.clone();
+        // Nothing is ever sent on `stopped`; it disconnects with the watcher
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+            let current = connected(&sysfs);
+            if current == previous && current != reported {
+                if sender.send(current.clone()).is_err() {
+                    return;
+                }
+                if let Some(ctx) = &repaint {
+                    ctx.request_repaint();
+                }
+                reported = current.clone();
+            }
+            previous = current;
+        }
+    });
+
+    Watcher {
+        changes,
+        _stop: stop,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::backlight::tests::laptop_sysfs;
+
+    pub fn plug(root: &Path, connector: &str, edid: &[u8]) {
+        let dir = root.join(DRM_CLASS).join(connector);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("status"), "connected\n").unwrap();
+        fs::write(dir.join("edid"), edid).unwrap();
+    }
+
+    /// The first report matching `expected`; the watcher may catch a
+    /// fixture half-written.
+    fn wait_for(changes: &Receiver<Connected>, expected: impl Fn(&Connected) -> bool) -> Connected {
+        loop {
+            let seen = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+            if expected(&seen) {
+                return seen;
+            }
+        }
+    }
+
+    #[test]
+    fn test_connected() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(connected(root.path()).is_empty());
+
+        laptop_sysfs(root.path());
+        fs::create_dir_all(root.path().join(DRM_CLASS).join("card0")).unwrap();
+        assert_eq!(
+            connected(root.path()).keys().collect::<Vec<_>>(),
+            ["card0-eDP-1"]
+        );
+
+        plug(
+            root.path(),
+            "card0-DP-1",
+            include_bytes!("../tests/fixtures/dell-u2715h.edid"),
+        );
+        let now = connected(root.path());
+        assert_eq!(
+            now.keys().collect::<Vec<_>>(),
+            ["card0-DP-1", "card0-eDP-1"]
+        );
+        assert_eq!(now["card0-DP-1"].len(), 128);
+    }
+
+    #[test]
+    fn test_watch_reports_settled_changes() {
+        let root = tempfile::tempdir().unwrap();
+        laptop_sysfs(root.path());
+        let watcher = watch(
+            root.path().to_path_buf(),
+            Duration::from_millis(5),
+            connected(root.path()),
+            None,
+        );
+        let changes = &watcher.changes;
+
+        plug(
+            root.path(),
+            "card0-DP-1",
+            include_bytes!("../tests/fixtures/dell-u2715h.edid"),
+        );
+        wait_for(changes, |c| {
+            c.get("card0-DP-1").is_some_and(|edid| edid.len() == 128)
+        });
+
+        // Same port, different monitor
+        plug(
+            root.path(),
+            "card0-DP-1",
+            include_bytes!("../tests/fixtures/lg-ultragear.edid"),
+        );
+        wait_for(changes, |c| {
+            c.get("card0-DP-1").is_some_and(|edid| edid.len() == 256)
+        });
+
+        fs::write(
+            root.path().join(DRM_CLASS).join("card0-DP-1/status"),
+            "disconnected\n",
+        )
+        .unwrap();
+        let seen = wait_for(changes, |c| !c.contains_key("card0-DP-1"));
+        assert_eq!(seen.keys().collect::<Vec<_>>(), ["card0-eDP-1"]);
+    }
+
+    #[test]
+    fn test_watch_stops_when_dropped() {
+        let root = tempfile::tempdir().unwrap();
+        let Watcher { changes, _stop } = watch(
+            root.path().to_path_buf(),
+            Duration::from_secs(60),
+            Connected::new(),
+            None,
+        );
+
+        // Without waiting out the interval or for a change to report
+        drop(_stop);
+        assert_eq!(
+            changes.recv_timeout(Duration::from_secs(5)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
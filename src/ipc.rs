@@ -0,0 +1,449 @@
+//! Line-delimited JSON protocol spoken over the daemon's Unix socket, and a
+//! client for it.
+//!
+//! Each request is one JSON object on its own line, answered by one reply
+//! line. After `subscribe` the connection only carries `changed` events.
+
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ddc::{
+    Capabilities, Ddc, VCP_AUDIO_VOLUME, VCP_BRIGHTNESS, VCP_CONTRAST, VcpValue, WriteOutcome,
+};
+use crate::display::Display;
+use crate::edid::Edid;
+use crate::error::Error;
+
+/// Features addressable by name, all handled as percentages.
+pub const FEATURES: [(&str, u8); 3] = [
+    ("brightness", VCP_BRIGHTNESS),
+    ("contrast", VCP_CONTRAST),
+    ("volume", VCP_AUDIO_VOLUME),
+];
+
+/// A feature's VCP code from its name, or from the code itself written like
+/// `0x60` for features without one.
+pub fn feature_code(name: &str) -> Result<u8, Error> {
+    if let Some(hex) = name.strip_prefix("0x")
+        && let Ok(code) = u8::from_str_radix(hex, 16)
+    {
+        return Ok(code);
+    }
+    FEATURES
+        .iter()
+        .find(|(feature, _)| feature.eq_ignore_ascii_case(name))
+        .map(|&(_, code)| code)
+        .ok_or_else(|| {
+            let known: Vec<&str> = FEATURES.iter().map(|(f, _)| *f).collect();
+            Error::Failed(format!(
+                "Unknown feature '{}': expected {} or a VCP code like 0x60",
+                name,
+                known.join(", ")
+            ))
+        })
+}
+
+pub fn feature_name(code: u8) -> Option<&'static str> {
+    FEATURES
+        .iter()
+        .find(|&&(_, c)| c == code)
+        .map(|&(name, _)| name)
+}
+
+/// How a feature is addressed in requests: by name if it has one.
+pub fn feature(code: u8) -> String {
+    feature_name(code).map_or_else(|| format!("0x{:02x}", code), str::to_string)
+}
+
+/// `$XDG_RUNTIME_DIR/dim-and-dimmer.sock`.
+pub fn socket_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|v| !v.is_empty())
+        .map(|dir| PathBuf::from(dir).join("dim-and-dimmer.sock"))
+}
+
+/// A new value for a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Percent of the monitor's range.
+    Absolute(u8),
+    /// Percentage points added to the current value.
+    Relative(i16),
+    /// A value in the monitor's own units.
+    Raw(u16),
+}
+
+impl Level {
+    /// Parse command-line syntax: `40`, `40%`, `+10` or `-10`.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = || {
+            Error::Failed(format!(
+                "Invalid value '{}': expected 0-100, +N or -N",
+                text
+            ))
+        };
+        let number = text.trim_end_matches('%');
+
+        if number.starts_with('+') || number.starts_with('-') {
+            number.parse().map(Level::Relative).map_err(|_| invalid())
+        } else {
+            match number.parse() {
+                Ok(percent) if percent <= 100 => Ok(Level::Absolute(percent)),
+                _ => Err(invalid()),
+            }
+        }
+    }
+
+    pub fn apply(self, current: VcpValue) -> VcpValue {
+        match self {
+            Level::Absolute(percent) => current.with_percent(percent),
+            Level::Relative(delta) => {
+                current.with_percent((current.percent() as i16 + delta).clamp(0, 100) as u8)
+            }
+            Level::Raw(value) => VcpValue::new(value.min(current.max), current.max),
+        }
+    }
+}
+
+/// One display's value of a feature.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reading {
+    /// The display's stable key.
+    pub display: String,
+    pub name: String,
+    pub feature: String,
+    pub percent: u8,
+    /// Raw VCP value and maximum; absent for software dimming.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<u16>,
+}
+
+impl Reading {
+    pub fn vcp(display: &Display, feature: &str, value: VcpValue) -> Self {
+        Self {
+            display: display.key.clone(),
+            name: display.name.clone(),
+            feature: feature.to_string(),
+            percent: value.percent(),
+            value: Some(value.current),
+            max: Some(value.max),
+        }
+    }
+
+    /// A feature with no raw value of its own, like software dimming.
+    pub fn percent(display: &Display, feature: &str, percent: u8) -> Self {
+        Self {
+            display: display.key.clone(),
+            name: display.name.clone(),
+            feature: feature.to_string(),
+            percent,
+            value: None,
+            max: None,
+        }
+    }
+
+    pub fn vcp_value(&self) -> Option<VcpValue> {
+        Some(VcpValue::new(self.value?, self.max?))
+    }
+}
+
+/// What `list` reports about a display.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisplayInfo {
+    /// The display's stable key, used to address it in requests.
+    pub display: String,
+    /// ddcutil's display number.
+    pub number: String,
+    pub name: String,
+    pub connector: String,
+    pub i2c_bus: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backlight: Option<String>,
+    #[serde(flatten)]
+    pub edid: Edid,
+    /// The monitor's raw capabilities string, as the daemon read it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<String>,
+}
+
+impl From<&Display> for DisplayInfo {
+    fn from(d: &Display) -> Self {
+        Self {
+            display: d.key.clone(),
+            number: d.id.clone(),
+            name: d.name.clone(),
+            connector: d.drm_connector.clone(),
+            i2c_bus: d.i2c_bus.clone(),
+            backlight: d.backlight.clone(),
+            edid: d.edid.clone(),
+            capabilities: d.capabilities.as_ref().map(|c| c.raw.clone()),
+        }
+    }
+}
+
+impl From<DisplayInfo> for Display {
+    fn from(info: DisplayInfo) -> Self {
+        Self {
+            id: info.number,
+            name: info.name,
+            drm_connector: info.connector,
+            i2c_bus: info.i2c_bus,
+            backlight: info.backlight,
+            edid: info.edid,
+            key: info.display,
+            capabilities: info
+                .capabilities
+                .and_then(|raw| Capabilities::parse(&raw).ok()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Request {
+    List,
+    Get {
+        display: String,
+        feature: String,
+    },
+    Set {
+        display: String,
+        feature: String,
+        level: Level,
+    },
+    Subscribe,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "lowercase")]
+pub enum Reply {
+    Displays {
+        displays: Vec<DisplayInfo>,
+    },
+    Value(Reading),
+    Subscribed,
+    /// Sent to subscribers whenever a write lands, whoever made it.
+    Changed(Reading),
+    /// `message` is the error as a person would read it.
+    Error {
+        error: Error,
+        message: String,
+    },
+}
+
+impl Reply {
+    pub fn error(error: Error) -> Self {
+        Reply::Error {
+            message: error.to_string(),
+            error,
+        }
+    }
+}
+
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    pub fn connect(path: &Path) -> Result<Self, Error> {
+        let stream = UnixStream::connect(path)
+            .map_err(|e| Error::from_io(&path.display().to_string(), e))?;
+        let writer = stream
+            .try_clone()
+            .map_err(|e| Error::from_io("daemon socket", e))?;
+
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+        })
+    }
+
+    /// Connect to the daemon if one is running.
+    pub fn connect_default() -> Option<Self> {
+        Self::connect(&socket_path()?).ok()
+    }
+
+    /// Send one request and wait for its reply, turning error replies into
+    /// `Err`.
+    pub fn request(&mut self, request: &Request) -> Result<Reply, Error> {
+        let mut line = serde_json::to_string(request).map_err(|e| Error::Failed(e.to_string()))?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .map_err(|e| Error::from_io("daemon socket", e))?;
+
+        match self.next_reply()? {
+            Reply::Error { error, .. } => Err(error),
+            reply => Ok(reply),
+        }
+    }
+
+    /// Block until the daemon sends the next line, e.g. a change event after
+    /// subscribing.
+    pub fn next_reply(&mut self) -> Result<Reply, Error> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err(Error::NotResponding("daemon closed the connection".into())),
+            Ok(_) => serde_json::from_str(&line)
+                .map_err(|e| Error::Parse(format!("daemon reply: {}", e))),
+            Err(e) => Err(Error::from_io("daemon socket", e)),
+        }
+    }
+
+    pub fn list(&mut self) -> Result<Vec<DisplayInfo>, Error> {
+        match self.request(&Request::List)? {
+            Reply::Displays { displays } => Ok(displays),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn get(&mut self, display: &str, feature: &str) -> Result<Reading, Error> {
+        let request = Request::Get {
+            display: display.to_string(),
+            feature: feature.to_string(),
+        };
+        match self.request(&request)? {
+            Reply::Value(reading) => Ok(reading),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn set(&mut self, display: &str, feature: &str, level: Level) -> Result<Reading, Error> {
+        let request = Request::Set {
+            display: display.to_string(),
+            feature: feature.to_string(),
+            level,
+        };
+        match self.request(&request)? {
+            Reply::Value(reading) => Ok(reading),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    /// Turn this connection into a stream of change events, read with
+    /// [`Client::next_change`].
+    pub fn subscribe(mut self) -> Result<Self, Error> {
+        match self.request(&Request::Subscribe)? {
+            Reply::Subscribed => Ok(self),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn next_change(&mut self) -> Result<Reading, Error> {
+        match self.next_reply()? {
+            Reply::Changed(reading) => Ok(reading),
+            reply => Err(unexpected(reply)),
+        }
+    }
+}
+
+/// Where a GUI attached to the daemon sends its reads and writes, so the
+/// daemon's cache and other frontends stay in sync. Without a daemon, or
+/// while it can't be reached, they go straight to the monitor.
+#[derive(Clone)]
+pub struct Route {
+    pub ddc: Ddc,
+    socket: Option<PathBuf>,
+}
+
+impl Route {
+    pub fn new(ddc: Ddc, socket: Option<PathBuf>) -> Self {
+        Self { ddc, socket }
+    }
+
+    /// A connection per call, so a restarted daemon is picked up again.
+    fn client(&self) -> Option<Client> {
+        Client::connect(self.socket.as_deref()?).ok()
+    }
+
+    pub fn read_vcp(&self, display: &Display, code: u8) -> Result<VcpValue, Error> {
+        let Some(mut client) = self.client() else {
+            return self.ddc.read_vcp(display, code);
+        };
+        let reading = client.get(&display.key, &feature(code))?;
+        reading
+            .vcp_value()
+            .ok_or_else(|| Error::Parse(format!("daemon sent no value for {}", reading.feature)))
+    }
+
+    pub fn set_vcp(&self, display: &Display, code: u8, value: u16) -> WriteOutcome {
+        let Some(mut client) = self.client() else {
+            return self.ddc.set_vcp(display, code, value);
+        };
+
+        match client.set(&display.key, &feature(code), Level::Raw(value)) {
+            Ok(reading) if reading.value == Some(value) => WriteOutcome::Applied,
+            Ok(reading) => WriteOutcome::Mismatch {
+                expected: value,
+                actual: reading.value.unwrap_or_default(),
+            },
+            Err(e) => WriteOutcome::Unresponsive(e),
+        }
+    }
+}
+
+fn unexpected(reply: Reply) -> Error {
+    Error::Parse(format!("unexpected daemon reply {:?}", reply))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_format() {
+        let request: Request = serde_json::from_str(
+            r#"{"cmd":"set","display":"2","feature":"brightness","level":{"relative":-10}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request,
+            Request::Set {
+                display: "2".into(),
+                feature: "brightness".into(),
+                level: Level::Relative(-10),
+            }
+        );
+
+        let reply = Reply::error(Error::UnsupportedFeature(0x62));
+        assert_eq!(
+            serde_json::to_string(&reply).unwrap(),
+            r#"{"reply":"error","error":{"kind":"unsupported-feature","detail":98},"message":"VCP feature 62 is not supported"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Reply>(&serde_json::to_string(&reply).unwrap()).unwrap(),
+            reply
+        );
+    }
+
+    #[test]
+    fn test_feature_names() {
+        assert_eq!(feature_code("Brightness").unwrap(), VCP_BRIGHTNESS);
+        assert_eq!(feature_code("0x60").unwrap(), 0x60);
+        assert!(feature_code("0x600").is_err());
+        assert_eq!(feature(VCP_AUDIO_VOLUME), "volume");
+        assert_eq!(feature(0xd6), "0xd6");
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(Level::parse("40").unwrap(), Level::Absolute(40));
+        assert_eq!(Level::parse("+10").unwrap(), Level::Relative(10));
+        assert_eq!(Level::parse("-15%").unwrap(), Level::Relative(-15));
+        assert!(Level::parse("140").is_err());
+        assert!(Level::parse("bright").is_err());
+
+        let current = VcpValue::new(45, 50);
+        assert_eq!(Level::Relative(30).apply(current), VcpValue::new(50, 50));
+        assert_eq!(Level::Absolute(10).apply(current), VcpValue::new(5, 50));
+        assert_eq!(Level::Raw(70).apply(current), VcpValue::new(50, 50));
+    }
+}
@@ -1,23 +1,50 @@
 mod app;
+mod backlight;
+mod brightness;
+mod cli;
 mod commands;
+mod config;
+mod daemon;
+mod dbus;
 mod ddc;
 mod display;
+mod edid;
+mod error;
 mod gamma;
+mod hotplug;
+mod ipc;
+mod tray;
 mod ui;
+mod worker;
+
+use std::process::ExitCode;
 
 use eframe::egui;
 
 use app::App;
 
-fn main() -> eframe::Result<()> {
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return cli::run(&args);
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([450.0, 400.0]),
         ..Default::default()
     };
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "DimAndDimmer",
         options,
         Box::new(|_cc| Ok(Box::new(App::new()))),
-    )
+    );
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,589 @@
+//! System tray icon, as a StatusNotifierItem with a dbusmenu popup.
+//!
+//! The tray only turns clicks into [`TrayEvent`]s; the app applies them to
+//! its own state on the next frame, the same way slider changes go.
+
+use std::collections::HashMap;
+use std::process;
+use std::sync::mpsc::Sender;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use zbus::blocking::{Connection, connection};
+use zbus::fdo;
+use zbus::interface;
+use zbus::zvariant::{ObjectPath, OwnedValue, Type, Value};
+
+use crate::error::Error;
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+
+/// Brightness presets offered for each display.
+pub const PRESETS: [u8; 4] = [25, 50, 75, 100];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// Set a display's brightness to a percentage.
+    Preset {
+        display: String,
+        percent: u8,
+    },
+    /// Mouse wheel over the icon: +1 for up, -1 for down.
+    Scroll(i32),
+    Open,
+    Quit,
+}
+
+/// What the menu shows for one display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayDisplay {
+    pub id: String,
+    pub name: String,
+    pub brightness: Option<u8>,
+}
+
+/// Keeps the icon on the panel for as long as it lives.
+pub struct Tray {
+    connection: Connection,
+}
+
+impl Tray {
+    /// Put an icon on the session bus's tray, if the desktop has one.
+    pub fn start_session(
+        displays: Vec<TrayDisplay>,
+        events: Sender<TrayEvent>,
+        repaint: Option<egui::Context>,
+    ) -> Result<Self, Error> {
+        let builder = connection::Builder::session().map_err(bus_error)?;
+        Self::start(builder, displays, events, repaint)
+    }
+
+    pub fn start(
+        builder: connection::Builder,
+        displays: Vec<TrayDisplay>,
+        events: Sender<TrayEvent>,
+        repaint: Option<egui::Context>,
+    ) -> Result<Self, Error> {
+        let emitter = Emitter { events, repaint };
+        let service = format!("org.kde.StatusNotifierItem-{}-1", process::id());
+        let connection = builder
+            .name(service.as_str())
+            .and_then(|b| {
+                b.serve_at(
+                    ITEM_PATH,
+                    Item {
+                        emitter: emitter.clone(),
+                    },
+                )
+            })
+            .and_then(|b| {
+                b.serve_at(
+                    MENU_PATH,
+                    Menu {
+                        emitter,
+                        displays,
+                        revision: 1,
+                    },
+                )
+            })
+            .and_then(|b| b.build())
+            .map_err(bus_error)?;
+
+        connection
+            .call_method(
+                Some(WATCHER),
+                "/StatusNotifierWatcher",
+                Some(WATCHER),
+                "RegisterStatusNotifierItem",
+                &service,
+            )
+            .map_err(|e| Error::Failed(format!("No system tray: {}", e)))?;
+
+        Ok(Self { connection })
+    }
+
+    /// Rebuild the menu when displays or their brightness changed.
+    pub fn update(&self, displays: Vec<TrayDisplay>) -> Result<(), Error> {
+        let menu = self
+            .connection
+            .object_server()
+            .interface::<_, Menu>(MENU_PATH)
+            .map_err(bus_error)?;
+        let revision = {
+            let mut menu = menu.get_mut();
+            menu.displays = displays;
+            menu.revision += 1;
+            menu.revision
+        };
+
+        self.connection
+            .emit_signal(
+                None::<()>,
+                MENU_PATH,
+                MENU_INTERFACE,
+                "LayoutUpdated",
+                &(revision, 0i32),
+            )
+            .map_err(bus_error)
+    }
+}
+
+fn bus_error(e: zbus::Error) -> Error {
+    Error::Failed(format!("D-Bus: {}", e))
+}
+
+/// Hands events to the app and wakes it, even while its window is hidden.
+#[derive(Clone)]
+struct Emitter {
+    events: Sender<TrayEvent>,
+    repaint: Option<egui::Context>,
+}
+
+impl Emitter {
+    fn send(&self, event: TrayEvent) {
+        let _ = self.events.send(event);
+        if let Some(ctx) = &self.repaint {
+            ctx.request_repaint();
+        }
+    }
+}
+
+struct Item {
+    emitter: Emitter,
+}
+
+/// An icon pixmap: width, height and ARGB32 bytes.
+type Pixmap = (i32, i32, Vec<u8>);
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "Hardware"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> &str {
+        "dim-and-dimmer"
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> &str {
+        "Dim and Dimmer"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn icon_name(&self) -> &str {
+        "display-brightness"
+    }
+
+    #[zbus(property)]
+    fn icon_pixmap(&self) -> Vec<Pixmap> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn tool_tip(&self) -> (String, Vec<Pixmap>, String, String) {
+        (
+            String::new(),
+            Vec::new(),
+            "Dim and Dimmer".into(),
+            "Scroll to change brightness".into(),
+        )
+    }
+
+    /// Left clicks open the window; the menu is only for right clicks.
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn menu(&self) -> ObjectPath<'_> {
+        ObjectPath::from_static_str_unchecked(MENU_PATH)
+    }
+
+    fn activate(&self, _x: i32, _y: i32) {
+        self.emitter.send(TrayEvent::Open);
+    }
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {
+        self.emitter.send(TrayEvent::Open);
+    }
+
+    /// Hosts that don't read `Menu` have nothing else to show.
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, delta: i32, orientation: &str) {
+        if orientation.eq_ignore_ascii_case("vertical") && delta != 0 {
+            self.emitter.send(TrayEvent::Scroll(delta.signum()));
+        }
+    }
+}
+
+/// One entry of the popup. Ids are positions in a depth-first walk, so they
+/// stay the same as long as the display list does.
+struct Entry {
+    label: String,
+    event: Option<TrayEvent>,
+    separator: bool,
+    children: Vec<Entry>,
+}
+
+impl Entry {
+    fn new(label: impl Into<String>, event: Option<TrayEvent>) -> Self {
+        Self {
+            label: label.into(),
+            event,
+            separator: false,
+            children: Vec::new(),
+        }
+    }
+
+    fn separator() -> Self {
+        Self {
+            separator: true,
+            ..Self::new("", None)
+        }
+    }
+
+    fn properties(&self) -> HashMap<String, OwnedValue> {
+        let mut properties = HashMap::new();
+        let mut add = |name: &str, value: Value| {
+            if let Ok(value) = value.try_to_owned() {
+                properties.insert(name.to_string(), value);
+            }
+        };
+
+        if self.separator {
+            add("type", Value::from("separator"));
+        } else {
+            add("label", Value::from(self.label.as_str()));
+        }
+        if !self.children.is_empty() {
+            add("children-display", Value::from("submenu"));
+        }
+        properties
+    }
+
+    /// Find an entry by id, counting from `next`.
+    fn find(&self, id: i32, next: &mut i32) -> Option<&Entry> {
+        let own = *next;
+        *next += 1;
+        if own == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(id, next))
+    }
+
+    /// Render this entry as `own_id`, down to `depth` levels (-1 for all).
+    fn layout(&self, own_id: i32, depth: i32) -> Layout {
+        let mut next = own_id + 1;
+        let mut children = Vec::new();
+
+        for child in &self.children {
+            let id = next;
+            next += child.count();
+            if depth != 0 {
+                let rendered = child.layout(id, depth - 1);
+                if let Ok(value) = Value::from(rendered).try_to_owned() {
+                    children.push(value);
+                }
+            }
+        }
+
+        Layout {
+            id: own_id,
+            properties: self.properties(),
+            children,
+        }
+    }
+
+    fn count(&self) -> i32 {
+        1 + self.children.iter().map(Entry::count).sum::<i32>()
+    }
+}
+
+/// `(ia{sv}av)`: an item, its properties and its children, each of which is
+/// another layout wrapped in a variant.
+#[derive(Debug, Serialize, Deserialize, Type, Value, OwnedValue)]
+struct Layout {
+    id: i32,
+    properties: HashMap<String, OwnedValue>,
+    children: Vec<OwnedValue>,
+}
+
+fn root(displays: &[TrayDisplay]) -> Entry {
+    let mut root = Entry::new("", None);
+
+    for display in displays {
+        let label = match display.brightness {
+            Some(percent) => format!("{} ({}%)", display.name, percent),
+            None => display.name.clone(),
+        };
+        let mut entry = Entry::new(label, None);
+        entry.children = PRESETS
+            .iter()
+            .map(|&percent| {
+                let event = TrayEvent::Preset {
+                    display: display.id.clone(),
+                    percent,
+                };
+                Entry::new(format!("{}%", percent), Some(event))
+            })
+            .collect();
+        root.children.push(entry);
+    }
+
+    root.children.push(Entry::separator());
+    root.children
+        .push(Entry::new("Open Dim and Dimmer", Some(TrayEvent::Open)));
+    root.children
+        .push(Entry::new("Quit", Some(TrayEvent::Quit)));
+    root
+}
+
+struct Menu {
+    emitter: Emitter,
+    displays: Vec<TrayDisplay>,
+    revision: u32,
+}
+
+impl Menu {
+    fn entry<T>(&self, id: i32, with: impl FnOnce(&Entry) -> T) -> fdo::Result<T> {
+        root(&self.displays)
+            .find(id, &mut 0)
+            .map(with)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("No menu item {}", id)))
+    }
+
+    fn clicked(&self, id: i32, event_id: &str) -> fdo::Result<()> {
+        let event = self.entry(id, |entry| entry.event.clone())?;
+        if event_id == "clicked"
+            && let Some(event) = event
+        {
+            self.emitter.send(event);
+        }
+        Ok(())
+    }
+}
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> fdo::Result<(u32, Layout)> {
+        let root = root(&self.displays);
+        let parent = root
+            .find(parent_id, &mut 0)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("No menu item {}", parent_id)))?;
+        Ok((self.revision, parent.layout(parent_id, recursion_depth)))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        ids.into_iter()
+            .filter_map(|id| Some((id, self.entry(id, Entry::properties).ok()?)))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: &str) -> fdo::Result<OwnedValue> {
+        self.entry(id, Entry::properties)?
+            .remove(name)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("No property {}", name)))
+    }
+
+    fn event(&self, id: i32, event_id: &str, _data: Value<'_>, _timestamp: u32) -> fdo::Result<()> {
+        self.clicked(id, event_id)
+    }
+
+    /// Returns the ids that don't exist.
+    fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        events
+            .into_iter()
+            .filter(|(id, event_id, _, _)| self.clicked(*id, event_id).is_err())
+            .map(|(id, _, _, _)| id)
+            .collect()
+    }
+
+    /// The menu is rebuilt as values change, so it never needs a refresh.
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+
+    use zbus::blocking::Proxy;
+
+    use crate::dbus::tests::TestBus;
+
+    /// Stands in for the desktop's tray host.
+    #[derive(Default)]
+    struct Watcher {
+        items: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[interface(name = "org.kde.StatusNotifierWatcher")]
+    impl Watcher {
+        fn register_status_notifier_item(&self, service: String) {
+            self.items.lock().unwrap().push(service);
+        }
+    }
+
+    fn displays() -> Vec<TrayDisplay> {
+        vec![
+            TrayDisplay {
+                id: "1".into(),
+                name: "DELL U2715H".into(),
+                brightness: Some(70),
+            },
+            TrayDisplay {
+                id: "2".into(),
+                name: "Samsung 27\"".into(),
+                brightness: None,
+            },
+        ]
+    }
+
+    fn label(layout: &Layout) -> String {
+        String::try_from(layout.properties["label"].try_clone().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_menu_layout() {
+        let root = root(&displays());
+        let layout = root.layout(0, -1);
+        let children: Vec<Layout> = layout
+            .children
+            .iter()
+            .map(|c| Layout::try_from(c.try_clone().unwrap()).unwrap())
+            .collect();
+
+        assert_eq!(children.len(), 5);
+        assert_eq!(label(&children[0]), "DELL U2715H (70%)");
+        assert_eq!(label(&children[1]), "Samsung 27\"");
+        assert_eq!(children[2].properties.keys().collect::<Vec<_>>(), ["type"]);
+        assert_eq!(label(&children[4]), "Quit");
+
+        // Presets of the second display sit after the first's four
+        let presets = &children[1].children;
+        assert_eq!(children[1].id, 6);
+        assert_eq!(presets.len(), 4);
+        let fifty = root.find(8, &mut 0).unwrap();
+        assert_eq!(
+            fifty.event,
+            Some(TrayEvent::Preset {
+                display: "2".into(),
+                percent: 50
+            })
+        );
+
+        // Depth 0 leaves children out
+        assert!(root.layout(0, 0).children.is_empty());
+    }
+
+    #[test]
+    fn test_tray_on_private_bus() {
+        let Some(bus) = TestBus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let watcher = Watcher::default();
+        let items = watcher.items.clone();
+        let _host = bus
+            .connect()
+            .name(WATCHER)
+            .unwrap()
+            .serve_at("/StatusNotifierWatcher", watcher)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let (sender, events) = mpsc::channel();
+        let tray = Tray::start(bus.connect(), displays(), sender, None).unwrap();
+        let service = items.lock().unwrap()[0].clone();
+        assert!(service.starts_with("org.kde.StatusNotifierItem-"));
+
+        let client = bus.connect().build().unwrap();
+        let item = Proxy::new(
+            &client,
+            service.as_str(),
+            ITEM_PATH,
+            "org.kde.StatusNotifierItem",
+        )
+        .unwrap();
+        let menu = Proxy::new(&client, service.as_str(), MENU_PATH, MENU_INTERFACE).unwrap();
+
+        item.call::<_, _, ()>("Scroll", &(120i32, "vertical"))
+            .unwrap();
+        item.call::<_, _, ()>("Activate", &(0i32, 0i32)).unwrap();
+        menu.call::<_, _, ()>("Event", &(5i32, "clicked", Value::from(0i32), 0u32))
+            .unwrap();
+        menu.call::<_, _, ()>("Event", &(13i32, "clicked", Value::from(0i32), 0u32))
+            .unwrap();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                TrayEvent::Scroll(1),
+                TrayEvent::Open,
+                TrayEvent::Preset {
+                    display: "1".into(),
+                    percent: 100
+                },
+                TrayEvent::Quit,
+            ]
+        );
+
+        let mut updated = displays();
+        updated[0].brightness = Some(25);
+        tray.update(updated).unwrap();
+        let (revision, layout): (u32, Layout) = menu
+            .call("GetLayout", &(1i32, 0i32, Vec::<String>::new()))
+            .unwrap();
+        assert_eq!(revision, 2);
+        assert_eq!(label(&layout), "DELL U2715H (25%)");
+    }
+}
@@ -3,7 +3,20 @@ use std::ops::RangeInclusive;
 use eframe::egui;
 
 use crate::app::{App, InitState};
-use crate::ddc::{VCP_BRIGHTNESS, VCP_CONTRAST, set_vcp};
+use crate::ddc::mccs::{self, FeatureKind};
+use crate::ddc::{
+    BLACK_LEVELS, COLOR_CODES, Capabilities, Feature, GAINS, PowerMode, VCP_AUDIO_MUTE,
+    VCP_AUDIO_VOLUME, VCP_BRIGHTNESS, VCP_COLOR_PRESET, VCP_CONTRAST, VCP_INPUT_SOURCE,
+    VCP_POWER_MODE, VcpValue, input_name, is_user_preset,
+};
+use crate::gamma::{self, SessionType};
+use crate::tray::TrayEvent;
+
+const CHANNEL_COLORS: [egui::Color32; 3] = [
+    egui::Color32::from_rgb(230, 90, 90),
+    egui::Color32::from_rgb(90, 200, 90),
+    egui::Color32::from_rgb(100, 140, 240),
+];
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -12,23 +25,27 @@ impl eframe::App for App {
             self.start_init(ctx.clone());
         }
 
+        if ctx.input(|i| i.viewport().close_requested()) {
+            if self.has_tray() && !self.quitting {
+                // Keep running in the tray. Wayland ignores a window hiding
+                // itself, which would leave it open, so there it minimizes
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                ctx.send_viewport_cmd(match gamma::detect_session() {
+                    SessionType::Wayland => egui::ViewportCommand::Minimized(true),
+                    _ => egui::ViewportCommand::Visible(false),
+                });
+            } else {
+                // Don't leave ddcutil running against a wedged bus after we're gone
+                self.cancel_pending();
+            }
+        }
+
         // Receive result (only succeeds once, after thread's request_repaint)
         if let Some(rx) = self.init_receiver.take() {
             match rx.try_recv() {
                 Ok(result) => {
-                    self.ddcutil_available = result.ddcutil_available;
-                    self.displays = result.displays;
-                    self.gamma_backend = result.gamma_backend;
-                    self.gamma_output_map = result.gamma_output_map;
-                    self.brightness = result.brightness;
-                    self.contrast = result.contrast;
-
-                    if let Some(err) = result.error {
-                        self.init_state = InitState::Failed(err);
-                    } else {
-                        self.init_state = InitState::Ready;
-                        self.status = Some("Ready".into());
-                    }
+                    self.finish_init(result);
+                    self.start_tray();
                 }
                 Err(std::sync::mpsc::TryRecvError::Empty) => {
                     self.init_receiver = Some(rx); // Put it back
@@ -39,6 +56,24 @@ impl eframe::App for App {
             }
         }
 
+        self.poll_writes();
+        self.poll_reads();
+        self.poll_hotplug();
+        for event in self.poll_tray() {
+            match event {
+                TrayEvent::Open => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                TrayEvent::Quit => {
+                    self.quitting = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                _ => {}
+            }
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.add_space(10.0);
 
@@ -57,7 +92,7 @@ impl eframe::App for App {
                     );
                 }
                 InitState::Ready => {
-                    show_main_ui(self, ui);
+                    egui::ScrollArea::vertical().show(ui, |ui| show_main_ui(self, ui));
                 }
             }
         });
@@ -65,7 +100,8 @@ impl eframe::App for App {
 }
 
 fn show_main_ui(app: &mut App, ui: &mut egui::Ui) {
-    if !app.ddcutil_available {
+    // A laptop panel needs no ddcutil, so only say so when nothing was found
+    if !app.ddcutil_available && app.displays.is_empty() {
         ui.colored_label(egui::Color32::RED, "ddcutil not installed");
         return;
     }
@@ -76,91 +112,696 @@ fn show_main_ui(app: &mut App, ui: &mut egui::Ui) {
     }
 
     display_selector(app, ui);
+
+    if app.config.ui.dashboard {
+        ui.separator();
+        dashboard(app, ui);
+        ui.separator();
+        messages(app, ui);
+        return;
+    }
+
+    power_controls(app, ui);
+    ui.separator();
+
+    // Without capabilities, assume the basics are there
+    let supports = |code| {
+        app.display()
+            .capabilities
+            .as_ref()
+            .is_none_or(|c| c.supports(code))
+    };
+    let (has_brightness, has_contrast) = (supports(VCP_BRIGHTNESS), supports(VCP_CONTRAST));
+
+    // A sleeping monitor won't answer, so hold off until it's woken
+    let awake = app.power_mode().is_on();
+
+    if app.all_displays {
+        linked_controls(app, ui);
+    }
+
+    let step = app.config.steps.slider;
+    let brightness_range = app.config.ranges.brightness.range();
+    let contrast_range = app.config.ranges.contrast.range();
+
+    let extended = app.config.ui.extended_range && app.gamma_available(app.selected_display);
+
+    ui.add_enabled_ui(awake, |ui| {
+        // In "All displays" mode the shared sliders above take over
+        if !app.all_displays && has_brightness && extended {
+            extended_slider(app, ui);
+        } else if !app.all_displays
+            && has_brightness
+            && let Some(update) = vcp_slider(
+                ui,
+                "Brightness",
+                &mut app.brightness,
+                brightness_range,
+                step,
+            )
+        {
+            send_vcp(app, "brightness", VCP_BRIGHTNESS, update);
+        }
+
+        if !app.all_displays
+            && has_contrast
+            && let Some(update) =
+                vcp_slider(ui, "Contrast", &mut app.contrast, contrast_range, step)
+        {
+            send_vcp(app, "contrast", VCP_CONTRAST, update);
+        }
+
+        input_selector(app, ui);
+        audio_controls(app, ui);
+    });
+
+    if app.gamma_available(app.selected_display) {
+        ui.separator();
+        dimming_controls(app, ui, app.selected_display);
+    }
+
+    ui.add_enabled_ui(awake, |ui| {
+        color_panel(app, ui);
+        advanced_panel(app, ui);
+    });
+
     ui.separator();
+    messages(app, ui);
+}
+
+/// Brightness down to the monitor's minimum, then software dimming below it.
+fn extended_slider(app: &mut App, ui: &mut egui::Ui) {
+    let level = app.extended_drag.unwrap_or_else(|| app.extended_level());
+    let mut value = VcpValue::new(level.into(), 100);
+    match vcp_slider(
+        ui,
+        "Brightness (extended)",
+        &mut value,
+        0..=100,
+        app.config.steps.slider,
+    ) {
+        Some(SliderUpdate::Dragging(v)) => app.preview_extended(v.percent()),
+        Some(SliderUpdate::Released(v)) => app.apply_extended(v.percent()),
+        None => {}
+    }
+}
+
+fn dimming_controls(app: &mut App, ui: &mut egui::Ui, index: usize) {
+    let id = app.displays[index].id.clone();
+    let level = app
+        .dimming_drag
+        .get(&id)
+        .copied()
+        .unwrap_or_else(|| app.gamma_level(index));
+    let mut value = VcpValue::new(level.into(), 100);
+    let range = app.config.ranges.dimming.range();
+    match vcp_slider(
+        ui,
+        "Software Dimming",
+        &mut value,
+        range,
+        app.config.steps.slider,
+    ) {
+        // Keep the slider where the user is dragging it
+        Some(SliderUpdate::Dragging(v)) => {
+            app.dimming_drag.insert(id, v.percent());
+        }
+        Some(SliderUpdate::Released(v)) => app.apply_dimming(index, v.percent()),
+        None => {}
+    }
+
+    if ui.button("Reset Gamma").clicked() {
+        app.reset_gamma(index);
+    }
+}
 
-    if let Some(v) = slider(ui, "Brightness", &mut app.brightness, 1..=100) {
-        match set_vcp(app.display_id(), VCP_BRIGHTNESS, v) {
-            Ok(_) => {
-                app.status = Some(format!("Set brightness to {}%", v));
-                app.error = None;
+/// Every display as its own card, laid out the way the monitors sit on the
+/// desktop.
+fn dashboard(app: &mut App, ui: &mut egui::Ui) {
+    for row in app.dashboard_rows() {
+        ui.horizontal_top(|ui| {
+            for index in row {
+                ui.push_id(("card", index), |ui| display_card(app, ui, index));
             }
-            Err(e) => {
-                app.error = Some(format!("Failed to set brightness: {}", e));
+        });
+        ui.add_space(5.0);
+    }
+}
+
+fn display_card(app: &mut App, ui: &mut egui::Ui, index: usize) {
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_width(240.0);
+        ui.vertical(|ui| {
+            let name = app.displays[index].name.clone();
+            ui.strong(&name);
+
+            let mode = app.power_mode_of(index);
+            match app.last_outcome(index) {
+                _ if !mode.is_on() => {
+                    ui.colored_label(egui::Color32::YELLOW, mode.label());
+                }
+                Some(outcome) if !outcome.is_applied() => {
+                    ui.colored_label(egui::Color32::RED, outcome.to_string());
+                }
+                _ => {
+                    ui.weak(mode.label());
+                }
             }
-        }
+            ui.add_space(5.0);
+
+            ui.add_enabled_ui(mode.is_on(), |ui| {
+                let ranges = &app.config.ranges;
+                let sliders = [
+                    ("Brightness", VCP_BRIGHTNESS, ranges.brightness.range()),
+                    ("Contrast", VCP_CONTRAST, ranges.contrast.range()),
+                ];
+                for (label, code, range) in sliders {
+                    let Some(mut value) = app.display_value(index, code) else {
+                        continue;
+                    };
+                    if let Some(update) =
+                        vcp_slider(ui, label, &mut value, range, app.config.steps.slider)
+                    {
+                        let label = format!("{} {}", name, label.to_lowercase());
+                        send_vcp_to(app, index, &label, code, update);
+                    }
+                }
+            });
+
+            if app.gamma_available(index) {
+                dimming_controls(app, ui, index);
+            }
+        });
+    });
+}
+
+/// Shared brightness and contrast sliders for "All displays" mode, with a
+/// row per display to link it in and nudge it relative to the others.
+fn linked_controls(app: &mut App, ui: &mut egui::Ui) {
+    ui.label("Linked displays");
+
+    egui::Grid::new("linked_displays")
+        .num_columns(3)
+        .show(ui, |ui| {
+            for index in 0..app.displays.len() {
+                let mut linked = app.is_linked(index);
+                if ui
+                    .checkbox(&mut linked, &app.displays[index].name)
+                    .changed()
+                {
+                    app.set_linked(index, linked);
+                }
+
+                for (code, name) in [(VCP_BRIGHTNESS, "brightness"), (VCP_CONTRAST, "contrast")] {
+                    let mut offset = app.offset(index, code);
+                    let response = ui
+                        .add_enabled(
+                            linked,
+                            egui::DragValue::new(&mut offset)
+                                .range(-50..=50)
+                                .suffix("%"),
+                        )
+                        .on_hover_text(format!("Added to the shared {} for this display", name));
+                    if response.changed() {
+                        app.set_offset(index, code, offset);
+                    }
+                    if response.drag_stopped() || response.lost_focus() {
+                        app.save_config();
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    ui.add_space(10.0);
+
+    let step = app.config.steps.slider;
+    let mut level = app.linked_brightness;
+    let range = app.config.ranges.brightness.range();
+    if let Some(update) = vcp_slider(ui, "Brightness (all displays)", &mut level, range, step) {
+        app.linked_brightness = level;
+        send_linked(app, "brightness", VCP_BRIGHTNESS, update);
     }
 
-    if let Some(v) = slider(ui, "Contrast", &mut app.contrast, 1..=100) {
-        match set_vcp(app.display_id(), VCP_CONTRAST, v) {
-            Ok(_) => {
-                app.status = Some(format!("Set contrast to {}%", v));
-                app.error = None;
+    let mut level = app.linked_contrast;
+    let range = app.config.ranges.contrast.range();
+    if let Some(update) = vcp_slider(ui, "Contrast (all displays)", &mut level, range, step) {
+        app.linked_contrast = level;
+        send_linked(app, "contrast", VCP_CONTRAST, update);
+    }
+}
+
+fn power_controls(app: &mut App, ui: &mut egui::Ui) {
+    if !app.config.features.power {
+        return;
+    }
+    let Some(feature) = app
+        .display()
+        .capabilities
+        .as_ref()
+        .and_then(|c| c.feature(VCP_POWER_MODE))
+    else {
+        return;
+    };
+
+    let mut modes: Vec<PowerMode> = feature
+        .values
+        .iter()
+        .filter_map(|&v| PowerMode::from_vcp(v))
+        .collect();
+    if modes.is_empty() {
+        modes = vec![PowerMode::Standby, PowerMode::Off];
+    }
+    if !modes.contains(&PowerMode::On) {
+        modes.insert(0, PowerMode::On);
+    }
+
+    let current = app.power_mode();
+    let mut picked = None;
+
+    ui.horizontal(|ui| {
+        ui.label("Power");
+        for mode in modes {
+            let mut response = ui.selectable_label(current == mode, mode.label());
+            if mode == PowerMode::PowerOff {
+                response =
+                    response.on_hover_text("Most monitors need their power button to turn back on");
             }
-            Err(e) => {
-                app.error = Some(format!("Failed to set contrast: {}", e));
+            if response.clicked() {
+                picked = Some(mode);
             }
         }
+    });
+
+    if !current.is_on() {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            format!(
+                "{} is {}; wake it to change its settings",
+                app.display().name,
+                current.describe()
+            ),
+        );
     }
 
-    if app.gamma_available() {
-        ui.separator();
+    if let Some(mode) = picked {
+        app.set_power_mode(mode);
+    }
+}
+
+fn input_selector(app: &mut App, ui: &mut egui::Ui) {
+    let inputs = app.input_sources();
+    if inputs.is_empty() || !app.config.features.inputs {
+        return;
+    }
+
+    ui.label("Input");
+
+    let current = app.current_input();
+    let mut picked = None;
+    ui.horizontal_wrapped(|ui| {
+        for &code in &inputs {
+            if ui
+                .selectable_label(current == Some(code), app.input_label(code))
+                .on_hover_text(input_name(code))
+                .clicked()
+            {
+                picked = Some(code);
+            }
+        }
+    });
+
+    if let Some(code) = picked {
+        app.switch_input(code);
+    }
 
-        if let Some(v) = slider(ui, "Software Dimming", &mut app.gamma_dimming, 20..=100) {
-            if let (Some(backend), Some(output)) = (&app.gamma_backend, app.gamma_output()) {
-                match backend.apply_dimming(output, v) {
-                    Ok(_) => {
-                        app.gamma_dimming = v;
-                        if v < 100 {
-                            app.status = Some(format!("Set software dimming to {}%", v));
-                        } else {
-                            app.status = Some("Software dimming removed".into());
+    egui::CollapsingHeader::new("Input labels")
+        .id_salt(("input_labels", app.display_id().to_string()))
+        .show(ui, |ui| {
+            egui::Grid::new("input_labels")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for &code in &inputs {
+                        ui.label(input_name(code));
+
+                        let mut label = app
+                            .config
+                            .input_label(app.display().key(), code)
+                            .unwrap_or_default()
+                            .to_string();
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut label).hint_text(input_name(code)),
+                        );
+                        if response.changed() {
+                            app.set_input_label(code, &label);
+                        }
+                        if response.lost_focus() {
+                            app.save_config();
                         }
-                        app.error = None;
+                        ui.end_row();
                     }
-                    Err(e) => {
-                        app.error = Some(format!("Gamma error: {}", e));
+                });
+        });
+
+    ui.add_space(10.0);
+}
+
+/// Speaker volume and mute, for monitors whose capabilities list them.
+fn audio_controls(app: &mut App, ui: &mut egui::Ui) {
+    let has_volume = app.display().supports(VCP_AUDIO_VOLUME);
+    let has_mute = app.display().supports(VCP_AUDIO_MUTE);
+    if !has_volume && !has_mute || !app.config.features.audio {
+        return;
+    }
+
+    if has_volume {
+        let mut volume = app.volume().unwrap_or_default();
+        let range = app.config.ranges.volume.range();
+        if let Some(update) = vcp_slider(ui, "Volume", &mut volume, range, app.config.steps.slider)
+        {
+            app.feature_values.insert(VCP_AUDIO_VOLUME, volume);
+            send_vcp(app, "volume", VCP_AUDIO_VOLUME, update);
+        }
+    }
+
+    if has_mute {
+        let mut muted = app.muted().unwrap_or(false);
+        if ui.checkbox(&mut muted, "Mute speakers").changed() {
+            app.set_muted(muted);
+        }
+        ui.add_space(10.0);
+    }
+}
+
+fn color_panel(app: &mut App, ui: &mut egui::Ui) {
+    let Some(caps) = app.display().capabilities.clone() else {
+        return;
+    };
+    if !COLOR_CODES.iter().any(|&code| caps.supports(code)) || !app.config.features.color {
+        return;
+    }
+
+    ui.separator();
+
+    egui::CollapsingHeader::new("Color")
+        .id_salt(("color", app.display_id().to_string()))
+        .show(ui, |ui| {
+            if ui.button("Read current values").clicked() {
+                app.refresh_color();
+            }
+            ui.add_space(5.0);
+
+            if let Some(preset) = caps.feature(VCP_COLOR_PRESET) {
+                ui.horizontal(|ui| {
+                    ui.label("Preset");
+                    feature_control(app, ui, preset);
+                });
+
+                let current = app
+                    .feature_values
+                    .get(&VCP_COLOR_PRESET)
+                    .map(|v| v.low_byte());
+                if let Some(current) = current
+                    && !is_user_preset(current)
+                    && preset.choices().iter().any(|&v| is_user_preset(v))
+                {
+                    ui.small("Pick a User preset to adjust the gains");
+                }
+            }
+
+            channel_sliders(app, ui, &caps, "Gain", &GAINS);
+            channel_sliders(app, ui, &caps, "Black level", &BLACK_LEVELS);
+        });
+}
+
+/// One raw slider per colour channel the monitor advertises. Sliders stay
+/// disabled until the current value has been read, so a drag never starts
+/// from a made-up zero.
+fn channel_sliders(
+    app: &mut App,
+    ui: &mut egui::Ui,
+    caps: &Capabilities,
+    title: &str,
+    channels: &[(u8, &str); 3],
+) {
+    if !channels.iter().any(|&(code, _)| caps.supports(code)) {
+        return;
+    }
+
+    ui.add_space(5.0);
+    ui.label(title);
+
+    egui::Grid::new(("channels", title))
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (&(code, name), color) in channels.iter().zip(CHANNEL_COLORS) {
+                if !caps.supports(code) {
+                    continue;
+                }
+
+                ui.colored_label(color, name);
+
+                let current = app.feature_values.get(&code).copied();
+                let mut value = current.unwrap_or_default();
+                let response = ui.add_enabled(
+                    current.is_some(),
+                    egui::Slider::new(&mut value.current, 0..=value.max),
+                );
+                if response.changed() {
+                    app.feature_values.insert(code, value);
+                }
+                // Clicks and key presses land straight away, drags once let go
+                if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                    app.apply_feature(code, value.current);
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn advanced_panel(app: &mut App, ui: &mut egui::Ui) {
+    let Some(caps) = app.display().capabilities.clone() else {
+        return;
+    };
+    if !app.config.features.advanced {
+        return;
+    }
+
+    ui.separator();
+
+    egui::CollapsingHeader::new("Advanced")
+        .id_salt(("advanced", app.display_id().to_string()))
+        .show(ui, |ui| {
+            if ui.button("Read current values").clicked() {
+                app.refresh_features();
+            }
+            ui.add_space(5.0);
+
+            egui::Grid::new("advanced_features")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for feature in caps.features.values() {
+                        // Already covered by a dedicated control
+                        if matches!(
+                            feature.code,
+                            VCP_BRIGHTNESS
+                                | VCP_CONTRAST
+                                | VCP_INPUT_SOURCE
+                                | VCP_POWER_MODE
+                                | VCP_AUDIO_VOLUME
+                                | VCP_AUDIO_MUTE
+                        ) || COLOR_CODES.contains(&feature.code)
+                        {
+                            continue;
+                        }
+
+                        ui.label(feature.name());
+                        feature_control(app, ui, feature);
+                        ui.end_row();
                     }
+                });
+        });
+}
+
+fn feature_control(app: &mut App, ui: &mut egui::Ui, feature: &Feature) {
+    let current = app.feature_values.get(&feature.code).copied();
+
+    if !feature.writable() {
+        ui.label(current.map_or("-".into(), |v| feature.format_value(v)));
+        return;
+    }
+
+    if !feature.readable() {
+        let value = feature.values.first().map_or(1, |&v| v.into());
+        if app.confirm_feature == Some(feature.code) {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(egui::RichText::new("Confirm").color(egui::Color32::RED))
+                    .clicked()
+                {
+                    app.confirm_feature = None;
+                    app.apply_feature(feature.code, value);
                 }
+                if ui.button("Cancel").clicked() {
+                    app.confirm_feature = None;
+                }
+            });
+        } else if ui.button("Run").clicked() {
+            // Factory resets wipe the user's settings, so take a second click
+            if mccs::is_destructive(feature.code) {
+                app.confirm_feature = Some(feature.code);
+            } else {
+                app.apply_feature(feature.code, value);
+            }
+        }
+        return;
+    }
+
+    match feature.kind {
+        FeatureKind::Continuous => {
+            // Disabled until read, so a drag never starts from a made-up zero
+            let mut value = current.unwrap_or_default();
+            let response = ui.add_enabled(
+                current.is_some(),
+                egui::Slider::new(&mut value.current, 0..=value.max),
+            );
+            if response.changed() {
+                app.feature_values.insert(feature.code, value);
+            }
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                app.apply_feature(feature.code, value.current);
             }
         }
+        FeatureKind::NonContinuous => {
+            let choices = feature.choices();
+            let selected_text = current.map_or("-".into(), |v| feature.format_value(v));
+            let current = current.map(|v| v.low_byte());
+            let mut picked = None;
 
-        if ui.button("Reset Gamma").clicked() {
-            if let (Some(backend), Some(output)) = (&app.gamma_backend, app.gamma_output()) {
-                if backend.reset(output).is_ok() {
-                    app.gamma_dimming = 100;
-                    app.status = Some("Reset gamma to normal".into());
-                }
+            if choices.is_empty() {
+                ui.label(selected_text);
+                return;
+            }
+
+            egui::ComboBox::from_id_salt(("feature", feature.code))
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for value in choices {
+                        if ui
+                            .selectable_label(current == Some(value), feature.value_name(value))
+                            .clicked()
+                        {
+                            picked = Some(value);
+                        }
+                    }
+                });
+
+            if let Some(value) = picked {
+                app.apply_feature(feature.code, value.into());
             }
         }
+        FeatureKind::Table => {}
     }
+}
 
-    ui.separator();
-    messages(app, ui);
+/// A slider's new value, and whether the user is still dragging it.
+#[derive(Clone, Copy)]
+enum SliderUpdate {
+    Dragging(VcpValue),
+    Released(VcpValue),
 }
 
-fn slider(ui: &mut egui::Ui, label: &str, value: &mut u8, range: RangeInclusive<u8>) -> Option<u8> {
+fn percent_slider(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut u8,
+    range: RangeInclusive<u8>,
+    step: u8,
+) -> egui::Response {
     ui.label(label);
 
     let mut temp = *value;
-    let response = ui.add(egui::Slider::new(&mut temp, range).suffix("%"));
+    let response = ui.add(
+        egui::Slider::new(&mut temp, range)
+            .step_by(step.max(1).into())
+            .suffix("%"),
+    );
 
     if response.changed() {
         *value = temp;
     }
 
     ui.add_space(10.0);
+    response
+}
 
-    if response.drag_stopped() {
-        Some(temp)
+/// Percentage slider over a feature with its own native range. Clicks and
+/// key presses count as released, since no further change is coming.
+fn vcp_slider(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut VcpValue,
+    range: RangeInclusive<u8>,
+    step: u8,
+) -> Option<SliderUpdate> {
+    let mut percent = value.percent();
+    let response = percent_slider(ui, label, &mut percent, range, step);
+
+    if percent != value.percent() {
+        *value = value.with_percent(percent);
+    }
+
+    if response.drag_stopped() || (response.changed() && !response.dragged()) {
+        Some(SliderUpdate::Released(*value))
+    } else if response.changed() {
+        Some(SliderUpdate::Dragging(*value))
     } else {
         None
     }
 }
 
+fn send_vcp(app: &mut App, label: &str, code: u8, update: SliderUpdate) {
+    match update {
+        SliderUpdate::Dragging(v) => app.preview_vcp(label, code, v),
+        SliderUpdate::Released(v) => app.apply_vcp(label, code, v),
+    }
+}
+
+fn send_vcp_to(app: &mut App, index: usize, label: &str, code: u8, update: SliderUpdate) {
+    // Keep the card's slider where the user is dragging it
+    let (SliderUpdate::Dragging(v) | SliderUpdate::Released(v)) = update;
+    app.set_display_value(index, code, v);
+
+    match update {
+        SliderUpdate::Dragging(v) => app.preview_vcp_to(index, label, code, v),
+        SliderUpdate::Released(v) => app.apply_vcp_to(index, label, code, v),
+    }
+}
+
+fn send_linked(app: &mut App, label: &str, code: u8, update: SliderUpdate) {
+    match update {
+        SliderUpdate::Dragging(v) => app.preview_linked(label, code, v),
+        SliderUpdate::Released(v) => app.apply_linked(label, code, v),
+    }
+}
+
 fn display_selector(app: &mut App, ui: &mut egui::Ui) {
     let mut refresh = false;
+    let mut switched = false;
+    let mut all_displays = app.all_displays;
 
     ui.horizontal(|ui| {
+        if ui
+            .checkbox(&mut app.config.ui.dashboard, "Dashboard")
+            .changed()
+        {
+            app.save_config();
+        }
+        if app.config.ui.dashboard {
+            refresh |= ui.button("Refresh").clicked();
+            return;
+        }
+
         egui::ComboBox::from_label("Display")
             .selected_text(&app.displays[app.selected_display].name)
             .show_ui(ui, |ui| {
@@ -169,20 +810,43 @@ fn display_selector(app: &mut App, ui: &mut egui::Ui) {
                         .selectable_value(&mut app.selected_display, i, &display.name)
                         .clicked()
                     {
-                        refresh = true;
+                        switched = true;
                     }
                 }
             });
 
         refresh |= ui.button("Refresh").clicked();
+
+        if app.displays.len() > 1 {
+            ui.checkbox(&mut all_displays, "All displays");
+        }
     });
 
-    if refresh {
+    if all_displays != app.all_displays {
+        app.set_all_displays(all_displays);
+    }
+
+    // Reads still running for the previous display are moot now
+    if switched {
+        app.confirm_feature = None;
+        app.extended_drag = None;
+        app.cancel_reads();
+        app.remember_selection();
+    }
+    if refresh && app.config.ui.dashboard {
+        app.refresh_dashboard();
+    } else if refresh || switched {
         app.refresh_values();
     }
 }
 
 fn messages(app: &App, ui: &mut egui::Ui) {
+    if app.writes_pending() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Applying...");
+        });
+    }
     if let Some(status) = &app.status {
         ui.colored_label(egui::Color32::GREEN, format!("[OK] {}", status));
     }
@@ -0,0 +1,266 @@
+//! Background DDC writers, one per display, so a slow monitor never stalls
+//! a frame.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use eframe::egui;
+
+use crate::ddc::{Ddc, PowerMode, VcpValue, WriteOutcome};
+use crate::display::Display;
+use crate::ipc::Route;
+
+/// Carries out one write on the worker's thread. Usually [`Ddc::set_vcp`],
+/// but a GUI attached to the daemon hands its writes to it instead.
+pub type Writer = Box<dyn FnMut(&Display, u8, u16) -> WriteOutcome + Send>;
+
+pub fn ddc_writer(ddc: Ddc) -> Writer {
+    Box::new(move |display, code, value| ddc.set_vcp(display, code, value))
+}
+
+/// Send every write along `route`, through the daemon when there is one.
+pub fn route_writer(route: Route) -> Writer {
+    Box::new(move |display, code, value| route.set_vcp(display, code, value))
+}
+
+/// What a queued write is for, so its completion can be reported the way the
+/// user asked for it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// A percentage slider such as brightness, contrast or volume.
+    Slider {
+        label: String,
+        value: VcpValue,
+    },
+    /// An advanced feature, with its new value already formatted for display.
+    Feature {
+        shown: String,
+        max: u16,
+    },
+    Input {
+        code: u8,
+        label: String,
+    },
+    Mute(bool),
+    Power(PowerMode),
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub display_id: String,
+    pub code: u8,
+    pub value: u16,
+    pub action: Action,
+    pub outcome: WriteOutcome,
+    /// Ids of the requests this write answers, including any it overtook.
+    pub requests: Vec<u64>,
+}
+
+struct Job {
+    code: u8,
+    value: u16,
+    action: Action,
+    requests: Vec<u64>,
+}
+
+/// Owns the write queue for one display. Writes to the same feature that
+/// pile up while the monitor is busy collapse into the latest one.
+pub struct DisplayWorker {
+    sender: Sender<Job>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl DisplayWorker {
+    pub fn spawn(
+        writer: Writer,
+        display: Display,
+        completions: Sender<Completion>,
+        in_flight: Arc<AtomicUsize>,
+        repaint: Option<egui::Context>,
+    ) -> Self {
+        let (sender, jobs) = mpsc::channel();
+        let counter = in_flight.clone();
+
+        thread::spawn(move || run(writer, display, jobs, completions, counter, repaint));
+
+        Self { sender, in_flight }
+    }
+
+    pub fn submit(&self, code: u8, value: u16, action: Action) {
+        self.queue(Job {
+            code,
+            value,
+            action,
+            requests: Vec::new(),
+        });
+    }
+
+    /// Like [`DisplayWorker::submit`], with an id to find the write's
+    /// completion by, even if a newer write overtakes it.
+    pub fn submit_request(&self, code: u8, value: u16, action: Action, request: u64) {
+        self.queue(Job {
+            code,
+            value,
+            action,
+            requests: vec![request],
+        });
+    }
+
+    fn queue(&self, job: Job) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        if self.sender.send(job).is_err() {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+fn run(
+    mut writer: Writer,
+    display: Display,
+    jobs: Receiver<Job>,
+    completions: Sender<Completion>,
+    in_flight: Arc<AtomicUsize>,
+    repaint: Option<egui::Context>,
+) {
+    let mut queue: Vec<Job> = Vec::new();
+
+    loop {
+        if queue.is_empty() {
+            match jobs.recv() {
+                Ok(job) => queue.push(job),
+                // The app dropped its handle
+                Err(_) => return,
+            }
+        }
+
+        // Pick up everything that arrived while the last write was running
+        while let Ok(mut job) = jobs.try_recv() {
+            match queue.iter_mut().find(|queued| queued.code == job.code) {
+                Some(queued) => {
+                    job.requests.append(&mut queued.requests);
+                    *queued = job;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                }
+                None => queue.push(job),
+            }
+        }
+
+        let job = queue.remove(0);
+        let outcome = writer(&display, job.code, job.value);
+        let _ = completions.send(Completion {
+            display_id: display.id.clone(),
+            code: job.code,
+            value: job.value,
+            action: job.action,
+            outcome,
+            requests: job.requests,
+        });
+
+        // Only count the job as done once its completion can be received
+        in_flight.fetch_sub(1, Ordering::SeqCst);
+        if let Some(ctx) = &repaint {
+            ctx.request_repaint();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ScriptedRunner;
+    use crate::ddc::WritePolicy;
+
+    fn display() -> Display {
+        Display {
+            id: "1".into(),
+            name: "DELL U2715H".into(),
+            drm_connector: "card1-DP-1".into(),
+            i2c_bus: None,
+            backlight: None,
+            edid: Default::default(),
+            key: "card1-DP-1".into(),
+            capabilities: None,
+        }
+    }
+
+    fn wait(in_flight: &AtomicUsize) {
+        while in_flight.load(Ordering::SeqCst) > 0 {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_rapid_writes_coalesce() {
+        let runner = Arc::new(ScriptedRunner::new());
+        for value in 0..=100 {
+            runner.set(
+                &format!("ddcutil setvcp 10 {} --display 1", value),
+                Ok(String::new()),
+            );
+        }
+        let ddc = Ddc::new(runner.clone(), false).with_policy(WritePolicy {
+            verify: false,
+            ..WritePolicy::immediate()
+        });
+        let (tx, rx) = mpsc::channel();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let worker = DisplayWorker::spawn(ddc_writer(ddc), display(), tx, in_flight.clone(), None);
+
+        for value in 0..=100 {
+            let action = Action::Slider {
+                label: "brightness".into(),
+                value: VcpValue::new(value, 100),
+            };
+            worker.submit(0x10, value, action);
+        }
+        wait(&in_flight);
+
+        let completions: Vec<Completion> = rx.try_iter().collect();
+        assert!(!completions.is_empty());
+        assert_eq!(completions.len(), runner.calls().len());
+        // Whatever got skipped, the last value always lands
+        assert_eq!(completions.last().map(|c| c.value), Some(100));
+        assert_eq!(
+            runner.calls().last().map(String::as_str),
+            Some("ddcutil setvcp 10 100 --display 1")
+        );
+        assert!(
+            completions
+                .iter()
+                .all(|c| c.outcome == WriteOutcome::Applied)
+        );
+    }
+
+    #[test]
+    fn test_overtaken_requests_follow_the_newer_write() {
+        let (started, has_started) = mpsc::channel();
+        let (release, released) = mpsc::channel::<()>();
+        let writer: Writer = Box::new(move |_, _, _| {
+            let _ = started.send(());
+            let _ = released.recv();
+            WriteOutcome::Applied
+        });
+        let (tx, rx) = mpsc::channel();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let worker = DisplayWorker::spawn(writer, display(), tx, in_flight.clone(), None);
+        let action = |value| Action::Slider {
+            label: "brightness".into(),
+            value: VcpValue::new(value, 100),
+        };
+
+        worker.submit_request(0x10, 10, action(10), 1);
+        has_started.recv().unwrap();
+        // Both queue up behind the write in progress, and the second wins
+        worker.submit_request(0x10, 20, action(20), 2);
+        worker.submit_request(0x10, 30, action(30), 3);
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        wait(&in_flight);
+
+        let completions: Vec<(u16, Vec<u64>)> =
+            rx.try_iter().map(|c| (c.value, c.requests)).collect();
+        assert_eq!(completions, [(10, vec![1]), (30, vec![3, 2])]);
+    }
+}